@@ -21,8 +21,21 @@ logoutLink.addEventListener("click", (e) => {
     });
 });
 
+// The auth cookie is short-lived, so when it has expired we try once to
+// rotate the refresh token with auth-service before giving up.
+const fetchProtected = () => fetch('/protected').then(response => {
+    if (response.status !== 401) {
+        return response;
+    }
+
+    return fetch(logoutLink.dataset.refreshHref, {
+        method: 'POST',
+        credentials: 'include', // This will include cookies in the request
+    }).then(refreshResponse => refreshResponse.ok ? fetch('/protected') : response);
+});
+
 (() => {
-    fetch('/protected').then(response => {
+    fetchProtected().then(response => {
         if (response.ok) {
             loginLink.style.display = "none";
             logoutLink.style.display = "block";
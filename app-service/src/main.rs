@@ -29,6 +29,7 @@ async fn main() {
 struct IndexTemplate {
     login_link: String,
     logout_link: String,
+    refresh_link: String,
 }
 
 async fn root() -> impl IntoResponse {
@@ -38,10 +39,12 @@ async fn root() -> impl IntoResponse {
     }
     let login_link = format!("http://{}:3000", address);
     let logout_link = format!("http://{}:3000/logout", address);
+    let refresh_link = format!("http://{}:3000/refresh", address);
 
     let template = IndexTemplate {
         login_link,
         logout_link,
+        refresh_link,
     };
     Html(template.render().unwrap())
 }
@@ -32,6 +32,17 @@ signupLoginLink.addEventListener("click", (e) => {
 
 // -----------------------------------------------------
 
+// The authorization endpoint sends users here to log in and expects them back.
+// Only /authorize is allowed as a target, so the parameter can not redirect elsewhere.
+function returnToAuthorization() {
+    const returnTo = new URLSearchParams(window.location.search).get("return_to");
+    if (returnTo !== null && returnTo.startsWith("/authorize?")) {
+        window.location.assign(returnTo);
+        return true;
+    }
+    return false;
+}
+
 const loginForm = document.getElementById("login-form");
 const loginButton = document.getElementById("login-form-submit");
 const loginErrAlter = document.getElementById("login-err-alert");
@@ -53,6 +64,9 @@ loginButton.addEventListener("click", (e) => {
             TwoFAForm.email.value = email;
             response.json().then(data => {
                 TwoFAForm.login_attempt_id.value = data.loginAttemptId;
+                TwoFAForm.email_code.placeholder = data.twoFAMethod === "totp"
+                    ? "Code from your authenticator app"
+                    : "Code sent to your email";
             });
 
             loginForm.email.value = "";
@@ -66,6 +80,9 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
+            if (returnToAuthorization()) {
+                return;
+            }
             alert("You have successfully logged in.");
         } else {
             response.json().then(data => {
@@ -104,7 +121,13 @@ signupButton.addEventListener("click", (e) => {
             signupForm.password.value = "";
             signupForm.twoFA.checked = false;
             signupErrAlter.style.display = "none";
-            alert("You have successfully created a user.");
+            response.json().then(data => {
+                let message = "You have successfully created a user. Check your email for a link to verify your address before logging in.";
+                if (data.recoveryCodes) {
+                    message += "\n\nSave these recovery codes somewhere safe, each one can be used once if you lose access to your 2FA method:\n" + data.recoveryCodes.join("\n");
+                }
+                alert(message);
+            });
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
@@ -145,6 +168,9 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
+            if (returnToAuthorization()) {
+                return;
+            }
             alert("You have successfully logged in.");
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
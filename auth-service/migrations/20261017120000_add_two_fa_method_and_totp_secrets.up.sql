@@ -0,0 +1,14 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN two_fa_method TEXT NOT NULL DEFAULT 'none'
+    CHECK (two_fa_method IN ('none', 'email', 'totp'));
+
+UPDATE users SET two_fa_method = 'email' WHERE requires_2fa;
+
+ALTER TABLE users DROP COLUMN requires_2fa;
+
+-- Secrets are stored AES-256-GCM encrypted, as nonce followed by ciphertext
+CREATE TABLE IF NOT EXISTS totp_secrets(
+   email TEXT NOT NULL PRIMARY KEY REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE,
+   secret BYTEA,
+   pending_secret BYTEA
+);
@@ -0,0 +1,5 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS clients(
+   client_id TEXT NOT NULL PRIMARY KEY,
+   client_secret_hash TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Stamped into auth tokens, bumping it invalidates every token issued before
+ALTER TABLE users ADD COLUMN token_version BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Add down migration script here
+ALTER TABLE totp_secrets DROP CONSTRAINT totp_secrets_email_fkey;
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_email_fkey;
+
+ALTER TABLE users DROP CONSTRAINT users_email_key;
+ALTER TABLE users DROP CONSTRAINT users_pkey;
+ALTER TABLE users ADD PRIMARY KEY (email);
+ALTER TABLE users DROP COLUMN IF EXISTS id;
+
+ALTER TABLE totp_secrets ADD CONSTRAINT totp_secrets_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
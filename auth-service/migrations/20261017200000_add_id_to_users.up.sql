@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- Users are identified by a surrogate id, so their email address can change.
+-- The email stays unique and tables keyed by it follow changes through ON UPDATE CASCADE.
+ALTER TABLE users ADD COLUMN id UUID NOT NULL DEFAULT gen_random_uuid();
+
+ALTER TABLE totp_secrets DROP CONSTRAINT totp_secrets_email_fkey;
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_email_fkey;
+
+ALTER TABLE users DROP CONSTRAINT users_pkey;
+ALTER TABLE users ADD PRIMARY KEY (id);
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+
+ALTER TABLE totp_secrets ADD CONSTRAINT totp_secrets_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_email_fkey
+    FOREIGN KEY (email) REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE;
@@ -0,0 +1,9 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS passkey_credentials;
+
+-- Passkey users fall back to emailed codes
+UPDATE users SET two_fa_method = 'email' WHERE two_fa_method = 'passkey';
+
+ALTER TABLE users DROP CONSTRAINT users_two_fa_method_check;
+ALTER TABLE users ADD CONSTRAINT users_two_fa_method_check
+    CHECK (two_fa_method IN ('none', 'email', 'totp'));
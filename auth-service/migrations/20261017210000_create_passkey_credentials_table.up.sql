@@ -0,0 +1,14 @@
+-- Add up migration script here
+ALTER TABLE users DROP CONSTRAINT users_two_fa_method_check;
+ALTER TABLE users ADD CONSTRAINT users_two_fa_method_check
+    CHECK (two_fa_method IN ('none', 'email', 'totp', 'passkey'));
+
+-- Credential ids are base64url encoded, public keys are uncompressed P-256 points
+CREATE TABLE IF NOT EXISTS passkey_credentials(
+   credential_id TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users(email) ON UPDATE CASCADE ON DELETE CASCADE,
+   public_key BYTEA NOT NULL,
+   sign_count BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS passkey_credentials_email_idx ON passkey_credentials(email);
@@ -0,0 +1,9 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS oauth_consents;
+
+-- Without the kind every remaining client would be treated as a service
+DELETE FROM clients WHERE kind = 'application';
+ALTER TABLE clients DROP COLUMN kind;
+ALTER TABLE clients DROP COLUMN redirect_uris;
+ALTER TABLE clients DROP COLUMN name;
+ALTER TABLE clients ALTER COLUMN client_secret_hash SET NOT NULL;
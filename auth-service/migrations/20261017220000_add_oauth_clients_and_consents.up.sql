@@ -0,0 +1,23 @@
+-- Add up migration script here
+-- Clients of the authorization code flow register where codes may be sent. Public
+-- clients (SPAs, mobile apps) have no secret and rely on PKCE alone.
+ALTER TABLE clients ALTER COLUMN client_secret_hash DROP NOT NULL;
+ALTER TABLE clients ADD COLUMN name TEXT;
+UPDATE clients SET name = client_id;
+ALTER TABLE clients ALTER COLUMN name SET NOT NULL;
+ALTER TABLE clients ADD COLUMN redirect_uris TEXT[] NOT NULL DEFAULT '{}';
+
+-- Only service clients, configured through AUTH_CLIENTS, may introspect and revoke
+-- tokens. All clients registered so far are services, new ones have to say which they are.
+ALTER TABLE clients ADD COLUMN kind TEXT NOT NULL DEFAULT 'service'
+    CHECK (kind IN ('service', 'application'));
+ALTER TABLE clients ALTER COLUMN kind DROP DEFAULT;
+
+-- Scopes each user allowed each client, so they are only asked once
+CREATE TABLE IF NOT EXISTS oauth_consents(
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   client_id TEXT NOT NULL REFERENCES clients(client_id) ON DELETE CASCADE,
+   scope TEXT NOT NULL,
+   granted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+   PRIMARY KEY (user_id, client_id)
+);
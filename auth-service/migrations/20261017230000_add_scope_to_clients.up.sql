@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Scopes a backend client may request for itself with the client credentials grant
+ALTER TABLE clients ADD COLUMN scope TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- Accounts at external OpenID Connect providers linked to users, by the provider's subject
+CREATE TABLE IF NOT EXISTS federated_identities(
+   provider TEXT NOT NULL,
+   subject TEXT NOT NULL,
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   linked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+   PRIMARY KEY (provider, subject)
+);
@@ -0,0 +1,24 @@
+-- Add up migration script here
+-- Roles and the permissions they allow are managed here rather than through the API.
+-- The first admin is granted directly, e.g.
+--   INSERT INTO user_roles (user_id, role) SELECT id, 'admin' FROM users WHERE email = '...';
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions(
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   permission TEXT NOT NULL,
+   PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE IF NOT EXISTS user_roles(
+   user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   granted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+   PRIMARY KEY (user_id, role)
+);
+
+INSERT INTO roles (name) VALUES ('admin') ON CONFLICT DO NOTHING;
+INSERT INTO role_permissions (role, permission) VALUES ('admin', 'roles:manage'), ('admin', 'clients:manage')
+    ON CONFLICT DO NOTHING;
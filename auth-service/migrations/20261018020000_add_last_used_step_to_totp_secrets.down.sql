@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE totp_secrets DROP COLUMN last_used_step;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Time step of the last accepted code, codes from that step or earlier are refused
+ALTER TABLE totp_secrets ADD COLUMN last_used_step BIGINT;
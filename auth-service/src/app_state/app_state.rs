@@ -1,4 +1,25 @@
 use crate::domain::*;
+use crate::services::data_stores::{
+    hashmap_authorization_code_store::HashmapAuthorizationCodeStore,
+    hashmap_client_store::HashmapClientStore,
+    hashmap_consent_store::HashmapConsentStore,
+    hashmap_email_change_token_store::HashmapEmailChangeTokenStore,
+    hashmap_email_verification_token_store::HashmapEmailVerificationTokenStore,
+    hashmap_federated_identity_store::HashmapFederatedIdentityStore,
+    hashmap_federated_login_store::HashmapFederatedLoginStore,
+    hashmap_login_attempt_store::HashmapLoginAttemptStore,
+    hashmap_magic_link_token_store::HashmapMagicLinkTokenStore,
+    hashmap_passkey_challenge_store::HashmapPasskeyChallengeStore,
+    hashmap_passkey_store::HashmapPasskeyStore,
+    hashmap_password_reset_token_store::HashmapPasswordResetTokenStore,
+    hashmap_recovery_code_store::HashmapRecoveryCodeStore,
+    hashmap_refresh_token_store::HashmapRefreshTokenStore,
+    hashmap_role_store::HashmapRoleStore,
+    hashmap_session_store::HashmapSessionStore,
+    hashmap_totp_secret_store::HashmapTotpSecretStore,
+    hashmap_two_fa_code_store::HashmapTwoFACodeStore,
+};
+use crate::services::oidc_provider_client::OidcProviderClient;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -7,13 +28,54 @@ pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<RwLock<dyn EmailClient + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+pub type EmailVerificationTokenStoreType =
+    Arc<RwLock<dyn EmailVerificationTokenStore + Send + Sync>>;
+pub type EmailChangeTokenStoreType = Arc<RwLock<dyn EmailChangeTokenStore + Send + Sync>>;
+pub type MagicLinkTokenStoreType = Arc<RwLock<dyn MagicLinkTokenStore + Send + Sync>>;
+pub type TotpSecretStoreType = Arc<RwLock<dyn TotpSecretStore + Send + Sync>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+pub type LoginAttemptStoreType = Arc<RwLock<dyn LoginAttemptStore + Send + Sync>>;
+pub type ClientStoreType = Arc<RwLock<dyn ClientStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type PasskeyStoreType = Arc<RwLock<dyn PasskeyStore + Send + Sync>>;
+pub type PasskeyChallengeStoreType = Arc<RwLock<dyn PasskeyChallengeStore + Send + Sync>>;
+pub type AuthorizationCodeStoreType = Arc<RwLock<dyn AuthorizationCodeStore + Send + Sync>>;
+pub type ConsentStoreType = Arc<RwLock<dyn ConsentStore + Send + Sync>>;
+pub type FederatedLoginStoreType = Arc<RwLock<dyn FederatedLoginStore + Send + Sync>>;
+pub type FederatedIdentityStoreType = Arc<RwLock<dyn FederatedIdentityStore + Send + Sync>>;
+pub type RoleStoreType = Arc<RwLock<dyn RoleStore + Send + Sync>>;
+pub type IdentityProviderClientType = Arc<RwLock<dyn IdentityProviderClient + Send + Sync>>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    // Emailed codes confirming sensitive actions of logged in users, apart from login codes
+    pub reauthentication_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub email_change_token_store: EmailChangeTokenStoreType,
+    pub magic_link_token_store: MagicLinkTokenStoreType,
+    pub totp_secret_store: TotpSecretStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
+    pub client_store: ClientStoreType,
+    pub session_store: SessionStoreType,
+    pub passkey_store: PasskeyStoreType,
+    pub passkey_challenge_store: PasskeyChallengeStoreType,
+    pub authorization_code_store: AuthorizationCodeStoreType,
+    pub consent_store: ConsentStoreType,
+    pub federated_login_store: FederatedLoginStoreType,
+    pub federated_identity_store: FederatedIdentityStoreType,
+    pub role_store: RoleStoreType,
+    // External OpenID Connect providers users can log in with, none unless configured
+    pub identity_providers: Arc<Vec<IdentityProvider>>,
+    pub identity_provider_client: IdentityProviderClientType,
 }
 
 impl AppState {
@@ -28,6 +90,153 @@ impl AppState {
             banned_token_store,
             two_fa_code_store,
             email_client,
+            // Stores added after the core four default to in-memory implementations
+            // and are swapped for persistent ones with the `with_*` methods below.
+            reauthentication_code_store: Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            refresh_token_store: Arc::new(RwLock::new(HashmapRefreshTokenStore::default())),
+            password_reset_token_store: Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default())),
+            email_verification_token_store: Arc::new(RwLock::new(
+                HashmapEmailVerificationTokenStore::default(),
+            )),
+            email_change_token_store: Arc::new(RwLock::new(HashmapEmailChangeTokenStore::default())),
+            magic_link_token_store: Arc::new(RwLock::new(HashmapMagicLinkTokenStore::default())),
+            totp_secret_store: Arc::new(RwLock::new(HashmapTotpSecretStore::default())),
+            recovery_code_store: Arc::new(RwLock::new(HashmapRecoveryCodeStore::default())),
+            login_attempt_store: Arc::new(RwLock::new(HashmapLoginAttemptStore::default())),
+            client_store: Arc::new(RwLock::new(HashmapClientStore::default())),
+            session_store: Arc::new(RwLock::new(HashmapSessionStore::default())),
+            passkey_store: Arc::new(RwLock::new(HashmapPasskeyStore::default())),
+            passkey_challenge_store: Arc::new(RwLock::new(HashmapPasskeyChallengeStore::default())),
+            authorization_code_store: Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default())),
+            consent_store: Arc::new(RwLock::new(HashmapConsentStore::default())),
+            federated_login_store: Arc::new(RwLock::new(HashmapFederatedLoginStore::default())),
+            federated_identity_store: Arc::new(RwLock::new(HashmapFederatedIdentityStore::default())),
+            role_store: Arc::new(RwLock::new(HashmapRoleStore::default())),
+            identity_providers: Arc::new(Vec::new()),
+            identity_provider_client: Arc::new(RwLock::new(OidcProviderClient::new(
+                reqwest::Client::new(),
+            ))),
         }
     }
+
+    pub fn with_reauthentication_code_store(
+        mut self,
+        reauthentication_code_store: TwoFACodeStoreType,
+    ) -> Self {
+        self.reauthentication_code_store = reauthentication_code_store;
+        self
+    }
+
+    pub fn with_refresh_token_store(mut self, refresh_token_store: RefreshTokenStoreType) -> Self {
+        self.refresh_token_store = refresh_token_store;
+        self
+    }
+
+    pub fn with_password_reset_token_store(
+        mut self,
+        password_reset_token_store: PasswordResetTokenStoreType,
+    ) -> Self {
+        self.password_reset_token_store = password_reset_token_store;
+        self
+    }
+
+    pub fn with_email_verification_token_store(
+        mut self,
+        email_verification_token_store: EmailVerificationTokenStoreType,
+    ) -> Self {
+        self.email_verification_token_store = email_verification_token_store;
+        self
+    }
+
+    pub fn with_email_change_token_store(
+        mut self,
+        email_change_token_store: EmailChangeTokenStoreType,
+    ) -> Self {
+        self.email_change_token_store = email_change_token_store;
+        self
+    }
+
+    pub fn with_magic_link_token_store(mut self, magic_link_token_store: MagicLinkTokenStoreType) -> Self {
+        self.magic_link_token_store = magic_link_token_store;
+        self
+    }
+
+    pub fn with_totp_secret_store(mut self, totp_secret_store: TotpSecretStoreType) -> Self {
+        self.totp_secret_store = totp_secret_store;
+        self
+    }
+
+    pub fn with_recovery_code_store(mut self, recovery_code_store: RecoveryCodeStoreType) -> Self {
+        self.recovery_code_store = recovery_code_store;
+        self
+    }
+
+    pub fn with_login_attempt_store(mut self, login_attempt_store: LoginAttemptStoreType) -> Self {
+        self.login_attempt_store = login_attempt_store;
+        self
+    }
+
+    pub fn with_client_store(mut self, client_store: ClientStoreType) -> Self {
+        self.client_store = client_store;
+        self
+    }
+
+    pub fn with_session_store(mut self, session_store: SessionStoreType) -> Self {
+        self.session_store = session_store;
+        self
+    }
+
+    pub fn with_passkey_store(mut self, passkey_store: PasskeyStoreType) -> Self {
+        self.passkey_store = passkey_store;
+        self
+    }
+
+    pub fn with_passkey_challenge_store(
+        mut self,
+        passkey_challenge_store: PasskeyChallengeStoreType,
+    ) -> Self {
+        self.passkey_challenge_store = passkey_challenge_store;
+        self
+    }
+
+    pub fn with_authorization_code_store(
+        mut self,
+        authorization_code_store: AuthorizationCodeStoreType,
+    ) -> Self {
+        self.authorization_code_store = authorization_code_store;
+        self
+    }
+
+    pub fn with_consent_store(mut self, consent_store: ConsentStoreType) -> Self {
+        self.consent_store = consent_store;
+        self
+    }
+
+    pub fn with_federated_login_store(mut self, federated_login_store: FederatedLoginStoreType) -> Self {
+        self.federated_login_store = federated_login_store;
+        self
+    }
+
+    pub fn with_federated_identity_store(
+        mut self,
+        federated_identity_store: FederatedIdentityStoreType,
+    ) -> Self {
+        self.federated_identity_store = federated_identity_store;
+        self
+    }
+
+    pub fn with_role_store(mut self, role_store: RoleStoreType) -> Self {
+        self.role_store = role_store;
+        self
+    }
+
+    pub fn with_identity_providers(
+        mut self,
+        identity_providers: Vec<IdentityProvider>,
+        identity_provider_client: IdentityProviderClientType,
+    ) -> Self {
+        self.identity_providers = Arc::new(identity_providers);
+        self.identity_provider_client = identity_provider_client;
+        self
+    }
 }
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+// How a user proved who they are when a session was started, named after the values
+// registered for the `amr` claim of ID tokens (RFC 8176). The first step of a two step
+// login is not tracked past the login attempt, so those sessions record the second
+// factor and `MultiFactor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthenticationMethod {
+    #[serde(rename = "pwd")]
+    Password,
+    // Codes and links sent by email, TOTP codes and recovery codes
+    #[serde(rename = "otp")]
+    OneTimeCode,
+    #[serde(rename = "hwk")]
+    Passkey,
+    #[serde(rename = "mfa")]
+    MultiFactor,
+    // Logins at an external identity provider, which RFC 8176 has no value for
+    #[serde(rename = "fed")]
+    Federated,
+}
+
+impl AuthenticationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Password => "pwd",
+            Self::OneTimeCode => "otp",
+            Self::Passkey => "hwk",
+            Self::MultiFactor => "mfa",
+            Self::Federated => "fed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_as_amr_value() {
+        for method in [
+            AuthenticationMethod::Password,
+            AuthenticationMethod::OneTimeCode,
+            AuthenticationMethod::Passkey,
+            AuthenticationMethod::MultiFactor,
+            AuthenticationMethod::Federated,
+        ] {
+            let serialized = serde_json::to_string(&method).unwrap();
+            assert_eq!(serialized, format!("\"{}\"", method.as_str()));
+            assert_eq!(serde_json::from_str::<AuthenticationMethod>(&serialized).unwrap(), method);
+        }
+    }
+}
@@ -1,9 +1,17 @@
 use uuid::Uuid;
 use color_eyre::eyre::{eyre, Context, Report, Result};
-use crate::domain::User;
+use crate::domain::{User, UserId};
+use crate::domain::{AuthenticationMethod, OAuthClient, PasskeyCeremony, PasskeyChallenge, PasskeyCredential, Permission, PkceChallenge, RedirectUri, Role, Scope, TotpSecret};
 use crate::domain::Password;
 use crate::domain::Email;
+use crate::utils::constants::{
+    LOGIN_LOCKOUT_BASE_SECONDS, LOGIN_LOCKOUT_MAX_SECONDS, LOGIN_LOCKOUT_THRESHOLD,
+};
 use secrecy::{ExposeSecret, Secret};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
 
 use thiserror::Error;
 
@@ -11,7 +19,20 @@ use thiserror::Error;
 pub trait UserStore {
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError>;
+    // Also bumps the token version, so tokens issued with the old password stop working
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Saves the settings of a user fetched from the store, such as their 2FA method. The
+    // password and email address have their own methods, changing them has further effects.
+    async fn update_user(&mut self, user: &User) -> Result<(), UserStoreError>;
+    // Auth tokens carry the version they were issued with and are rejected once it was bumped
+    async fn get_token_version(&self, id: &UserId) -> Result<u64, UserStoreError>;
+    async fn bump_token_version(&mut self, id: &UserId) -> Result<(), UserStoreError>;
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Fails with `UserAlreadyExists` if the new address belongs to another user
+    async fn update_email(&mut self, email: &Email, new_email: Email) -> Result<(), UserStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -39,8 +60,9 @@ impl PartialEq for UserStoreError {
 }
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn store_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
-    async fn check_banned_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    // Tokens are banned by their `jti` claim
+    async fn store_token(&mut self, jti: String) -> Result<(), BannedTokenStoreError>;
+    async fn check_banned_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -49,6 +71,948 @@ pub enum BannedTokenStoreError {
     UnexpectedError(#[source] Report),
 }
 
+// This trait represents the interface all concrete refresh token stores should implement
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &TokenFamilyId) -> Result<(), RefreshTokenStoreError>;
+    async fn is_family_revoked(&self, family_id: &TokenFamilyId) -> Result<bool, RefreshTokenStoreError>;
+    async fn revoke_user_families(&mut self, user_id: &UserId) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    RefreshTokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RefreshTokenNotFound, Self::RefreshTokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Everything we need to know about an issued refresh token. Every token issued
+// from the same login shares a family id, so reuse of a rotated token can revoke them all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub user_id: UserId,
+    pub family_id: TokenFamilyId,
+    pub used: bool,
+}
+
+impl RefreshTokenRecord {
+    pub fn new(user_id: UserId, family_id: TokenFamilyId) -> Self {
+        Self {
+            user_id,
+            family_id,
+            used: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RefreshToken(Secret<String>);
+
+impl RefreshToken {
+    pub fn parse(token: String) -> Result<Self> {
+        if is_opaque_token(&token) {
+            Ok(Self(Secret::new(token)))
+        } else {
+            Err(eyre!("Invalid refresh token"))
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        RefreshToken(Secret::new(generate_opaque_token()))
+    }
+}
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for RefreshToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// Opaque random string, the token itself carries no information
+fn generate_opaque_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(OPAQUE_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+fn is_opaque_token(token: &str) -> bool {
+    token.len() == OPAQUE_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+const OPAQUE_TOKEN_LENGTH: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TokenFamilyId(String);
+
+impl TokenFamilyId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = uuid::Uuid::parse_str(&id).wrap_err("Invalid token family id")?;
+        Ok(Self(parsed_id.to_string()))
+    }
+}
+
+impl Default for TokenFamilyId {
+    fn default() -> Self {
+        TokenFamilyId(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for TokenFamilyId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+
+// This trait represents the interface all concrete password reset token stores should implement
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    async fn get_email(&self, token: &PasswordResetToken) -> Result<Email, PasswordResetTokenStoreError>;
+    async fn remove_token(&mut self, token: &PasswordResetToken) -> Result<(), PasswordResetTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Password reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PasswordResetToken(Secret<String>);
+
+impl PasswordResetToken {
+    pub fn parse(token: String) -> Result<Self> {
+        if is_opaque_token(&token) {
+            Ok(Self(Secret::new(token)))
+        } else {
+            Err(eyre!("Invalid password reset token"))
+        }
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        PasswordResetToken(Secret::new(generate_opaque_token()))
+    }
+}
+
+impl PartialEq for PasswordResetToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for PasswordResetToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// This trait represents the interface all concrete magic link token stores should implement
+#[async_trait::async_trait]
+pub trait MagicLinkTokenStore {
+    async fn add_token(&mut self, email: Email, token: MagicLinkToken) -> Result<(), MagicLinkTokenStoreError>;
+    async fn get_email(&self, token: &MagicLinkToken) -> Result<Email, MagicLinkTokenStoreError>;
+    async fn remove_token(&mut self, token: &MagicLinkToken) -> Result<(), MagicLinkTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum MagicLinkTokenStoreError {
+    #[error("Magic link token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for MagicLinkTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MagicLinkToken(Secret<String>);
+
+impl MagicLinkToken {
+    pub fn parse(token: String) -> Result<Self> {
+        if is_opaque_token(&token) {
+            Ok(Self(Secret::new(token)))
+        } else {
+            Err(eyre!("Invalid magic link token"))
+        }
+    }
+}
+
+impl Default for MagicLinkToken {
+    fn default() -> Self {
+        MagicLinkToken(Secret::new(generate_opaque_token()))
+    }
+}
+
+impl PartialEq for MagicLinkToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for MagicLinkToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// This trait represents the interface all concrete email verification token stores should implement
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+    async fn get_email(&self, token: &EmailVerificationToken) -> Result<Email, EmailVerificationTokenStoreError>;
+    async fn remove_token(&mut self, token: &EmailVerificationToken) -> Result<(), EmailVerificationTokenStoreError>;
+    // Records that a verification email is about to be resent, failing with
+    // `ResendThrottled` if one was already resent within the throttle window
+    async fn register_resend(&mut self, email: &Email) -> Result<(), EmailVerificationTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailVerificationTokenStoreError {
+    #[error("Email verification token not found")]
+    TokenNotFound,
+    #[error("Verification email resent too recently")]
+    ResendThrottled,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailVerificationTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::ResendThrottled, Self::ResendThrottled)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmailVerificationToken(Secret<String>);
+
+impl EmailVerificationToken {
+    pub fn parse(token: String) -> Result<Self> {
+        if is_opaque_token(&token) {
+            Ok(Self(Secret::new(token)))
+        } else {
+            Err(eyre!("Invalid email verification token"))
+        }
+    }
+}
+
+impl Default for EmailVerificationToken {
+    fn default() -> Self {
+        EmailVerificationToken(Secret::new(generate_opaque_token()))
+    }
+}
+
+impl PartialEq for EmailVerificationToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for EmailVerificationToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// This trait represents the interface all concrete email change token stores should implement.
+// A token confirms moving a user to the new address it was sent to.
+#[async_trait::async_trait]
+pub trait EmailChangeTokenStore {
+    async fn add_token(
+        &mut self,
+        user_id: UserId,
+        new_email: Email,
+        token: EmailChangeToken,
+    ) -> Result<(), EmailChangeTokenStoreError>;
+    async fn get_change(&self, token: &EmailChangeToken) -> Result<(UserId, Email), EmailChangeTokenStoreError>;
+    async fn remove_token(&mut self, token: &EmailChangeToken) -> Result<(), EmailChangeTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailChangeTokenStoreError {
+    #[error("Email change token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailChangeTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmailChangeToken(Secret<String>);
+
+impl EmailChangeToken {
+    pub fn parse(token: String) -> Result<Self> {
+        if is_opaque_token(&token) {
+            Ok(Self(Secret::new(token)))
+        } else {
+            Err(eyre!("Invalid email change token"))
+        }
+    }
+}
+
+impl Default for EmailChangeToken {
+    fn default() -> Self {
+        EmailChangeToken(Secret::new(generate_opaque_token()))
+    }
+}
+
+impl PartialEq for EmailChangeToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for EmailChangeToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// This trait represents the interface all concrete TOTP secret stores should implement.
+// A newly enrolled secret stays pending until the user proves their authenticator
+// app produces valid codes for it, so re-enrolling never breaks an active secret.
+#[async_trait::async_trait]
+pub trait TotpSecretStore {
+    async fn set_pending_secret(&mut self, email: &Email, secret: TotpSecret) -> Result<(), TotpSecretStoreError>;
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, TotpSecretStoreError>;
+    async fn confirm_pending_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError>;
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, TotpSecretStoreError>;
+    // Records the time step of an accepted code. Codes from that step or an earlier one
+    // are refused from then on, so a code can only be used once (RFC 6238 section 5.2).
+    async fn record_used_step(&mut self, email: &Email, step: u64) -> Result<(), TotpSecretStoreError>;
+    // Removes both the active and the pending secret, if there are any
+    async fn delete_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum TotpSecretStoreError {
+    #[error("TOTP secret not found")]
+    SecretNotFound,
+    #[error("TOTP code already used")]
+    StepAlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TotpSecretStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SecretNotFound, Self::SecretNotFound)
+                | (Self::StepAlreadyUsed, Self::StepAlreadyUsed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete recovery code stores should implement
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    // Replaces any existing codes for the user with the given set
+    async fn replace_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), RecoveryCodeStoreError>;
+    // Checks the code and burns it so it can not be used again
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Recovery code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Recovery codes are shown to users as two groups of five characters, e.g. `K7QXM-4TZPA`.
+// Characters that are easy to confuse when written down (0/O, 1/I) are left out.
+#[derive(Clone, Debug)]
+pub struct RecoveryCode(Secret<String>);
+
+impl RecoveryCode {
+    pub fn parse(code: String) -> Result<Self> {
+        let normalized: String = code
+            .trim()
+            .chars()
+            .filter(|c| *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        if normalized.len() == RECOVERY_CODE_LENGTH
+            && normalized.bytes().all(|b| RECOVERY_CODE_ALPHABET.contains(&b))
+        {
+            let (first, second) = normalized.split_at(RECOVERY_CODE_LENGTH / 2);
+            Ok(Self(Secret::new(format!("{}-{}", first, second))))
+        } else {
+            Err(eyre!("Invalid recovery code"))
+        }
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let code: String = (0..RECOVERY_CODE_LENGTH)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect();
+        let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+        RecoveryCode(Secret::new(format!("{}-{}", first, second)))
+    }
+}
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for RecoveryCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+const RECOVERY_CODE_LENGTH: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[async_trait::async_trait]
+pub trait LoginAttemptStore {
+    // Fails with `LockedOut` while the user is locked after too many failed logins
+    async fn check_lockout(&self, user_id: &UserId) -> Result<(), LoginAttemptStoreError>;
+    // Counts a failed login, locking the user once enough of them have piled up
+    async fn record_failure(&mut self, user_id: &UserId) -> Result<(), LoginAttemptStoreError>;
+    async fn reset(&mut self, user_id: &UserId) -> Result<(), LoginAttemptStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum LoginAttemptStoreError {
+    #[error("Too many failed login attempts")]
+    LockedOut { retry_after_seconds: u64 },
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for LoginAttemptStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::LockedOut { retry_after_seconds: a },
+                Self::LockedOut { retry_after_seconds: b },
+            ) => a == b,
+            (Self::UnexpectedError(_), Self::UnexpectedError(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+// Lockout to apply after the given number of consecutive failed logins. There is none
+// below the threshold, past it the lockout doubles with every further failure.
+pub fn login_lockout_seconds(failed_attempts: u64) -> Option<u64> {
+    if failed_attempts < LOGIN_LOCKOUT_THRESHOLD {
+        return None;
+    }
+    let doublings = (failed_attempts - LOGIN_LOCKOUT_THRESHOLD).min(u32::MAX as u64) as u32;
+    let seconds = LOGIN_LOCKOUT_BASE_SECONDS.saturating_mul(2u64.saturating_pow(doublings));
+    Some(seconds.min(LOGIN_LOCKOUT_MAX_SECONDS))
+}
+
+// This trait represents the interface all concrete passkey stores should implement
+#[async_trait::async_trait]
+pub trait PasskeyStore {
+    async fn add_credential(&mut self, credential: PasskeyCredential) -> Result<(), PasskeyStoreError>;
+    async fn get_credential(&self, credential_id: &str) -> Result<PasskeyCredential, PasskeyStoreError>;
+    async fn get_user_credentials(&self, email: &Email) -> Result<Vec<PasskeyCredential>, PasskeyStoreError>;
+    async fn update_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), PasskeyStoreError>;
+    async fn delete_user_credentials(&mut self, email: &Email) -> Result<(), PasskeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PasskeyStoreError {
+    #[error("Passkey already registered")]
+    CredentialAlreadyExists,
+    #[error("Passkey not found")]
+    CredentialNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasskeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CredentialAlreadyExists, Self::CredentialAlreadyExists)
+                | (Self::CredentialNotFound, Self::CredentialNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete passkey challenge stores should implement.
+// Challenges are short lived and each one completes a single ceremony.
+#[async_trait::async_trait]
+pub trait PasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: PasskeyChallenge,
+        ceremony: PasskeyCeremony,
+    ) -> Result<(), PasskeyChallengeStoreError>;
+    async fn get_ceremony(&self, challenge: &PasskeyChallenge) -> Result<PasskeyCeremony, PasskeyChallengeStoreError>;
+    async fn remove_challenge(&mut self, challenge: &PasskeyChallenge) -> Result<(), PasskeyChallengeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PasskeyChallengeStoreError {
+    #[error("Passkey challenge not found")]
+    ChallengeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasskeyChallengeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ChallengeNotFound, Self::ChallengeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete session stores should implement.
+// A session is one login, its id is shared with the refresh token family it started.
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, session_id: &TokenFamilyId) -> Result<Session, SessionStoreError>;
+    async fn get_user_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError>;
+    // Records activity on the session, fails with `SessionNotFound` once it was revoked
+    async fn touch_session(&mut self, session_id: &TokenFamilyId) -> Result<(), SessionStoreError>;
+    async fn remove_session(&mut self, session_id: &TokenFamilyId) -> Result<(), SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Timestamps are unix seconds, like the ones in auth tokens
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: TokenFamilyId,
+    pub user_id: UserId,
+    pub created_at: i64,
+    pub last_seen: i64,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    // Set for sessions of OAuth clients, whose tokens carry the granted scope
+    pub client_id: Option<String>,
+    pub scope: Option<Scope>,
+    pub methods: Vec<AuthenticationMethod>,
+}
+
+impl Session {
+    pub fn new(user_id: UserId, user_agent: Option<String>, ip_address: Option<String>) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id: TokenFamilyId::default(),
+            user_id,
+            created_at: now,
+            last_seen: now,
+            user_agent,
+            ip_address,
+            client_id: None,
+            scope: None,
+            methods: Vec::new(),
+        }
+    }
+
+    pub fn with_methods(mut self, methods: &[AuthenticationMethod]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    pub fn with_client(mut self, client_id: String, scope: Scope) -> Self {
+        self.client_id = Some(client_id);
+        self.scope = Some(scope);
+        self
+    }
+}
+
+// Registry of the services allowed to call the token endpoints meant for backends and
+// of the applications using the authorization code flow. Client secrets are only ever
+// kept as hashes.
+#[async_trait::async_trait]
+pub trait ClientStore {
+    // Adds the service client, or replaces its secret and scope if it is already registered
+    async fn register_client(
+        &mut self,
+        client_id: &str,
+        secret: Secret<String>,
+        scope: Scope,
+    ) -> Result<(), ClientStoreError>;
+    // Adds an OAuth client, confidential clients come with a secret
+    async fn add_client(&mut self, client: OAuthClient, secret: Option<Secret<String>>) -> Result<(), ClientStoreError>;
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, ClientStoreError>;
+    // Fails with `InvalidCredentials` for unknown clients and wrong secrets alike
+    async fn validate_client(&self, client_id: &str, secret: &Secret<String>) -> Result<(), ClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ClientStoreError {
+    #[error("Client already exists")]
+    ClientAlreadyExists,
+    #[error("Client not found")]
+    ClientNotFound,
+    #[error("Invalid client credentials")]
+    InvalidCredentials,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientAlreadyExists, Self::ClientAlreadyExists)
+                | (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Authorization codes waiting to be exchanged at the token endpoint
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore {
+    async fn add_code(&mut self, code: AuthorizationCode, grant: AuthorizationGrant) -> Result<(), AuthorizationCodeStoreError>;
+    async fn get_grant(&self, code: &AuthorizationCode) -> Result<AuthorizationGrant, AuthorizationCodeStoreError>;
+    async fn remove_code(&mut self, code: &AuthorizationCode) -> Result<(), AuthorizationCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorizationCodeStoreError {
+    #[error("Authorization code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuthorizationCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// What the user authorized, checked again when the client exchanges the code
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationGrant {
+    pub client_id: String,
+    pub redirect_uri: RedirectUri,
+    pub user_id: UserId,
+    pub scope: Scope,
+    pub code_challenge: PkceChallenge,
+    // Passed on to the ID token, along with how and when the user logged in
+    pub nonce: Option<String>,
+    pub auth_time: i64,
+    pub methods: Vec<AuthenticationMethod>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizationCode(Secret<String>);
+
+impl AuthorizationCode {
+    pub fn parse(code: String) -> Result<Self> {
+        if is_opaque_token(&code) {
+            Ok(Self(Secret::new(code)))
+        } else {
+            Err(eyre!("Invalid authorization code"))
+        }
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        AuthorizationCode(Secret::new(generate_opaque_token()))
+    }
+}
+
+impl PartialEq for AuthorizationCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for AuthorizationCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// Logins started with an external identity provider, waiting for the user to come back
+// from it. Keyed by the `state` sent along with the authorization request.
+#[async_trait::async_trait]
+pub trait FederatedLoginStore {
+    async fn add_login(&mut self, state: FederatedLoginState, login: FederatedLogin) -> Result<(), FederatedLoginStoreError>;
+    async fn get_login(&self, state: &FederatedLoginState) -> Result<FederatedLogin, FederatedLoginStoreError>;
+    async fn remove_login(&mut self, state: &FederatedLoginState) -> Result<(), FederatedLoginStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum FederatedLoginStoreError {
+    #[error("Federated login not found")]
+    LoginNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for FederatedLoginStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::LoginNotFound, Self::LoginNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// What the callback needs to finish a login: the provider it was started with, the nonce
+// its ID token has to carry and the PKCE verifier for the code exchange
+#[derive(Debug, Clone)]
+pub struct FederatedLogin {
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: Secret<String>,
+}
+
+impl FederatedLogin {
+    pub fn new(provider: String) -> Self {
+        Self {
+            provider,
+            nonce: generate_opaque_token(),
+            code_verifier: Secret::new(generate_opaque_token()),
+        }
+    }
+
+    // S256 code challenge sent with the authorization request (RFC 7636 section 4.2)
+    pub fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.code_verifier.expose_secret().as_bytes()))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FederatedLoginState(Secret<String>);
+
+impl FederatedLoginState {
+    pub fn parse(state: String) -> Result<Self> {
+        if is_opaque_token(&state) {
+            Ok(Self(Secret::new(state)))
+        } else {
+            Err(eyre!("Invalid federated login state"))
+        }
+    }
+}
+
+impl Default for FederatedLoginState {
+    fn default() -> Self {
+        FederatedLoginState(Secret::new(generate_opaque_token()))
+    }
+}
+
+impl PartialEq for FederatedLoginState {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for FederatedLoginState {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// Accounts at external identity providers linked to users, so later logins find the user
+// even after their email address changed on either side
+#[async_trait::async_trait]
+pub trait FederatedIdentityStore {
+    async fn link_identity(&mut self, provider: &str, subject: &str, user_id: &UserId) -> Result<(), FederatedIdentityStoreError>;
+    async fn get_user_id(&self, provider: &str, subject: &str) -> Result<UserId, FederatedIdentityStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum FederatedIdentityStoreError {
+    #[error("Federated identity not found")]
+    IdentityNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for FederatedIdentityStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::IdentityNotFound, Self::IdentityNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Roles granted to users and the permissions each role allows. Roles themselves are
+// defined in the database, only granting them to users goes through the API.
+#[async_trait::async_trait]
+pub trait RoleStore {
+    // Sorted by name, empty for users without roles
+    async fn get_roles(&self, user_id: &UserId) -> Result<Vec<Role>, RoleStoreError>;
+    async fn grant_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError>;
+    // Revoking a role the user does not hold is not an error
+    async fn revoke_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError>;
+    async fn has_permission(&self, roles: &[Role], permission: &Permission) -> Result<bool, RoleStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RoleStoreError {
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RoleStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RoleNotFound, Self::RoleNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Scopes each user allowed each OAuth client, so they are not asked again
+#[async_trait::async_trait]
+pub trait ConsentStore {
+    // Replaces whatever the user allowed the client before
+    async fn grant_consent(&mut self, user_id: &UserId, client_id: &str, scope: Scope) -> Result<(), ConsentStoreError>;
+    async fn get_consent(&self, user_id: &UserId, client_id: &str) -> Result<Scope, ConsentStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ConsentStoreError {
+    #[error("Consent not found")]
+    ConsentNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ConsentStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ConsentNotFound, Self::ConsentNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Client secrets are opaque random strings like the other tokens handed out
+pub fn generate_client_secret() -> Secret<String> {
+    Secret::new(generate_opaque_token())
+}
 
 // This trait represents the interface all concrete 2FA code stores should implement
 #[async_trait::async_trait]
@@ -64,12 +1028,17 @@ pub trait TwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    // Counts a wrong code against the pending login attempt. Once too many have been
+    // tried the attempt is discarded and `TooManyAttempts` is returned.
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
 }
 
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login Attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("Too many failed 2FA attempts")]
+    TooManyAttempts,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -80,6 +1049,7 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+                | (Self::TooManyAttempts, Self::TooManyAttempts)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -118,9 +1088,8 @@ pub struct TwoFACode(Secret<String>);
 
 impl TwoFACode {
     pub fn parse(code: String) -> Result<Self> { // Updated!
-        let code_as_u32 = code.parse::<u32>().wrap_err("Invalid 2FA code")?; // Updated!
-
-        if (100_000..=999_999).contains(&code_as_u32) {
+        // Codes may start with a zero, both generated ones and authenticator app ones
+        if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
             Ok(Self(Secret::new(code)))
         } else {
             Err(eyre!("Invalid 2FA code")) // Updated!
@@ -13,6 +13,38 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("Too many failed login attempts")]
+    AccountLocked { retry_after_seconds: u64 },
+    #[error("Too many failed 2FA attempts")]
+    TooMany2FAAttempts,
+    #[error("Invalid client credentials")]
+    InvalidClient,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Password reused")]
+    PasswordReused,
+    #[error("Invalid authorization grant")]
+    InvalidGrant,
+    #[error("Unsupported grant type")]
+    UnsupportedGrantType,
+    #[error("Invalid scope")]
+    InvalidScope,
+    #[error("Unknown client or unregistered redirect URI")]
+    InvalidClientRedirect,
+    #[error("Unknown identity provider")]
+    UnknownIdentityProvider,
+    #[error("Login with identity provider failed")]
+    FederatedLoginFailed,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Unknown role")]
+    UnknownRole,
+    #[error("Missing permission")]
+    MissingPermission,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
\ No newline at end of file
@@ -0,0 +1,276 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::Secret;
+use serde::Deserialize;
+use url::Url;
+
+use super::Password;
+use crate::utils::constants::JWT_LEEWAY_SECONDS;
+
+// An external OpenID Connect provider users can log in with, e.g. a corporate IdP.
+// Its endpoints are discovered from the issuer, this service is registered with it as
+// a confidential client.
+#[derive(Debug, Clone)]
+pub struct IdentityProvider {
+    // Identifies the provider in login URLs
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Secret<String>,
+}
+
+impl IdentityProvider {
+    pub fn new(
+        name: String,
+        issuer: String,
+        client_id: String,
+        client_secret: Secret<String>,
+    ) -> Result<Self> {
+        let valid_name = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid_name {
+            return Err(eyre!("Provider names may only contain a-z, 0-9 and -"));
+        }
+        // Only https, or plain http to a provider on the same machine
+        let url = Url::parse(&issuer).map_err(|_| eyre!("Invalid issuer URL"))?;
+        let secure = match url.scheme() {
+            "https" => url.has_host(),
+            "http" => matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")),
+            _ => false,
+        };
+        if !secure {
+            return Err(eyre!("Issuer must be an https URL"));
+        }
+        if client_id.is_empty() {
+            return Err(eyre!("Client id must not be empty"));
+        }
+        Ok(Self {
+            name,
+            issuer,
+            client_id,
+            client_secret,
+        })
+    }
+}
+
+// The parts of a provider's metadata (OpenID Connect Discovery section 3) a login needs
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+// Who the provider says logged in
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalIdentity {
+    // Stable id of the user at the provider, unlike their email address
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+}
+
+#[derive(Deserialize)]
+struct ExternalIdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+// Providers publish public keys only, so tokens with a MAC are never accepted
+const ID_TOKEN_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+// Validates an ID token from the provider's token endpoint (OpenID Connect Core section
+// 3.1.3.7): signed with one of its keys, issued by it for this service and bound to the
+// login this service started through the nonce
+pub fn verify_id_token(
+    id_token: &str,
+    provider: &IdentityProvider,
+    jwks: &JwkSet,
+    nonce: &str,
+) -> Result<ExternalIdentity> {
+    let header = decode_header(id_token).wrap_err("failed to decode ID token header")?;
+    if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+        return Err(eyre!("unsupported ID token algorithm"));
+    }
+    // Providers with a single key may leave out the kid
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or(eyre!("ID token was signed with an unknown key"))?;
+    let key = DecodingKey::from_jwk(jwk).wrap_err("invalid provider key")?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[provider.issuer.as_str()]);
+    validation.set_audience(&[provider.client_id.as_str()]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    validation.leeway = JWT_LEEWAY_SECONDS;
+
+    let claims = decode::<ExternalIdTokenClaims>(id_token, &key, &validation)
+        .wrap_err("invalid ID token")?
+        .claims;
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(eyre!("ID token nonce does not match"));
+    }
+
+    Ok(ExternalIdentity {
+        subject: claims.sub,
+        email: claims.email,
+        email_verified: claims.email_verified,
+    })
+}
+
+// Users provisioned from a provider never see this, they can set a password of their own
+// through a password reset
+pub fn generate_random_password() -> Password {
+    let password: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect();
+    Password(Secret::new(password))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use ring::{rand::SystemRandom, signature::Ed25519KeyPair};
+    use serde_json::json;
+
+    use super::*;
+    use crate::utils::signing_key::SigningKey;
+
+    const ISSUER: &str = "https://idp.example.com";
+    const CLIENT_ID: &str = "auth-service";
+    const NONCE: &str = "n-0S6_WzA2Mj";
+
+    fn provider() -> IdentityProvider {
+        IdentityProvider::new(
+            "corp".to_owned(),
+            ISSUER.to_owned(),
+            CLIENT_ID.to_owned(),
+            Secret::new("secret".to_owned()),
+        )
+        .unwrap()
+    }
+
+    fn provider_key() -> SigningKey {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+        SigningKey::from_pem(pem.as_bytes()).unwrap()
+    }
+
+    fn jwks(key: &SigningKey) -> JwkSet {
+        JwkSet {
+            keys: vec![key.jwk().unwrap().clone()],
+        }
+    }
+
+    fn claims() -> serde_json::Value {
+        let now = Utc::now().timestamp();
+        json!({
+            "iss": ISSUER,
+            "sub": "248289761001",
+            "aud": CLIENT_ID,
+            "exp": now + 300,
+            "iat": now,
+            "nonce": NONCE,
+            "email": "jane@example.com",
+            "email_verified": true,
+        })
+    }
+
+    fn sign(key: &SigningKey, claims: &serde_json::Value) -> String {
+        encode(&key.header(), claims, key.encoding_key()).unwrap()
+    }
+
+    #[test]
+    fn test_verify_id_token() {
+        let key = provider_key();
+        let identity = verify_id_token(&sign(&key, &claims()), &provider(), &jwks(&key), NONCE).unwrap();
+        assert_eq!(
+            identity,
+            ExternalIdentity {
+                subject: "248289761001".to_owned(),
+                email: Some("jane@example.com".to_owned()),
+                email_verified: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_id_token_rejects_wrong_nonce() {
+        let key = provider_key();
+        let token = sign(&key, &claims());
+        assert!(verify_id_token(&token, &provider(), &jwks(&key), "other-nonce").is_err());
+    }
+
+    #[test]
+    fn test_verify_id_token_rejects_wrong_issuer_and_audience() {
+        let key = provider_key();
+        for (claim, value) in [("iss", "https://evil.example.com"), ("aud", "other-client")] {
+            let mut claims = claims();
+            claims[claim] = json!(value);
+            let token = sign(&key, &claims);
+            assert!(verify_id_token(&token, &provider(), &jwks(&key), NONCE).is_err(), "{}", claim);
+        }
+    }
+
+    #[test]
+    fn test_verify_id_token_rejects_expired_token() {
+        let key = provider_key();
+        let mut claims = claims();
+        claims["exp"] = json!(Utc::now().timestamp() - 2 * JWT_LEEWAY_SECONDS as i64);
+        let token = sign(&key, &claims);
+        assert!(verify_id_token(&token, &provider(), &jwks(&key), NONCE).is_err());
+    }
+
+    #[test]
+    fn test_verify_id_token_rejects_unknown_key() {
+        let token = sign(&provider_key(), &claims());
+        assert!(verify_id_token(&token, &provider(), &jwks(&provider_key()), NONCE).is_err());
+    }
+
+    #[test]
+    fn test_verify_id_token_rejects_shared_secret() {
+        let key = provider_key();
+        let token = encode(&Header::default(), &claims(), &EncodingKey::from_secret(b"secret")).unwrap();
+        assert!(verify_id_token(&token, &provider(), &jwks(&key), NONCE).is_err());
+    }
+
+    #[test]
+    fn test_provider_requires_valid_name_and_issuer() {
+        let new = |name: &str, issuer: &str| {
+            IdentityProvider::new(
+                name.to_owned(),
+                issuer.to_owned(),
+                CLIENT_ID.to_owned(),
+                Secret::new("secret".to_owned()),
+            )
+        };
+        assert!(new("corp", ISSUER).is_ok());
+        assert!(new("corp", "http://127.0.0.1:8080").is_ok());
+        assert!(new("Corp", ISSUER).is_err());
+        assert!(new("corp/idp", ISSUER).is_err());
+        assert!(new("", ISSUER).is_err());
+        assert!(new("corp", "http://idp.example.com").is_err());
+        assert!(new("corp", "not a url").is_err());
+    }
+}
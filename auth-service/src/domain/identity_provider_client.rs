@@ -0,0 +1,21 @@
+use color_eyre::eyre::Result;
+use jsonwebtoken::jwk::JwkSet;
+use secrecy::Secret;
+
+use super::{IdentityProvider, ProviderMetadata};
+
+// Calls made to an external OpenID Connect provider while a user logs in with it
+#[async_trait::async_trait]
+pub trait IdentityProviderClient {
+    async fn discover(&self, provider: &IdentityProvider) -> Result<ProviderMetadata>;
+    // Exchanges the authorization code for the provider's ID token
+    async fn exchange_code(
+        &self,
+        provider: &IdentityProvider,
+        metadata: &ProviderMetadata,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &Secret<String>,
+    ) -> Result<String>;
+    async fn get_jwks(&self, metadata: &ProviderMetadata) -> Result<JwkSet>;
+}
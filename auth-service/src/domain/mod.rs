@@ -4,6 +4,14 @@ pub mod data_stores;
 pub mod email;
 pub mod password;
 pub mod email_client;
+pub mod two_fa_method;
+pub mod authentication_method;
+pub mod totp;
+pub mod passkey;
+pub mod oauth;
+pub mod federation;
+pub mod identity_provider_client;
+pub mod role;
 
 
 pub use user::*;
@@ -11,4 +19,12 @@ pub use error::*;
 pub use email::*;
 pub use password::*;
 pub use data_stores::*;
-pub use email_client::*;
\ No newline at end of file
+pub use email_client::*;
+pub use two_fa_method::*;
+pub use authentication_method::*;
+pub use totp::*;
+pub use passkey::*;
+pub use oauth::*;
+pub use federation::*;
+pub use identity_provider_client::*;
+pub use role::*;
\ No newline at end of file
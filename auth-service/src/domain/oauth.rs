@@ -0,0 +1,273 @@
+use std::collections::BTreeSet;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Result};
+use sha2::{Digest, Sha256};
+use url::Url;
+use uuid::Uuid;
+
+// An application users log in to through the authorization code flow. Confidential
+// clients authenticate with a secret, public ones (SPAs, mobile apps) only with PKCE.
+// Backend services are confidential clients without redirect URIs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthClient {
+    pub id: String,
+    pub name: String,
+    pub redirect_uris: Vec<RedirectUri>,
+    pub confidential: bool,
+    pub kind: ClientKind,
+    // What the client may request for itself with the client credentials grant
+    pub scope: Scope,
+}
+
+impl OAuthClient {
+    pub fn new(name: String, redirect_uris: Vec<RedirectUri>, confidential: bool) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            redirect_uris,
+            confidential,
+            kind: ClientKind::Application,
+            scope: Scope::default(),
+        }
+    }
+
+    // Redirect URIs are compared as they were registered, no normalization
+    pub fn has_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|registered| registered.as_ref() == redirect_uri)
+    }
+}
+
+// Services are configured by operators and may introspect and revoke tokens and use the
+// client credentials grant. Applications registered through the API only get users'
+// consent through the authorization code flow, even when they hold a secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientKind {
+    Service,
+    Application,
+}
+
+impl ClientKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "service" => Ok(Self::Service),
+            "application" => Ok(Self::Application),
+            _ => Err(eyre!("{} is not a valid client kind", kind)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Service => "service",
+            Self::Application => "application",
+        }
+    }
+}
+
+// Where authorization codes may be sent. Only https, loopback http (RFC 8252 section 7.3)
+// and reverse domain private-use schemes of native apps (RFC 8252 section 7.1) are allowed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedirectUri(String);
+
+impl RedirectUri {
+    pub fn parse(uri: String) -> Result<Self> {
+        let url = Url::parse(&uri).map_err(|_| eyre!("Invalid redirect URI"))?;
+        if url.fragment().is_some() {
+            return Err(eyre!("Redirect URIs must not have a fragment"));
+        }
+        let allowed = match url.scheme() {
+            "https" => url.has_host(),
+            "http" => matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")),
+            scheme => scheme.contains('.'),
+        };
+        if !allowed {
+            return Err(eyre!("Redirect URI scheme not allowed"));
+        }
+        Ok(Self(uri))
+    }
+
+    // The URI with the given parameters added to whatever query it was registered with
+    pub fn with_params(&self, params: &[(&str, &str)]) -> String {
+        let mut url = Url::parse(&self.0).expect("redirect URIs are valid URLs once parsed");
+        url.query_pairs_mut().extend_pairs(params);
+        url.into()
+    }
+}
+
+impl AsRef<str> for RedirectUri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Space separated set of scopes (RFC 6749 section 3.3), kept sorted so equal sets compare equal
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scope(String);
+
+impl Scope {
+    pub fn parse(scope: &str) -> Result<Self> {
+        let tokens = scope.split(' ').filter(|token| !token.is_empty());
+        let mut scopes = BTreeSet::new();
+        for token in tokens {
+            // NQCHAR, printable ASCII except space, `"` and `\`
+            if !token.chars().all(|c| c.is_ascii_graphic() && c != '"' && c != '\\') {
+                return Err(eyre!("Invalid scope"));
+            }
+            scopes.insert(token);
+        }
+        Ok(Self(scopes.into_iter().collect::<Vec<_>>().join(" ")))
+    }
+
+    pub fn includes(&self, scope: &str) -> bool {
+        self.tokens().any(|own| own == scope)
+    }
+
+    pub fn contains(&self, other: &Scope) -> bool {
+        other.tokens().all(|token| self.tokens().any(|own| own == token))
+    }
+
+    pub fn union(&self, other: &Scope) -> Scope {
+        Self(
+            self.tokens()
+                .chain(other.tokens())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    fn tokens(&self) -> impl Iterator<Item = &str> {
+        self.0.split(' ').filter(|token| !token.is_empty())
+    }
+}
+
+impl AsRef<str> for Scope {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// PKCE code challenge (RFC 7636). Only S256 is accepted, `plain` would give the
+// code away to anyone who can see the authorization request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PkceChallenge(String);
+
+impl PkceChallenge {
+    pub fn parse(challenge: String, method: Option<&str>) -> Result<Self> {
+        if method != Some("S256") {
+            return Err(eyre!("Unsupported code challenge method"));
+        }
+        match URL_SAFE_NO_PAD.decode(&challenge) {
+            Ok(digest) if digest.len() == 32 => Ok(Self(challenge)),
+            _ => Err(eyre!("Invalid code challenge")),
+        }
+    }
+
+    pub fn verify(&self, verifier: &str) -> bool {
+        let well_formed = (43..=128).contains(&verifier.len())
+            && verifier
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'));
+        well_formed && URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) == self.0
+    }
+}
+
+impl AsRef<str> for PkceChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From RFC 7636 appendix B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn test_pkce_challenge_verifies_matching_verifier() {
+        let challenge = PkceChallenge::parse(CHALLENGE.to_owned(), Some("S256")).unwrap();
+        assert!(challenge.verify(VERIFIER));
+        assert!(!challenge.verify(&VERIFIER.replace('d', "e")));
+        assert!(!challenge.verify("too-short"));
+    }
+
+    #[test]
+    fn test_pkce_challenge_requires_s256() {
+        assert!(PkceChallenge::parse(CHALLENGE.to_owned(), None).is_err());
+        assert!(PkceChallenge::parse(CHALLENGE.to_owned(), Some("plain")).is_err());
+        assert!(PkceChallenge::parse("not-a-digest".to_owned(), Some("S256")).is_err());
+    }
+
+    #[test]
+    fn test_redirect_uri_parse() {
+        for uri in [
+            "https://app.example.com/callback",
+            "http://localhost:8080/callback",
+            "http://127.0.0.1/callback",
+            "com.example.app:/oauth/callback",
+        ] {
+            assert!(RedirectUri::parse(uri.to_owned()).is_ok(), "{}", uri);
+        }
+        for uri in [
+            "http://app.example.com/callback",
+            "https://app.example.com/callback#fragment",
+            "javascript:alert(1)",
+            "/callback",
+        ] {
+            assert!(RedirectUri::parse(uri.to_owned()).is_err(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_client_matches_registered_redirect_uris_exactly() {
+        let client = OAuthClient::new(
+            "App".to_owned(),
+            vec![RedirectUri::parse("https://app.example.com/callback".to_owned()).unwrap()],
+            false,
+        );
+        assert!(client.has_redirect_uri("https://app.example.com/callback"));
+        assert!(!client.has_redirect_uri("https://app.example.com/callback/"));
+        assert!(!client.has_redirect_uri("https://app.example.com/callback?next=/"));
+    }
+
+    #[test]
+    fn test_client_kind_round_trips() {
+        for kind in [ClientKind::Service, ClientKind::Application] {
+            assert_eq!(ClientKind::parse(kind.as_str()).unwrap(), kind);
+        }
+        assert!(ClientKind::parse("admin").is_err());
+    }
+
+    #[test]
+    fn test_redirect_uri_with_params() {
+        let uri = RedirectUri::parse("https://app.example.com/callback?tenant=1".to_owned()).unwrap();
+        assert_eq!(
+            uri.with_params(&[("code", "abc"), ("state", "a b&c")]),
+            "https://app.example.com/callback?tenant=1&code=abc&state=a+b%26c"
+        );
+    }
+
+    #[test]
+    fn test_scope_is_normalized() {
+        let scope = Scope::parse("write  read read").unwrap();
+        assert_eq!(scope.as_ref(), "read write");
+        assert_eq!(Scope::parse("").unwrap(), Scope::default());
+        assert!(Scope::parse("read \"write\"").is_err());
+    }
+
+    #[test]
+    fn test_scope_contains_and_union() {
+        let read = Scope::parse("read").unwrap();
+        let read_write = Scope::parse("read write").unwrap();
+        assert!(read_write.contains(&read));
+        assert!(!read.contains(&read_write));
+        assert!(read.contains(&Scope::default()));
+        assert!(read_write.includes("write"));
+        assert!(!read.includes("write"));
+        assert_eq!(read.union(&Scope::parse("write").unwrap()), read_write);
+    }
+}
@@ -0,0 +1,541 @@
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use color_eyre::eyre::{eyre, Result};
+use rand::Rng;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::Email;
+
+// A passkey registered by a user, identified by the id its authenticator gave it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasskeyCredential {
+    // Base64url encoded, as it appears in WebAuthn responses
+    pub id: String,
+    pub email: Email,
+    // Uncompressed P-256 point of the key the authenticator signs assertions with
+    pub public_key: Vec<u8>,
+    // Signature counter reported by the authenticator, used to detect cloned keys
+    pub sign_count: u32,
+}
+
+// Random value an authenticator has to sign, base64url encoded as clients echo it back
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PasskeyChallenge(String);
+
+impl PasskeyChallenge {
+    pub fn parse(challenge: String) -> Result<Self> {
+        match URL_SAFE_NO_PAD.decode(&challenge) {
+            Ok(bytes) if bytes.len() == CHALLENGE_BYTES => Ok(Self(challenge)),
+            _ => Err(eyre!("Invalid passkey challenge")),
+        }
+    }
+}
+
+impl Default for PasskeyChallenge {
+    fn default() -> Self {
+        let bytes: [u8; CHALLENGE_BYTES] = rand::thread_rng().gen();
+        Self(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for PasskeyChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// What a challenge was issued for, so a response can only complete that ceremony
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasskeyCeremony {
+    Registration { email: Email },
+    // Passwordless login, the credential tells who is logging in
+    Login,
+    SecondFactor { email: Email },
+}
+
+// Response of `navigator.credentials.create()`, serialized the way `toJSON()` does it
+#[derive(Deserialize)]
+pub struct PasskeyRegistrationCredential {
+    pub id: String,
+    pub response: PasskeyAttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyAttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+// Response of `navigator.credentials.get()`, serialized the way `toJSON()` does it
+#[derive(Deserialize)]
+pub struct PasskeyAssertionCredential {
+    pub id: String,
+    pub response: PasskeyAssertionResponse,
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyAssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+impl PasskeyRegistrationCredential {
+    // The signed challenge tells which ceremony this response belongs to
+    pub fn challenge(&self) -> Result<PasskeyChallenge> {
+        client_challenge(&self.response.client_data_json)
+    }
+
+    // Checks the attestation against the challenge and returns the new credential.
+    // Only "none" attestation is accepted, which is what the creation options ask for.
+    pub fn verify(
+        &self,
+        challenge: &PasskeyChallenge,
+        email: &Email,
+        rp_id: &str,
+        origin: &str,
+    ) -> Result<PasskeyCredential> {
+        let client_data_json = decode(&self.response.client_data_json)?;
+        verify_client_data(&client_data_json, "webauthn.create", challenge, origin)?;
+
+        let attestation: Value = ciborium::de::from_reader(decode(&self.response.attestation_object)?.as_slice())
+            .map_err(|_| eyre!("Attestation object is not valid CBOR"))?;
+        let attestation = attestation
+            .as_map()
+            .ok_or_else(|| eyre!("Attestation object is not a map"))?;
+        let field = |name: &str| {
+            attestation
+                .iter()
+                .find(|(key, _)| key.as_text() == Some(name))
+                .map(|(_, value)| value)
+        };
+        if field("fmt").and_then(Value::as_text) != Some("none") {
+            return Err(eyre!("Only none attestation is supported"));
+        }
+        let auth_data = field("authData")
+            .and_then(Value::as_bytes)
+            .ok_or_else(|| eyre!("Attestation object has no authenticator data"))?;
+
+        let auth_data = AuthenticatorData::parse(auth_data)?;
+        auth_data.verify(rp_id)?;
+        let (credential_id, public_key) = auth_data
+            .attested_credential
+            .ok_or_else(|| eyre!("Authenticator data has no attested credential"))?;
+
+        let id = URL_SAFE_NO_PAD.encode(credential_id);
+        if id != self.id {
+            return Err(eyre!("Credential id does not match the attested credential"));
+        }
+
+        Ok(PasskeyCredential {
+            id,
+            email: email.clone(),
+            public_key,
+            sign_count: auth_data.sign_count,
+        })
+    }
+}
+
+impl PasskeyAssertionCredential {
+    // The signed challenge tells which ceremony this response belongs to
+    pub fn challenge(&self) -> Result<PasskeyChallenge> {
+        client_challenge(&self.response.client_data_json)
+    }
+
+    // Id of the user the authenticator holds this credential for, if it said so
+    pub fn user_handle(&self) -> Result<Option<String>> {
+        match &self.response.user_handle {
+            Some(user_handle) => Ok(Some(
+                String::from_utf8(decode(user_handle)?).map_err(|_| eyre!("Invalid user handle"))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // Checks the signature with the stored public key and returns the new sign count
+    pub fn verify(
+        &self,
+        challenge: &PasskeyChallenge,
+        credential: &PasskeyCredential,
+        rp_id: &str,
+        origin: &str,
+    ) -> Result<u32> {
+        if self.id != credential.id {
+            return Err(eyre!("Assertion is for another credential"));
+        }
+
+        let client_data_json = decode(&self.response.client_data_json)?;
+        verify_client_data(&client_data_json, "webauthn.get", challenge, origin)?;
+
+        let raw_auth_data = decode(&self.response.authenticator_data)?;
+        let auth_data = AuthenticatorData::parse(&raw_auth_data)?;
+        auth_data.verify(rp_id)?;
+
+        let mut signed_data = raw_auth_data;
+        signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &credential.public_key)
+            .verify(&signed_data, &decode(&self.response.signature)?)
+            .map_err(|_| eyre!("Invalid assertion signature"))?;
+
+        // Authenticators without a counter always report zero, any other
+        // counter has to grow or the key may have been cloned
+        if (auth_data.sign_count != 0 || credential.sign_count != 0)
+            && auth_data.sign_count <= credential.sign_count
+        {
+            return Err(eyre!("Sign count did not increase"));
+        }
+
+        Ok(auth_data.sign_count)
+    }
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+fn client_challenge(client_data_json: &str) -> Result<PasskeyChallenge> {
+    let client_data: ClientData = serde_json::from_slice(&decode(client_data_json)?)
+        .map_err(|_| eyre!("Client data is not valid JSON"))?;
+    PasskeyChallenge::parse(client_data.challenge)
+}
+
+fn verify_client_data(
+    client_data_json: &[u8],
+    ceremony_type: &str,
+    challenge: &PasskeyChallenge,
+    origin: &str,
+) -> Result<()> {
+    let client_data: ClientData = serde_json::from_slice(client_data_json)
+        .map_err(|_| eyre!("Client data is not valid JSON"))?;
+    if client_data.ceremony_type != ceremony_type {
+        return Err(eyre!("Client data is for another ceremony"));
+    }
+    if client_data.challenge != challenge.as_ref() {
+        return Err(eyre!("Client data is for another challenge"));
+    }
+    if client_data.origin != origin {
+        return Err(eyre!("Client data comes from another origin"));
+    }
+    Ok(())
+}
+
+struct AuthenticatorData {
+    rp_id_hash: Vec<u8>,
+    flags: u8,
+    sign_count: u32,
+    // Credential id and public key, only present when a credential is created
+    attested_credential: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl AuthenticatorData {
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 37 {
+            return Err(eyre!("Authenticator data is too short"));
+        }
+        let flags = bytes[32];
+        let sign_count = u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]);
+
+        let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+            // 16 bytes of authenticator model id come first, then the length of the credential id
+            let rest = bytes.get(53..).ok_or_else(|| eyre!("Attested credential is too short"))?;
+            if rest.len() < 2 {
+                return Err(eyre!("Attested credential is too short"));
+            }
+            let id_length = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+            let credential_id = rest
+                .get(2..2 + id_length)
+                .ok_or_else(|| eyre!("Attested credential is too short"))?
+                .to_vec();
+            // Extensions may follow the key, so only the first CBOR item is read
+            let public_key: Value = ciborium::de::from_reader(Cursor::new(&rest[2 + id_length..]))
+                .map_err(|_| eyre!("Credential public key is not valid CBOR"))?;
+            Some((credential_id, parse_cose_key(&public_key)?))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            rp_id_hash: bytes[..32].to_vec(),
+            flags,
+            sign_count,
+            attested_credential,
+        })
+    }
+
+    // Passkeys replace a password, so the user must have been verified as well as present
+    fn verify(&self, rp_id: &str) -> Result<()> {
+        if self.rp_id_hash != Sha256::digest(rp_id.as_bytes()).as_slice() {
+            return Err(eyre!("Authenticator data is for another relying party"));
+        }
+        if self.flags & FLAG_USER_PRESENT == 0 || self.flags & FLAG_USER_VERIFIED == 0 {
+            return Err(eyre!("User was not present and verified"));
+        }
+        Ok(())
+    }
+}
+
+// Only ES256 keys are supported, which every passkey provider offers
+fn parse_cose_key(key: &Value) -> Result<Vec<u8>> {
+    let key = key.as_map().ok_or_else(|| eyre!("COSE key is not a map"))?;
+    let field = |label: i128| {
+        key.iter()
+            .find(|(key, _)| key.as_integer().map(i128::from) == Some(label))
+            .map(|(_, value)| value)
+    };
+    let integer = |label: i128| field(label).and_then(Value::as_integer).map(i128::from);
+
+    if integer(COSE_KEY_TYPE) != Some(COSE_KEY_TYPE_EC2)
+        || integer(COSE_ALGORITHM) != Some(COSE_ALGORITHM_ES256)
+        || integer(COSE_CURVE) != Some(COSE_CURVE_P256)
+    {
+        return Err(eyre!("Only ES256 credentials are supported"));
+    }
+
+    let coordinate = |label: i128| {
+        field(label)
+            .and_then(Value::as_bytes)
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| eyre!("Invalid COSE key coordinate"))
+    };
+    let mut public_key = vec![0x04];
+    public_key.extend_from_slice(coordinate(COSE_X)?);
+    public_key.extend_from_slice(coordinate(COSE_Y)?);
+    Ok(public_key)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| eyre!("Value is not base64url encoded"))
+}
+
+const CHALLENGE_BYTES: usize = 32;
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+const COSE_KEY_TYPE: i128 = 1;
+const COSE_ALGORITHM: i128 = 3;
+const COSE_CURVE: i128 = -1;
+const COSE_X: i128 = -2;
+const COSE_Y: i128 = -3;
+const COSE_KEY_TYPE_EC2: i128 = 2;
+const COSE_ALGORITHM_ES256: i128 = -7;
+const COSE_CURVE_P256: i128 = 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+    };
+    use secrecy::Secret;
+    use serde_json::json;
+
+    const RP_ID: &str = "localhost";
+    const ORIGIN: &str = "http://localhost:3000";
+
+    // Minimal software authenticator holding a single ES256 key
+    struct Authenticator {
+        key_pair: EcdsaKeyPair,
+        credential_id: Vec<u8>,
+    }
+
+    impl Authenticator {
+        fn new() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+            let key_pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+            Self {
+                key_pair,
+                credential_id: rand::thread_rng().gen::<[u8; 16]>().to_vec(),
+            }
+        }
+
+        fn auth_data(&self, flags: u8, sign_count: u32) -> Vec<u8> {
+            let mut auth_data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+            auth_data.push(flags);
+            auth_data.extend_from_slice(&sign_count.to_be_bytes());
+            auth_data
+        }
+
+        fn client_data(ceremony_type: &str, challenge: &PasskeyChallenge) -> Vec<u8> {
+            json!({ "type": ceremony_type, "challenge": challenge.as_ref(), "origin": ORIGIN })
+                .to_string()
+                .into_bytes()
+        }
+
+        fn create(&self, challenge: &PasskeyChallenge) -> PasskeyRegistrationCredential {
+            let public_key = self.key_pair.public_key().as_ref();
+            let cose_key = Value::Map(vec![
+                (Value::from(1), Value::from(2)),
+                (Value::from(3), Value::from(-7)),
+                (Value::from(-1), Value::from(1)),
+                (Value::from(-2), Value::Bytes(public_key[1..33].to_vec())),
+                (Value::from(-3), Value::Bytes(public_key[33..].to_vec())),
+            ]);
+
+            let mut auth_data = self.auth_data(0x45, 0);
+            auth_data.extend_from_slice(&[0; 16]);
+            auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            auth_data.extend_from_slice(&self.credential_id);
+            ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+            let attestation = Value::Map(vec![
+                (Value::from("fmt"), Value::from("none")),
+                (Value::from("attStmt"), Value::Map(vec![])),
+                (Value::from("authData"), Value::Bytes(auth_data)),
+            ]);
+            let mut attestation_object = Vec::new();
+            ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+
+            PasskeyRegistrationCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: PasskeyAttestationResponse {
+                    client_data_json: URL_SAFE_NO_PAD
+                        .encode(Self::client_data("webauthn.create", challenge)),
+                    attestation_object: URL_SAFE_NO_PAD.encode(attestation_object),
+                },
+            }
+        }
+
+        fn get(&self, challenge: &PasskeyChallenge, sign_count: u32) -> PasskeyAssertionCredential {
+            let client_data_json = Self::client_data("webauthn.get", challenge);
+            let auth_data = self.auth_data(0x05, sign_count);
+            let mut signed_data = auth_data.clone();
+            signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+            let signature = self.key_pair.sign(&SystemRandom::new(), &signed_data).unwrap();
+
+            PasskeyAssertionCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: PasskeyAssertionResponse {
+                    client_data_json: URL_SAFE_NO_PAD.encode(client_data_json),
+                    authenticator_data: URL_SAFE_NO_PAD.encode(auth_data),
+                    signature: URL_SAFE_NO_PAD.encode(signature.as_ref()),
+                    user_handle: None,
+                },
+            }
+        }
+    }
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    fn register(authenticator: &Authenticator) -> PasskeyCredential {
+        let challenge = PasskeyChallenge::default();
+        authenticator
+            .create(&challenge)
+            .verify(&challenge, &email(), RP_ID, ORIGIN)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_challenge_round_trips() {
+        let challenge = PasskeyChallenge::default();
+        assert_eq!(PasskeyChallenge::parse(challenge.as_ref().to_owned()).unwrap(), challenge);
+        assert!(PasskeyChallenge::parse("short".to_owned()).is_err());
+    }
+
+    #[test]
+    fn test_verify_registration() {
+        let authenticator = Authenticator::new();
+        let challenge = PasskeyChallenge::default();
+        let response = authenticator.create(&challenge);
+        assert_eq!(response.challenge().unwrap(), challenge);
+
+        let credential = response.verify(&challenge, &email(), RP_ID, ORIGIN).unwrap();
+        assert_eq!(credential.id, URL_SAFE_NO_PAD.encode(&authenticator.credential_id));
+        assert_eq!(credential.public_key, authenticator.key_pair.public_key().as_ref());
+        assert_eq!(credential.sign_count, 0);
+    }
+
+    #[test]
+    fn test_verify_registration_rejects_other_challenge_or_origin() {
+        let authenticator = Authenticator::new();
+        let challenge = PasskeyChallenge::default();
+        let response = authenticator.create(&challenge);
+
+        assert!(response
+            .verify(&PasskeyChallenge::default(), &email(), RP_ID, ORIGIN)
+            .is_err());
+        assert!(response
+            .verify(&challenge, &email(), RP_ID, "https://evil.example")
+            .is_err());
+        assert!(response
+            .verify(&challenge, &email(), "evil.example", ORIGIN)
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion() {
+        let authenticator = Authenticator::new();
+        let credential = register(&authenticator);
+
+        let challenge = PasskeyChallenge::default();
+        let response = authenticator.get(&challenge, 1);
+        assert_eq!(response.challenge().unwrap(), challenge);
+        assert_eq!(response.verify(&challenge, &credential, RP_ID, ORIGIN).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_verify_assertion_rejects_other_key() {
+        let credential = register(&Authenticator::new());
+        let other = Authenticator {
+            credential_id: URL_SAFE_NO_PAD.decode(&credential.id).unwrap(),
+            ..Authenticator::new()
+        };
+
+        let challenge = PasskeyChallenge::default();
+        let response = other.get(&challenge, 1);
+        assert!(response.verify(&challenge, &credential, RP_ID, ORIGIN).is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion_rejects_registration_client_data() {
+        let authenticator = Authenticator::new();
+        let credential = register(&authenticator);
+
+        let challenge = PasskeyChallenge::default();
+        let mut response = authenticator.get(&challenge, 1);
+        response.response.client_data_json =
+            URL_SAFE_NO_PAD.encode(Authenticator::client_data("webauthn.create", &challenge));
+        assert!(response.verify(&challenge, &credential, RP_ID, ORIGIN).is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion_rejects_sign_count_that_did_not_grow() {
+        let authenticator = Authenticator::new();
+        let credential = PasskeyCredential {
+            sign_count: 5,
+            ..register(&authenticator)
+        };
+
+        let challenge = PasskeyChallenge::default();
+        let response = authenticator.get(&challenge, 5);
+        assert!(response.verify(&challenge, &credential, RP_ID, ORIGIN).is_err());
+
+        // Authenticators without a counter keep reporting zero
+        let credential = PasskeyCredential {
+            sign_count: 0,
+            ..credential
+        };
+        let response = authenticator.get(&challenge, 0);
+        assert_eq!(response.verify(&challenge, &credential, RP_ID, ORIGIN).unwrap(), 0);
+    }
+}
@@ -0,0 +1,77 @@
+use color_eyre::eyre::{eyre, Result};
+
+// Lets an admin grant and revoke roles, held by the `admin` role the migrations create
+pub const MANAGE_ROLES_PERMISSION: &str = "roles:manage";
+// Lets an admin register OAuth applications
+pub const MANAGE_CLIENTS_PERMISSION: &str = "clients:manage";
+
+// A named set of permissions users are granted. Roles and what they allow live in the
+// database, tokens only carry the names of the roles their user holds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Role(String);
+
+impl Role {
+    pub fn parse(role: String) -> Result<Self> {
+        if !is_valid_name(&role, &['-', '_']) {
+            return Err(eyre!("Role names may only contain a-z, 0-9, - and _"));
+        }
+        Ok(Self(role))
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Something a role allows, by convention `resource:action` like `roles:manage`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Permission(String);
+
+impl Permission {
+    pub fn parse(permission: String) -> Result<Self> {
+        if !is_valid_name(&permission, &['-', '_', ':']) {
+            return Err(eyre!("Permissions may only contain a-z, 0-9, -, _ and :"));
+        }
+        Ok(Self(permission))
+    }
+}
+
+impl AsRef<str> for Permission {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn is_valid_name(name: &str, separators: &[char]) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || separators.contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_role() {
+        assert!(Role::parse("admin".to_owned()).is_ok());
+        assert!(Role::parse("support-staff_2".to_owned()).is_ok());
+        assert!(Role::parse("".to_owned()).is_err());
+        assert!(Role::parse("Admin".to_owned()).is_err());
+        assert!(Role::parse("roles:manage".to_owned()).is_err());
+        assert!(Role::parse("a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_parse_permission() {
+        assert!(Permission::parse(MANAGE_ROLES_PERMISSION.to_owned()).is_ok());
+        assert!(Permission::parse("reports:read-all".to_owned()).is_ok());
+        assert!(Permission::parse("".to_owned()).is_err());
+        assert!(Permission::parse("roles manage".to_owned()).is_err());
+        assert!(Permission::parse("ROLES:MANAGE".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret as TotpRsSecret, TOTP};
+
+use super::{Email, TwoFACode};
+
+// Base32 encoded shared secret used by authenticator apps (RFC 6238)
+#[derive(Debug, Clone)]
+pub struct TotpSecret(Secret<String>);
+
+impl TotpSecret {
+    pub fn parse(secret: Secret<String>) -> Result<Self> {
+        let bytes = TotpRsSecret::Encoded(secret.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("TOTP secret is not valid base32"))?;
+        if bytes.len() < MIN_SECRET_BYTES {
+            return Err(eyre!("TOTP secret must be at least 128 bits long"));
+        }
+        Ok(Self(secret))
+    }
+
+    // URI understood by authenticator apps, usually shown to the user as a QR code
+    pub fn provisioning_uri(&self, email: &Email) -> Result<String> {
+        Ok(self.totp(email.as_ref().expose_secret().to_owned())?.get_url())
+    }
+
+    // Accepts codes from up to `skew` time steps before or after the current one,
+    // to tolerate clock drift between the server and the user's device. Returns the
+    // time step the code belongs to, so it can be refused once it has been used.
+    pub fn verify(&self, code: &TwoFACode, skew: u8) -> Result<Option<u64>> {
+        let totp = self.totp(String::new())?;
+        let current_step = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() / TIME_STEP_SECONDS;
+        let steps = current_step.saturating_sub(skew as u64)..=current_step + skew as u64;
+        Ok(steps
+            .into_iter()
+            .find(|step| totp.check(code.as_ref(), step * TIME_STEP_SECONDS)))
+    }
+
+    fn totp(&self, account_name: String) -> Result<TOTP> {
+        let bytes = TotpRsSecret::Encoded(self.0.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("TOTP secret is not valid base32"))?;
+        TOTP::new(
+            Algorithm::SHA1,
+            6,
+            0,
+            TIME_STEP_SECONDS,
+            bytes,
+            Some(TOTP_ISSUER.to_owned()),
+            account_name,
+        )
+        .map_err(|e| eyre!("Failed to build TOTP: {}", e))
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        // 160 bits, the size recommended by RFC 4226
+        let bytes: [u8; 20] = rand::thread_rng().gen();
+        let encoded = match TotpRsSecret::Raw(bytes.to_vec()).to_encoded() {
+            TotpRsSecret::Encoded(encoded) => encoded,
+            TotpRsSecret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+        };
+        TotpSecret(Secret::new(encoded))
+    }
+}
+
+impl AsRef<Secret<String>> for TotpSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+const MIN_SECRET_BYTES: usize = 16;
+const TIME_STEP_SECONDS: u64 = 30;
+const TOTP_ISSUER: &str = "Auth Service";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current_step() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() / TIME_STEP_SECONDS
+    }
+
+    fn code_at_offset(secret: &TotpSecret, offset_steps: i64) -> TwoFACode {
+        let totp = secret.totp(String::new()).unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let time = now + offset_steps * TIME_STEP_SECONDS as i64;
+        TwoFACode::parse(totp.generate(time as u64)).unwrap()
+    }
+
+    #[test]
+    fn test_default_secret_parses() {
+        let secret = TotpSecret::default();
+        assert!(TotpSecret::parse(secret.as_ref().clone()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_secrets() {
+        assert!(TotpSecret::parse(Secret::new("not base32!".to_owned())).is_err());
+        // Valid base32 but only 80 bits
+        assert!(TotpSecret::parse(Secret::new("JBSWY3DPEHPK3PXP".to_owned())).is_err());
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let secret = TotpSecret::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let uri = secret.provisioning_uri(&email).unwrap();
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref().expose_secret())));
+        assert!(uri.contains("test%40example.com"));
+    }
+
+    #[test]
+    fn test_verify_current_code() {
+        let secret = TotpSecret::default();
+        let step = current_step();
+        assert_eq!(secret.verify(&code_at_offset(&secret, 0), 0).unwrap(), Some(step));
+    }
+
+    #[test]
+    fn test_verify_respects_skew() {
+        let secret = TotpSecret::default();
+        // Two steps away is outside the window either way
+        let code = code_at_offset(&secret, -2);
+        assert_eq!(secret.verify(&code, 1).unwrap(), None);
+        assert_eq!(secret.verify(&code, 2).unwrap(), Some(current_step() - 2));
+    }
+}
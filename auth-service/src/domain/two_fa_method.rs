@@ -0,0 +1,55 @@
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+// The second factor a user has to provide after their password
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAMethod {
+    None,
+    Email,
+    Totp,
+    Passkey,
+}
+
+impl TwoFAMethod {
+    pub fn parse(method: &str) -> Result<Self> {
+        match method {
+            "none" => Ok(Self::None),
+            "email" => Ok(Self::Email),
+            "totp" => Ok(Self::Totp),
+            "passkey" => Ok(Self::Passkey),
+            _ => Err(eyre!("{} is not a valid 2FA method.", method)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Email => "email",
+            Self::Totp => "totp",
+            Self::Passkey => "passkey",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trips() {
+        for method in [
+            TwoFAMethod::None,
+            TwoFAMethod::Email,
+            TwoFAMethod::Totp,
+            TwoFAMethod::Passkey,
+        ] {
+            assert_eq!(TwoFAMethod::parse(method.as_str()).unwrap(), method);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_method() {
+        assert!(TwoFAMethod::parse("sms").is_err());
+    }
+}
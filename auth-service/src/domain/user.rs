@@ -1,21 +1,52 @@
-// The User struct should contain 3 fields. email, which is a String; 
-// password, which is also a String; and requires_2fa, which is a boolean. 
+// The User struct holds the user's id, email, password, the second factor
+// they have to provide on login and whether their email has been verified.
 
-use super::{Email, Password};
+use color_eyre::eyre::{Context, Result};
+use uuid::Uuid;
+
+use super::{Email, Password, TwoFAMethod};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
+    pub id: UserId,
     pub email: Email,
     pub password: Password,
-    pub requires_2fa: bool
+    pub two_fa_method: TwoFAMethod,
+    pub email_verified: bool,
 }
 
 impl User {
-    pub fn new(email: Email, password: Password, requires_2fa: bool) -> User {
+    pub fn new(email: Email, password: Password, two_fa_method: TwoFAMethod) -> User {
         User {
+            id: UserId::default(),
             email: email,
             password: password,
-            requires_2fa: requires_2fa
+            two_fa_method: two_fa_method,
+            // Every account starts unverified until the owner confirms the address
+            email_verified: false,
         }
     }
-}
\ No newline at end of file
+}
+
+// Identifies a user for good, unlike their email address which they can change
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserId(String);
+
+impl UserId {
+    pub fn parse(id: String) -> Result<Self> {
+        let parsed_id = Uuid::parse_str(&id).wrap_err("Invalid user id")?;
+        Ok(Self(parsed_id.to_string()))
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        UserId(Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for UserId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
@@ -1,8 +1,10 @@
 // This struct encapsulates our application-related logic.
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    middleware::AddExtension,
+    http::{header, HeaderValue, Method, StatusCode},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
     serve::Serve,
     Json, Router,
 };
@@ -10,7 +12,7 @@ use redis::{Client, RedisResult};
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use secrecy::{ExposeSecret, Secret};
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 use utils::tracing::{make_span_with_request_id, on_request, on_response};
@@ -28,7 +30,7 @@ pub mod services;
 pub mod utils;
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<IntoMakeServiceWithConnectInfo<Router, SocketAddr>, AddExtension<Router, ConnectInfo<SocketAddr>>>,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -44,8 +46,8 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
             // Allow cookies to be included in requests
             .allow_credentials(true)
             .allow_origin(allowed_origins);
@@ -54,9 +56,51 @@ impl Application {
             .nest_service("/", ServeDir::new("assets"))
             .route("/signup", post(routes::signup))
             .route("/login", post(routes::login))
+            .route("/login/magic-link", post(routes::request_magic_link))
+            .route("/login/magic-link/consume", get(routes::consume_magic_link))
+            .route("/login/oidc/:provider", get(routes::start_federated_login))
+            .route("/login/oidc/:provider/callback", get(routes::federated_login_callback))
             .route("/logout", post(routes::logout))
+            .route("/logout-all", post(routes::logout_all))
+            .route("/refresh", post(routes::refresh))
+            .route("/change-password", post(routes::change_password))
+            .route("/change-email", post(routes::change_email))
+            .route("/change-email/confirm", get(routes::confirm_email_change))
+            .route("/account", delete(routes::delete_account))
+            .route("/password-reset/request", post(routes::request_password_reset))
+            .route("/password-reset/confirm", post(routes::confirm_password_reset))
+            .route("/verify-email", get(routes::verify_email))
+            .route("/verify-email/resend", post(routes::resend_verification_email))
+            .route("/2fa/enable", post(routes::enable_2fa))
+            .route("/2fa/disable", post(routes::disable_2fa))
+            .route("/totp/enroll", post(routes::enroll_totp))
+            .route("/totp/confirm", post(routes::confirm_totp))
+            .route("/recovery-codes/regenerate", post(routes::regenerate_recovery_codes))
             .route("/verify-2fa", post(routes::verify_2fa))
+            .route("/verify-2fa/recovery-code", post(routes::verify_recovery_code))
+            .route("/verify-2fa/passkey/start", post(routes::start_passkey_2fa))
+            .route("/verify-2fa/passkey", post(routes::verify_passkey_2fa))
+            .route("/passkeys/register/start", post(routes::start_passkey_registration))
+            .route("/passkeys/register/finish", post(routes::finish_passkey_registration))
+            .route("/passkeys/login/start", post(routes::start_passkey_login))
+            .route("/passkeys/login/finish", post(routes::finish_passkey_login))
             .route("/verify-token", post(routes::verify_token))
+            .route("/introspect", post(routes::introspect))
+            .route("/revoke", post(routes::revoke))
+            .route("/oauth/clients", post(routes::register_oauth_client))
+            .route("/authorize", get(routes::authorize).post(routes::authorize_consent))
+            .route("/token", post(routes::token))
+            .route("/userinfo", get(routes::userinfo).post(routes::userinfo))
+            .route("/sessions", get(routes::list_sessions))
+            .route("/sessions/revoke", post(routes::revoke_session))
+            .route("/sessions/revoke-others", post(routes::revoke_other_sessions))
+            .route(
+                "/admin/users/:user_id/roles",
+                get(routes::list_user_roles).post(routes::grant_user_role),
+            )
+            .route("/admin/users/:user_id/roles/:role", delete(routes::revoke_user_role))
+            .route("/.well-known/jwks.json", get(routes::jwks))
+            .route("/.well-known/openid-configuration", get(routes::openid_configuration))
             .with_state(shared_state)
             .layer(cors)
             .layer(
@@ -68,7 +112,11 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Peer addresses are recorded with login sessions
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         // Create a new Application instance and return it
         let app = Application { server, address };
@@ -89,6 +137,12 @@ pub struct ErrorResponse {
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let retry_after = match &self {
+            AuthAPIError::AccountLocked { retry_after_seconds } => Some(*retry_after_seconds),
+            _ => None,
+        };
+        // Clients authenticate with HTTP Basic, tell them so when they get it wrong
+        let challenge_client = matches!(self, AuthAPIError::InvalidClient);
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
@@ -97,6 +151,34 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AuthAPIError::AccountLocked { .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many failed login attempts")
+            }
+            AuthAPIError::TooMany2FAAttempts => {
+                (StatusCode::UNAUTHORIZED, "Too many incorrect 2FA codes, please log in again")
+            }
+            AuthAPIError::InvalidClient => (StatusCode::UNAUTHORIZED, "Invalid client credentials"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::PasswordReused => {
+                (StatusCode::BAD_REQUEST, "New password must differ from the current one")
+            }
+            AuthAPIError::InvalidGrant => (StatusCode::BAD_REQUEST, "Invalid authorization grant"),
+            AuthAPIError::UnsupportedGrantType => (StatusCode::BAD_REQUEST, "Unsupported grant type"),
+            AuthAPIError::InvalidScope => (StatusCode::BAD_REQUEST, "Invalid scope"),
+            AuthAPIError::InvalidClientRedirect => {
+                (StatusCode::BAD_REQUEST, "Unknown client or unregistered redirect URI")
+            }
+            AuthAPIError::UnknownIdentityProvider => {
+                (StatusCode::NOT_FOUND, "Unknown identity provider")
+            }
+            AuthAPIError::FederatedLoginFailed => {
+                (StatusCode::UNAUTHORIZED, "Login with identity provider failed")
+            }
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::UnknownRole => (StatusCode::BAD_REQUEST, "Unknown role"),
+            AuthAPIError::MissingPermission => (StatusCode::FORBIDDEN, "Missing permission"),
             AuthAPIError::UnexpectedError(_) => {
                 // Updated!
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
@@ -105,7 +187,18 @@ impl IntoResponse for AuthAPIError {
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
         });
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        if challenge_client {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Basic"));
+        }
+        response
     }
 }
 
@@ -1,12 +1,18 @@
 use auth_service::{
     app_state::AppState, domain::Email, get_redis_client, services::{data_stores::{
-        postgres_user_store::PostgresUserStore, redis_banned_token_store::RedisBannedTokenStore, redis_two_fa_code_store::RedisTwoFACodeStore,
-    }, postmark_email_client::PostmarkEmailClient}, utils::{constants::{prod, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME}, tracing::init_tracing}, Application
+        postgres_user_store::PostgresUserStore, postgres_totp_secret_store::PostgresTotpSecretStore, postgres_recovery_code_store::PostgresRecoveryCodeStore, postgres_client_store::PostgresClientStore, postgres_passkey_store::PostgresPasskeyStore, postgres_consent_store::PostgresConsentStore, postgres_federated_identity_store::PostgresFederatedIdentityStore, postgres_role_store::PostgresRoleStore, redis_banned_token_store::RedisBannedTokenStore, redis_two_fa_code_store::RedisTwoFACodeStore,
+        redis_refresh_token_store::RedisRefreshTokenStore, redis_password_reset_token_store::RedisPasswordResetTokenStore,
+        redis_email_verification_token_store::RedisEmailVerificationTokenStore, redis_email_change_token_store::RedisEmailChangeTokenStore, redis_magic_link_token_store::RedisMagicLinkTokenStore, redis_login_attempt_store::RedisLoginAttemptStore, redis_session_store::RedisSessionStore, redis_passkey_challenge_store::RedisPasskeyChallengeStore, redis_authorization_code_store::RedisAuthorizationCodeStore, redis_federated_login_store::RedisFederatedLoginStore,
+    }, oidc_provider_client::OidcProviderClient, postmark_email_client::PostmarkEmailClient}, utils::{constants::{env, prod, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME}, tracing::init_tracing}, Application
 };
 use auth_service::{
+    domain::{ClientStore, IdentityProvider, Scope},
     get_postgres_pool, services::mock_email_client::MockEmailClient, utils::constants::DATABASE_URL,
 };
+#[cfg(unix)]
+use auth_service::utils::keyring::reload_keyring_on_sighup;
 use reqwest::Client;
+use secrecy::Secret;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -17,19 +23,60 @@ async fn main() {
     init_tracing().expect("Failed to initialize tracing");
     let pg_pool = configure_postgresql().await;
     // let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+    let totp_secret_store = Arc::new(RwLock::new(PostgresTotpSecretStore::new(pg_pool.clone())));
+    let recovery_code_store = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+    let passkey_store = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+    let consent_store = Arc::new(RwLock::new(PostgresConsentStore::new(pg_pool.clone())));
+    let federated_identity_store = Arc::new(RwLock::new(PostgresFederatedIdentityStore::new(pg_pool.clone())));
+    let role_store = Arc::new(RwLock::new(PostgresRoleStore::new(pg_pool.clone())));
+    let client_store = Arc::new(RwLock::new(configure_clients(PostgresClientStore::new(pg_pool)).await));
     // let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
     // let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
     let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(Arc::new(RwLock::new(configure_redis())))));
     // let email_client = Arc::new(RwLock::new(MockEmailClient));
     let email_client = Arc::new(RwLock::new(configure_postmark_email_client()));
+    let reauthentication_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::for_reauthentication(Arc::new(RwLock::new(configure_redis())))));
+    let refresh_token_store = Arc::new(RwLock::new(RedisRefreshTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let email_verification_token_store = Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let email_change_token_store = Arc::new(RwLock::new(RedisEmailChangeTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let magic_link_token_store = Arc::new(RwLock::new(RedisMagicLinkTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let login_attempt_store = Arc::new(RwLock::new(RedisLoginAttemptStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let session_store = Arc::new(RwLock::new(RedisSessionStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let passkey_challenge_store = Arc::new(RwLock::new(RedisPasskeyChallengeStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let federated_login_store = Arc::new(RwLock::new(RedisFederatedLoginStore::new(Arc::new(RwLock::new(configure_redis())))));
+    let identity_provider_client = Arc::new(RwLock::new(configure_identity_provider_client()));
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
         email_client,
-    );
+    )
+    .with_reauthentication_code_store(reauthentication_code_store)
+    .with_refresh_token_store(refresh_token_store)
+    .with_password_reset_token_store(password_reset_token_store)
+    .with_email_verification_token_store(email_verification_token_store)
+    .with_email_change_token_store(email_change_token_store)
+    .with_magic_link_token_store(magic_link_token_store)
+    .with_totp_secret_store(totp_secret_store)
+    .with_recovery_code_store(recovery_code_store)
+    .with_login_attempt_store(login_attempt_store)
+    .with_client_store(client_store)
+    .with_session_store(session_store)
+    .with_passkey_store(passkey_store)
+    .with_passkey_challenge_store(passkey_challenge_store)
+    .with_authorization_code_store(authorization_code_store)
+    .with_consent_store(consent_store)
+    .with_federated_login_store(federated_login_store)
+    .with_federated_identity_store(federated_identity_store)
+    .with_role_store(role_store)
+    .with_identity_providers(configure_identity_providers(), identity_provider_client);
+    #[cfg(unix)]
+    tokio::spawn(reload_keyring_on_sighup());
+
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
@@ -52,6 +99,65 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
+// Registers the backend clients listed in AUTH_CLIENTS as comma separated
+// `client_id:secret[:scope]` entries, so their secrets and scopes can be changed by
+// redeploying. Scopes are space separated and may contain colons, secrets may not.
+async fn configure_clients(mut client_store: PostgresClientStore) -> PostgresClientStore {
+    dotenvy::dotenv().ok();
+    let clients = std::env::var(env::AUTH_CLIENTS_ENV_VAR).unwrap_or_default();
+
+    for client in clients.split(',').map(str::trim).filter(|client| !client.is_empty()) {
+        let mut parts = client.splitn(3, ':');
+        let (client_id, secret) = match (parts.next(), parts.next()) {
+            (Some(client_id), Some(secret)) => (client_id, secret),
+            _ => panic!("AUTH_CLIENTS entries must look like client_id:secret[:scope]"),
+        };
+        let scope = Scope::parse(parts.next().unwrap_or_default())
+            .expect("AUTH_CLIENTS scopes must be valid");
+        client_store
+            .register_client(client_id, Secret::new(secret.to_owned()), scope)
+            .await
+            .expect("Failed to register client");
+    }
+
+    client_store
+}
+
+// Reads the external identity providers users can log in with from OIDC_PROVIDERS, as comma
+// separated `name|issuer|client_id|client_secret` entries
+fn configure_identity_providers() -> Vec<IdentityProvider> {
+    dotenvy::dotenv().ok();
+    let providers = std::env::var(env::OIDC_PROVIDERS_ENV_VAR).unwrap_or_default();
+
+    providers
+        .split(',')
+        .map(str::trim)
+        .filter(|provider| !provider.is_empty())
+        .map(|provider| {
+            let parts: Vec<&str> = provider.split('|').collect();
+            let [name, issuer, client_id, client_secret] = parts[..] else {
+                panic!("OIDC_PROVIDERS entries must look like name|issuer|client_id|client_secret");
+            };
+            IdentityProvider::new(
+                name.to_owned(),
+                issuer.to_owned(),
+                client_id.to_owned(),
+                Secret::new(client_secret.to_owned()),
+            )
+            .expect("Invalid identity provider in OIDC_PROVIDERS")
+        })
+        .collect()
+}
+
+fn configure_identity_provider_client() -> OidcProviderClient {
+    let http_client = Client::builder()
+        .timeout(prod::identity_provider_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    OidcProviderClient::new(http_client)
+}
+
 fn configure_redis() -> redis::Connection {
     get_redis_client(REDIS_HOST_NAME.to_owned())
         .expect("Failed to get Redis client")
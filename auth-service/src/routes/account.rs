@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    routes::{end_all_sessions, reauthenticate, Reauthentication, SecondFactorRequiredResponse},
+    utils::{
+        auth::get_authenticated_email,
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+#[tracing::instrument(name = "Delete Account", skip_all)]
+pub async fn delete_account(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<Response, AuthAPIError>) {
+    let email = match get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    match reauthenticate(&email, request.password, request.code, &state).await {
+        Ok(Reauthentication::Confirmed) => {}
+        Ok(Reauthentication::SecondFactorRequired(two_fa_method)) => {
+            return (jar, Ok(SecondFactorRequiredResponse::into_response(two_fa_method)))
+        }
+        Err(e) => return (jar, Err(e)),
+    }
+
+    let user_id = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user.id,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if let Err(e) = end_all_sessions(&user_id, &state).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = state.user_store.write().await.delete_user(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // Whatever else is kept about the user outside of the users table
+    if let Err(e) = state.two_fa_code_store.write().await.remove_code(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    if let Err(e) = state.reauthentication_code_store.write().await.remove_code(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    if let Err(e) = state.login_attempt_store.write().await.reset(&user_id).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    let jar = jar.remove(JWT_COOKIE_NAME).remove(REFRESH_TOKEN_COOKIE_NAME);
+
+    // The account is gone at this point either way, so a failed confirmation is only logged
+    let email_client = state.email_client.read().await;
+    if let Err(e) = email_client
+        .send_email(
+            &email,
+            "Your account was deleted",
+            "Your account and all data stored about it were deleted. If this was not you, contact support right away.",
+        )
+        .await
+    {
+        tracing::error!("Failed to send account deletion confirmation: {:?}", e);
+    }
+
+    (jar, Ok(StatusCode::OK.into_response()))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Secret<String>,
+    #[serde(rename = "2FACode")]
+    pub code: Option<String>,
+}
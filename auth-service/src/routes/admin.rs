@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Permission, Role, RoleStoreError, User, UserId, UserStoreError,
+        MANAGE_ROLES_PERMISSION,
+    },
+    utils::auth::{get_authenticated_claims, require_permission},
+};
+
+#[tracing::instrument(name = "List User Roles", skip_all)]
+pub async fn list_user_roles(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authorize_role_management(&jar, &state).await?;
+    let user = find_user(user_id, &state).await?;
+
+    Ok(Json(user_roles(&user, &state).await?))
+}
+
+// Tokens issued before the grant do not carry the role, the user gets it with the next
+// token, at the latest when refreshing
+#[tracing::instrument(name = "Grant User Role", skip_all)]
+pub async fn grant_user_role(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(user_id): Path<String>,
+    Json(request): Json<GrantRoleRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authorize_role_management(&jar, &state).await?;
+    let user = find_user(user_id, &state).await?;
+    let role = Role::parse(request.role).map_err(|_| AuthAPIError::UnknownRole)?;
+
+    match state.role_store.write().await.grant_role(&user.id, &role).await {
+        Ok(()) => {}
+        Err(RoleStoreError::RoleNotFound) => return Err(AuthAPIError::UnknownRole),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    Ok(Json(user_roles(&user, &state).await?))
+}
+
+// Bumping the token version ends the tokens that still carry the role, refreshing
+// gets the user new ones without it
+#[tracing::instrument(name = "Revoke User Role", skip_all)]
+pub async fn revoke_user_role(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((user_id, role)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authorize_role_management(&jar, &state).await?;
+    let user = find_user(user_id, &state).await?;
+    let role = Role::parse(role).map_err(|_| AuthAPIError::UnknownRole)?;
+
+    state
+        .role_store
+        .write()
+        .await
+        .revoke_role(&user.id, &role)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .user_store
+        .write()
+        .await
+        .bump_token_version(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(user_roles(&user, &state).await?))
+}
+
+async fn authorize_role_management(jar: &CookieJar, state: &AppState) -> Result<(), AuthAPIError> {
+    let claims = get_authenticated_claims(
+        jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+    let permission = Permission::parse(MANAGE_ROLES_PERMISSION.to_owned())
+        .map_err(AuthAPIError::UnexpectedError)?;
+    require_permission(&claims, &permission, state.role_store.clone()).await
+}
+
+async fn find_user(user_id: String, state: &AppState) -> Result<User, AuthAPIError> {
+    let user_id = UserId::parse(user_id).map_err(|_| AuthAPIError::UserNotFound)?;
+    match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => Ok(user),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::UserNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+async fn user_roles(user: &User, state: &AppState) -> Result<UserRolesResponse, AuthAPIError> {
+    let roles = state
+        .role_store
+        .read()
+        .await
+        .get_roles(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(UserRolesResponse {
+        roles: roles.into_iter().map(|role| role.as_ref().to_owned()).collect(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct GrantRoleRequest {
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserRolesResponse {
+    pub roles: Vec<String>,
+}
@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, EmailChangeToken, EmailChangeTokenStoreError, UserStoreError,
+    },
+    routes::{end_all_sessions, reauthenticate, Reauthentication, SecondFactorRequiredResponse},
+    utils::{auth::get_authenticated_email, constants::AUTH_SERVICE_URL},
+};
+
+// Starts moving the account to a new address. Nothing changes until the link
+// sent to the new address is opened, the old address is told about the request.
+#[tracing::instrument(name = "Change Email", skip_all)]
+pub async fn change_email(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    let new_email = match Email::parse(Secret::new(request.new_email)) {
+        Ok(new_email) if new_email != email => new_email,
+        _ => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let user = {
+        let user_store = state.user_store.read().await;
+        match user_store.get_user(&new_email).await {
+            Ok(_) => return Err(AuthAPIError::UserAlreadyExists),
+            Err(UserStoreError::UserNotFound) => {}
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+        user_store
+            .get_user(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+    };
+
+    // The token names the user by id, so it still finds them if anything else changes
+    let token = EmailChangeToken::default();
+    if let Err(e) = state
+        .email_change_token_store
+        .write()
+        .await
+        .add_token(user.id, new_email.clone(), token.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let link = format!(
+        "{}/change-email/confirm?token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.as_ref().expose_secret()
+    );
+
+    let email_client = state.email_client.read().await;
+    if let Err(e) = email_client
+        .send_email(
+            &new_email,
+            "Confirm your new email address",
+            &format!(
+                "Please confirm your new email address by opening this link: {} The link expires in 24 hours.",
+                link
+            ),
+        )
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    if let Err(e) = email_client
+        .send_email(
+            &email,
+            "Your email address is being changed",
+            &format!(
+                "A change of your account's email address to {} was requested. If this was not you, reset your password right away.",
+                new_email.as_ref().expose_secret()
+            ),
+        )
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    let response = Json(ChangeEmailResponse {
+        message: "A confirmation link has been sent to the new email address".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response).into_response())
+}
+
+#[tracing::instrument(name = "Confirm Email Change", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<ConfirmEmailChangeRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = match EmailChangeToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    let mut email_change_token_store = state.email_change_token_store.write().await;
+
+    let (user_id, new_email) = match email_change_token_store.get_change(&token).await {
+        Ok(change) => change,
+        Err(EmailChangeTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if let Err(e) = email_change_token_store.remove_token(&token).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(email_change_token_store);
+
+    let email = match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => user.email,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    end_all_sessions(&user_id, &state).await?;
+
+    let mut user_store = state.user_store.write().await;
+    match user_store.update_email(&email, new_email.clone()).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Opening the link proves the new address belongs to the user
+    if let Err(e) = user_store.mark_email_verified(&new_email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(user_store);
+
+    // Whatever else is kept about the old address outside of the users table
+    if let Err(e) = state.two_fa_code_store.write().await.remove_code(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    if let Err(e) = state.reauthentication_code_store.write().await.remove_code(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let response = Json(ChangeEmailResponse {
+        message: "Email changed, please log in with the new email address".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: String,
+    pub password: Secret<String>,
+    #[serde(rename = "2FACode")]
+    pub code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmailChangeRequest {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
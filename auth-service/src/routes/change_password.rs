@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password},
+    routes::{check_login_lockout, end_other_sessions, get_authenticated_session},
+    utils::auth::generate_user_auth_cookie,
+};
+
+#[tracing::instrument(name = "Change Password", skip_all)]
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (user, session_id) = match get_authenticated_session(&jar, &state).await {
+        Ok(session) => session,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    // A current password that does not even pass the rules cannot be the right one
+    let current_password = match Password::parse(request.current_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    let new_password = match Password::parse(request.new_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // A stolen session must not allow more password guesses than /login does
+    if let Err(e) = check_login_lockout(&user.id, &state).await {
+        return (jar, Err(e));
+    }
+
+    // The hashes are checked under a read lock so other requests are not held up by argon2
+    let user_store = state.user_store.read().await;
+
+    if user_store.validate_user(&user.email, &current_password).await.is_err() {
+        drop(user_store);
+        if let Err(e) = state.login_attempt_store.write().await.record_failure(&user.id).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    // Only the hash is stored, so reuse is detected by checking the new password against it
+    if user_store.validate_user(&user.email, &new_password).await.is_ok() {
+        return (jar, Err(AuthAPIError::PasswordReused));
+    }
+    drop(user_store);
+
+    if let Err(e) = state.login_attempt_store.write().await.reset(&user.id).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    if let Err(e) = state.user_store.write().await.update_password(&user.email, new_password).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // Updating the password bumped the token version, which ended the auth tokens of
+    // every session. This one stays logged in with a fresh token, the others are ended.
+    if let Err(e) = end_other_sessions(&user.id, &session_id, &state).await {
+        return (jar, Err(e));
+    }
+
+    let auth_cookie = match generate_user_auth_cookie(
+        &user.id,
+        &session_id,
+        state.user_store.clone(),
+        state.role_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+    let jar = jar.add(auth_cookie);
+
+    // The password is changed at this point either way, so a failed notification is only logged
+    let email_client = state.email_client.read().await;
+    if let Err(e) = email_client
+        .send_email(
+            &user.email,
+            "Your password was changed",
+            "The password of your account was just changed and all your other sessions were logged out. If this was not you, reset your password right away.",
+        )
+        .await
+    {
+        tracing::error!("Failed to send password change notification: {:?}", e);
+    }
+
+    (jar, Ok(StatusCode::OK))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
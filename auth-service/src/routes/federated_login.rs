@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Redirect},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        generate_random_password, verify_id_token, AuthAPIError, AuthenticationMethod, Email,
+        ExternalIdentity, FederatedIdentityStoreError, FederatedLogin, FederatedLoginState,
+        FederatedLoginStoreError, IdentityProvider, TwoFAMethod, User, UserStoreError,
+    },
+    routes::{handle_2fa, handle_no_2fa, SessionOrigin},
+    utils::constants::{AUTH_SERVICE_URL, FEDERATED_LOGIN_COOKIE_NAME, FEDERATED_LOGIN_TTL_SECONDS},
+};
+
+// Sends the user to the identity provider to log in (OpenID Connect Core section 3.1.2).
+// The state is also set as a cookie, so only the browser that started a login can finish it.
+#[tracing::instrument(name = "Start Federated Login", skip_all)]
+pub async fn start_federated_login(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(provider_name): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let provider = find_provider(&state, &provider_name)?;
+
+    let metadata = state
+        .identity_provider_client
+        .read()
+        .await
+        .discover(provider)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let login_state = FederatedLoginState::default();
+    let login = FederatedLogin::new(provider.name.clone());
+
+    let mut authorization_url = Url::parse(&metadata.authorization_endpoint)
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    authorization_url.query_pairs_mut().extend_pairs([
+        ("response_type", "code"),
+        ("client_id", provider.client_id.as_str()),
+        ("redirect_uri", redirect_uri(provider).as_str()),
+        ("scope", "openid email"),
+        ("state", login_state.as_ref().expose_secret().as_str()),
+        ("nonce", login.nonce.as_str()),
+        ("code_challenge", login.code_challenge().as_str()),
+        ("code_challenge_method", "S256"),
+    ]);
+
+    let cookie = Cookie::build((
+        FEDERATED_LOGIN_COOKIE_NAME,
+        login_state.as_ref().expose_secret().to_owned(),
+    ))
+    .path(FEDERATED_LOGIN_COOKIE_PATH)
+    .http_only(true)
+    // Lax, so the cookie comes along when the provider redirects back
+    .same_site(SameSite::Lax)
+    .max_age(time::Duration::seconds(FEDERATED_LOGIN_TTL_SECONDS as i64))
+    .build();
+
+    state
+        .federated_login_store
+        .write()
+        .await
+        .add_login(login_state, login)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((jar.add(cookie), Redirect::to(authorization_url.as_str())))
+}
+
+// Where the identity provider sends the user back to. The provider's ID token stands in for
+// the password step of a login, so users with 2FA still have to complete it.
+#[tracing::instrument(name = "Federated Login Callback", skip_all)]
+pub async fn federated_login_callback(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    origin: SessionOrigin,
+    Path(provider_name): Path<String>,
+    Query(request): Query<FederatedLoginCallbackRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let provider = match find_provider(&state, &provider_name) {
+        Ok(provider) => provider,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    // The login can only be attempted once, whatever happens next
+    let cookie_state = jar.get(FEDERATED_LOGIN_COOKIE_NAME).map(|cookie| cookie.value().to_owned());
+    let jar = jar.remove(Cookie::build(FEDERATED_LOGIN_COOKIE_NAME).path(FEDERATED_LOGIN_COOKIE_PATH));
+
+    let identity = match complete_federated_login(provider, cookie_state, request, &state).await {
+        Ok(identity) => identity,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let user = match find_or_provision_user(provider, identity, &state).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    match user.two_fa_method {
+        TwoFAMethod::None => {
+            let token_version =
+                match state.user_store.read().await.get_token_version(&user.id).await {
+                    Ok(token_version) => token_version,
+                    Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+                };
+            handle_no_2fa(&user, token_version, &[AuthenticationMethod::Federated], origin, &state, jar)
+                .await
+        }
+        method => handle_2fa(&user.email, method, &state, jar).await,
+    }
+}
+
+fn find_provider<'a>(state: &'a AppState, name: &str) -> Result<&'a IdentityProvider, AuthAPIError> {
+    state
+        .identity_providers
+        .iter()
+        .find(|provider| provider.name == name)
+        .ok_or(AuthAPIError::UnknownIdentityProvider)
+}
+
+// Registered with the provider as this service's redirect URI
+fn redirect_uri(provider: &IdentityProvider) -> String {
+    format!("{}/login/oidc/{}/callback", AUTH_SERVICE_URL.as_str(), provider.name)
+}
+
+// Checks the state against the login this browser started, exchanges the code and verifies
+// the ID token it was exchanged for
+async fn complete_federated_login(
+    provider: &IdentityProvider,
+    cookie_state: Option<String>,
+    request: FederatedLoginCallbackRequest,
+    state: &AppState,
+) -> Result<ExternalIdentity, AuthAPIError> {
+    // Denied and failed logins come back with an error instead of a code (RFC 6749 section 4.1.2.1)
+    let (code, returned_state) = match (request.code, request.state, request.error) {
+        (Some(code), Some(returned_state), None) => (code, returned_state),
+        _ => return Err(AuthAPIError::FederatedLoginFailed),
+    };
+
+    // A login started by someone else must not be finished in this browser, or an attacker
+    // could log the user into the attacker's account
+    if cookie_state.as_deref() != Some(returned_state.as_str()) {
+        return Err(AuthAPIError::FederatedLoginFailed);
+    }
+    let login_state =
+        FederatedLoginState::parse(returned_state).map_err(|_| AuthAPIError::FederatedLoginFailed)?;
+
+    let mut federated_login_store = state.federated_login_store.write().await;
+    let login = match federated_login_store.get_login(&login_state).await {
+        Ok(login) => login,
+        Err(FederatedLoginStoreError::LoginNotFound) => return Err(AuthAPIError::FederatedLoginFailed),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if let Err(e) = federated_login_store.remove_login(&login_state).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(federated_login_store);
+
+    if login.provider != provider.name {
+        return Err(AuthAPIError::FederatedLoginFailed);
+    }
+
+    let client = state.identity_provider_client.read().await;
+    let metadata = client.discover(provider).await.map_err(AuthAPIError::UnexpectedError)?;
+    let id_token = match client
+        .exchange_code(provider, &metadata, &code, &redirect_uri(provider), &login.code_verifier)
+        .await
+    {
+        Ok(id_token) => id_token,
+        Err(e) => {
+            tracing::warn!("code exchange with {} failed: {:?}", provider.name, e);
+            return Err(AuthAPIError::FederatedLoginFailed);
+        }
+    };
+    let jwks = client.get_jwks(&metadata).await.map_err(AuthAPIError::UnexpectedError)?;
+
+    verify_id_token(&id_token, provider, &jwks, &login.nonce).map_err(|e| {
+        tracing::warn!("rejected ID token from {}: {:?}", provider.name, e);
+        AuthAPIError::FederatedLoginFailed
+    })
+}
+
+// Users are found by their account at the provider once it is linked. Until then the
+// provider has to vouch for the email address, which links an existing user with it or
+// provisions a new one.
+async fn find_or_provision_user(
+    provider: &IdentityProvider,
+    identity: ExternalIdentity,
+    state: &AppState,
+) -> Result<User, AuthAPIError> {
+    let linked = state
+        .federated_identity_store
+        .read()
+        .await
+        .get_user_id(&provider.name, &identity.subject)
+        .await;
+    match linked {
+        Ok(user_id) => {
+            return match state.user_store.read().await.get_user_by_id(&user_id).await {
+                Ok(user) => Ok(user),
+                Err(UserStoreError::UserNotFound) => Err(AuthAPIError::FederatedLoginFailed),
+                Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+            };
+        }
+        Err(FederatedIdentityStoreError::IdentityNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    if !identity.email_verified {
+        return Err(AuthAPIError::EmailNotVerified);
+    }
+    let email = identity
+        .email
+        .and_then(|email| Email::parse(Secret::new(email)).ok())
+        .ok_or(AuthAPIError::FederatedLoginFailed)?;
+
+    let mut user_store = state.user_store.write().await;
+    let user = match user_store.get_user(&email).await {
+        // Whoever registered an unverified account may not own the address, linking it
+        // would let them keep access with their password
+        Ok(user) if !user.email_verified => return Err(AuthAPIError::EmailNotVerified),
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => {
+            let mut user = User::new(email, generate_random_password(), TwoFAMethod::None);
+            user.email_verified = true;
+            if let Err(e) = user_store.add_user(user.clone()).await {
+                return Err(AuthAPIError::UnexpectedError(e.into()));
+            }
+            user
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    drop(user_store);
+
+    state
+        .federated_identity_store
+        .write()
+        .await
+        .link_identity(&provider.name, &identity.subject, &user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(user)
+}
+
+const FEDERATED_LOGIN_COOKIE_PATH: &str = "/login/oidc";
+
+// Parameter names are fixed by RFC 6749
+#[derive(Deserialize)]
+pub struct FederatedLoginCallbackRequest {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::IntoResponse,
+    Form, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::auth::{authenticate_service, validate_token, Claims, SubjectType},
+};
+
+// Token introspection for backend services (RFC 7662)
+#[tracing::instrument(name = "Introspect", skip_all)]
+pub async fn introspect(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectionRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_service(&headers, state.client_store.clone()).await?;
+
+    // Expired, banned and malformed tokens are all simply reported as inactive
+    let response = match validate_token(
+        &request.token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => IntrospectionResponse::active(claims),
+        Err(_) => IntrospectionResponse::default(),
+    };
+
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+}
+
+// Inactive tokens only get `active: false`, nothing else about them is revealed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    // `user`, or `client` for tokens a backend service got for itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_type: Option<SubjectType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+impl IntrospectionResponse {
+    fn active(claims: Claims) -> Self {
+        Self {
+            active: true,
+            sub: Some(claims.sub),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            nbf: Some(claims.nbf),
+            iss: Some(claims.iss),
+            aud: Some(claims.aud),
+            jti: Some(claims.jti),
+            sub_type: Some(claims.sub_type),
+            sid: claims.sid,
+            scope: claims.scope,
+            client_id: claims.client_id,
+            roles: claims.roles,
+        }
+    }
+}
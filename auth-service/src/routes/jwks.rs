@@ -0,0 +1,15 @@
+use axum::{response::IntoResponse, Json};
+use color_eyre::eyre::eyre;
+
+use crate::{domain::AuthAPIError, utils::constants::JWT_KEYRING};
+
+// Public keys tokens are signed with, so other services can verify them offline.
+// Keys rotated out stay listed until tokens they signed have expired, shared HS256
+// secrets are never listed.
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks() -> Result<impl IntoResponse, AuthAPIError> {
+    let keyring = JWT_KEYRING
+        .read()
+        .map_err(|_| AuthAPIError::UnexpectedError(eyre!("JWT keyring lock poisoned")))?;
+    Ok(Json(keyring.jwks()))
+}
@@ -8,14 +8,19 @@ use secrecy::Secret;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, AuthenticationMethod, Email, LoginAttemptId, LoginAttemptStoreError, Password, TwoFACode,
+        TwoFAMethod, User, UserId,
+    },
+    routes::{start_session, SessionOrigin},
+    utils::auth::{generate_auth_cookie, generate_refresh_cookie},
 };
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    origin: SessionOrigin,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let password = match Password::parse(request.password) {
@@ -30,26 +35,70 @@ pub async fn login(
 
     let user_store = &state.user_store.read().await;
 
+    // Failures are counted per user, so the lockout follows them to a new address.
+    // Unknown addresses have nobody to lock out.
+    let user = user_store.get_user(&email).await.ok();
+
+    // Checked before the password so guesses against a locked account never reach argon2
+    if let Some(user) = &user {
+        if let Err(e) = check_login_lockout(&user.id, &state).await {
+            return (jar, Err(e));
+        }
+    }
+
     if user_store.validate_user(&email, &password).await.is_err() {
+        if let Some(user) = &user {
+            if let Err(e) = state.login_attempt_store.write().await.record_failure(&user.id).await {
+                return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+        }
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
-    let user = match user_store.get_user(&email).await {
-        Ok(user) => user,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    let user = match user {
+        Some(user) => user,
+        None => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
+    // Only checked once the password is known to be correct, so the error
+    // does not reveal whether an address is registered
+    if !user.email_verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
     // Handle request based on user's 2FA configuration
-    match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+    match user.two_fa_method {
+        TwoFAMethod::None => {
+            // Users with 2FA are only cleared once their second factor succeeds
+            if let Err(e) = state.login_attempt_store.write().await.reset(&user.id).await {
+                return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+            let token_version = match user_store.get_token_version(&user.id).await {
+                Ok(token_version) => token_version,
+                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            };
+            handle_no_2fa(&user, token_version, &[AuthenticationMethod::Password], origin, &state, jar)
+                .await
+        }
+        method => handle_2fa(&user.email, method, &state, jar).await,
+    }
+}
+
+pub(crate) async fn check_login_lockout(user_id: &UserId, state: &AppState) -> Result<(), AuthAPIError> {
+    match state.login_attempt_store.read().await.check_lockout(user_id).await {
+        Ok(()) => Ok(()),
+        Err(LoginAttemptStoreError::LockedOut { retry_after_seconds }) => {
+            Err(AuthAPIError::AccountLocked { retry_after_seconds })
+        }
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
     }
 }
 
 // New!
 #[tracing::instrument(name = "Handle 2FA", skip_all)]
-async fn handle_2fa(
+pub(crate) async fn handle_2fa(
     email: &Email,    // New!
+    two_fa_method: TwoFAMethod,
     state: &AppState, // New!
     jar: CookieJar,
 ) -> (
@@ -68,21 +117,27 @@ async fn handle_2fa(
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    let email_client = state.email_client.read().await;
-    if let Err(e) = email_client
-        .send_email(
-            email,
-            "Your 2FA code",
-            &format!("Your 2FA code is: {}", two_fa_code.as_ref().to_string()),
-        )
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    // TOTP and passkey users still get a login attempt in the code store so the second
+    // step can be tied to this login, but they answer with their authenticator and the
+    // generated code is never sent or accepted
+    if two_fa_method == TwoFAMethod::Email {
+        let email_client = state.email_client.read().await;
+        if let Err(e) = email_client
+            .send_email(
+                email,
+                "Your 2FA code",
+                &format!("Your 2FA code is: {}", two_fa_code.as_ref()),
+            )
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
+        }
     }
 
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().to_string(),
+        two_fa_method,
     }));
 
     let status_code = StatusCode::PARTIAL_CONTENT;
@@ -91,18 +146,40 @@ async fn handle_2fa(
 
 // New!
 #[tracing::instrument(name = "Handle No 2FA", skip_all)]
-async fn handle_no_2fa(
-    email: &Email,
+pub(crate) async fn handle_no_2fa(
+    user: &User,
+    token_version: u64,
+    methods: &[AuthenticationMethod],
+    origin: SessionOrigin,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(email) {
+    let family_id = match start_session(&user.id, methods, origin, state).await {
+        Ok(session_id) => session_id,
+        Err(e) => return (jar, Err(e)),
+    };
+    let roles = match state.role_store.read().await.get_roles(&user.id).await {
+        Ok(roles) => roles,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    let auth_cookie = match generate_auth_cookie(&user.id, &family_id, token_version, &roles) {
         Ok(cookie) => cookie,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     };
-    let updated_jar = jar.add(auth_cookie);
+    let refresh_cookie = match generate_refresh_cookie(
+        &user.id,
+        family_id,
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
     let json_response = Json(LoginResponse::RegularAuth);
     let status_code = StatusCode::OK;
     (updated_jar, Ok((status_code, json_response)))
@@ -128,4 +205,6 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
 }
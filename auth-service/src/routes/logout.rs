@@ -5,9 +5,13 @@ use axum_extra::extract::CookieJar;
 use crate::app_state::AppState;
 use color_eyre::eyre::Result;
 use crate::{
-    domain::AuthAPIError, utils::{auth::validate_token, constants::JWT_COOKIE_NAME}
+    domain::{AuthAPIError, TokenFamilyId},
+    routes::{end_all_sessions, end_session, get_authenticated_session},
+    utils::{
+        auth::validate_token,
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
 };
-use secrecy::Secret;
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     jar: CookieJar,
@@ -27,12 +31,19 @@ pub async fn logout(
     // Validate JWT token by calling `validate_token` from the auth service.
     // If the token is valid you can ignore the returned claims for now.
     // Return AuthAPIError::InvalidToken if validation fails.
-    match validate_token(&token, state.banned_token_store.clone()).await {
-        Ok(_) => {},
+    let claims = match validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => claims,
         Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
-    }
+    };
 
-    match state.banned_token_store.write().await.store_token(Secret::new(token.to_owned())).await  {
+    match state.banned_token_store.write().await.store_token(claims.jti).await  {
         Ok(_) => println!("Token stored in banned store"), // Debug print
         Err(e) => {
             println!("Failed to store token: {:?}", e); // Debug print
@@ -40,8 +51,40 @@ pub async fn logout(
         }
     } 
     
-    // Remove the JWT cookie from the jar
-    let jar = jar.remove(JWT_COOKIE_NAME);
+    // End the session so it cannot be extended with its refresh token either
+    if let Some(Ok(session_id)) = claims.sid.map(TokenFamilyId::parse) {
+        if let Err(e) = end_session(&session_id, &state).await {
+            return (jar, Err(e));
+        }
+    }
+
+    // Remove the JWT and refresh token cookies from the jar
+    let jar = jar.remove(JWT_COOKIE_NAME).remove(REFRESH_TOKEN_COOKIE_NAME);
     
     (jar, Ok(StatusCode::OK))
-}
\ No newline at end of file
+}
+
+// Logs the user out of every session at once. Bumping the token version invalidates
+// all auth tokens issued so far, including ones whose session was already ended.
+#[tracing::instrument(name = "Logout All", skip_all)]
+pub async fn logout_all(
+    jar: CookieJar,
+    State(state): State<Arc<AppState>>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let user_id = match get_authenticated_session(&jar, &state).await {
+        Ok((user, _)) => user.id,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if let Err(e) = state.user_store.write().await.bump_token_version(&user_id).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    if let Err(e) = end_all_sessions(&user_id, &state).await {
+        return (jar, Err(e));
+    }
+
+    let jar = jar.remove(JWT_COOKIE_NAME).remove(REFRESH_TOKEN_COOKIE_NAME);
+
+    (jar, Ok(StatusCode::OK))
+}
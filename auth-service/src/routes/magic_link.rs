@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, AuthenticationMethod, Email, MagicLinkToken, MagicLinkTokenStoreError, TwoFAMethod, UserStoreError},
+    routes::{handle_2fa, handle_no_2fa, SessionOrigin},
+    utils::constants::AUTH_SERVICE_URL,
+};
+
+#[tracing::instrument(name = "Request Magic Link", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = match Email::parse(Secret::new(request.email)) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    // Respond the same way whether or not the account exists,
+    // so this route cannot be used to find out who is registered
+    let response = Json(MagicLinkResponse {
+        message: "If the account exists, a login link has been sent".to_owned(),
+    });
+
+    match state.user_store.read().await.get_user(&email).await {
+        // Unverified users cannot log in, so they are not sent a link either
+        Ok(user) if user.email_verified => {}
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let token = MagicLinkToken::default();
+
+    if let Err(e) = state
+        .magic_link_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let link = format!(
+        "{}/login/magic-link/consume?token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.as_ref().expose_secret()
+    );
+
+    let email_client = state.email_client.read().await;
+    if let Err(e) = email_client
+        .send_email(
+            &email,
+            "Your login link",
+            &format!(
+                "Log in by opening this link: {} It expires in 15 minutes and works only once. If you did not ask to log in, you can ignore this email.",
+                link
+            ),
+        )
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    Ok((StatusCode::OK, response))
+}
+
+// Stands in for the password step of a login, so users with 2FA still have to
+// complete it through `/verify-2fa` before they get an auth cookie
+#[tracing::instrument(name = "Consume Magic Link", skip_all)]
+pub async fn consume_magic_link(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    origin: SessionOrigin,
+    Query(request): Query<ConsumeMagicLinkRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let token = match MagicLinkToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let mut magic_link_token_store = state.magic_link_token_store.write().await;
+
+    let email = match magic_link_token_store.get_email(&token).await {
+        Ok(email) => email,
+        Err(MagicLinkTokenStoreError::TokenNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if let Err(e) = magic_link_token_store.remove_token(&token).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    drop(magic_link_token_store);
+
+    let user_store = state.user_store.read().await;
+
+    let user = match user_store.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if !user.email_verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
+    match user.two_fa_method {
+        TwoFAMethod::None => {
+            let token_version = match user_store.get_token_version(&user.id).await {
+                Ok(token_version) => token_version,
+                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            };
+            handle_no_2fa(&user, token_version, &[AuthenticationMethod::OneTimeCode], origin, &state, jar)
+                .await
+        }
+        method => handle_2fa(&user.email, method, &state, jar).await,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConsumeMagicLinkRequest {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
@@ -1,12 +1,52 @@
+mod account;
+mod admin;
+mod change_email;
+mod change_password;
+mod federated_login;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod passkeys;
+mod password_reset;
+mod reauthenticate;
+mod recovery_codes;
+mod refresh;
+mod revoke;
+mod sessions;
 mod signup;
+mod totp;
+mod two_fa;
 mod verify_2fa; 
+mod verify_email;
 mod verify_token;
 
 // re-export items from sub-modules
+pub use account::*;
+pub use admin::*;
+pub use change_email::*;
+pub use change_password::*;
+pub use federated_login::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use magic_link::*;
+pub use oauth::*;
+pub use oidc::*;
+pub use passkeys::*;
+pub use password_reset::*;
+pub use reauthenticate::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use revoke::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
+pub use two_fa::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
\ No newline at end of file
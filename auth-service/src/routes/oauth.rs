@@ -0,0 +1,600 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{OriginalUri, Query, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Form, Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        generate_client_secret, AuthAPIError, AuthorizationCode, AuthorizationCodeStoreError,
+        AuthorizationGrant, ClientKind, ClientStoreError, ConsentStoreError, OAuthClient,
+        Permission, PkceChallenge, RedirectUri, RefreshToken, Scope, Session, SessionStoreError,
+        TokenFamilyId, UserId, UserStoreError, MANAGE_CLIENTS_PERMISSION,
+    },
+    routes::{rotate_refresh_token, start_client_session, SessionOrigin},
+    utils::auth::{
+        authenticate_client, generate_access_token, generate_client_token, generate_id_token,
+        generate_refresh_token,
+        get_authenticated_claims, id_tokens_supported, require_permission, TOKEN_TTL_SECONDS,
+    },
+};
+
+// Lets admins register an application that logs users in through the authorization code
+// flow. Confidential clients get a secret, which is only ever shown in this response.
+#[tracing::instrument(name = "Register OAuth Client", skip_all)]
+pub async fn register_oauth_client(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<RegisterClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = get_authenticated_claims(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+    let permission = Permission::parse(MANAGE_CLIENTS_PERMISSION.to_owned())
+        .map_err(AuthAPIError::UnexpectedError)?;
+    require_permission(&claims, &permission, state.role_store.clone()).await?;
+
+    if request.client_name.trim().is_empty() || request.redirect_uris.is_empty() {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    let redirect_uris = request
+        .redirect_uris
+        .into_iter()
+        .map(RedirectUri::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let client = OAuthClient::new(request.client_name, redirect_uris, request.confidential);
+    let secret = client.confidential.then(generate_client_secret);
+
+    if let Err(e) = state
+        .client_store
+        .write()
+        .await
+        .add_client(client.clone(), secret.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let response = RegisterClientResponse {
+        client_id: client.id,
+        client_secret: secret.map(|secret| secret.expose_secret().to_owned()),
+        client_name: client.name,
+        redirect_uris: client
+            .redirect_uris
+            .iter()
+            .map(|uri| uri.as_ref().to_owned())
+            .collect(),
+    };
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+// Authorization endpoint (RFC 6749 section 4.1.1). Users who are not logged in are sent
+// through the regular login page, which returns them here. Once they consented to the
+// requested scope the client gets an authorization code on its redirect URI.
+#[tracing::instrument(name = "Authorize", skip_all)]
+pub async fn authorize(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    OriginalUri(uri): OriginalUri,
+    Query(request): Query<AuthorizationRequest>,
+) -> Response {
+    let request = match validate_authorization_request(request, &state).await {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    let (user_id, login) = match get_authenticated_login(&jar, &state).await {
+        Ok(authenticated) => authenticated,
+        Err(AuthAPIError::MissingToken | AuthAPIError::InvalidToken) => {
+            return redirect_to_login(&uri)
+        }
+        Err(e) => return e.into_response(),
+    };
+
+    let consent = match state
+        .consent_store
+        .read()
+        .await
+        .get_consent(&user_id, &request.client.id)
+        .await
+    {
+        Ok(scope) => Some(scope),
+        Err(ConsentStoreError::ConsentNotFound) => None,
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()).into_response(),
+    };
+
+    // Only ask again when the client wants more than the user already allowed
+    if consent.is_some_and(|consent| consent.contains(&request.scope)) {
+        return issue_authorization_code(request, user_id, &login, &state).await;
+    }
+
+    let response = ConsentRequiredResponse {
+        message: "Consent required".to_owned(),
+        client_id: request.client.id,
+        client_name: request.client.name,
+        scope: request.scope.as_ref().to_owned(),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+// The user's answer to the consent prompt. Auth cookies are SameSite=Lax, so they are not
+// sent with cross-site form posts and another site can not approve on the user's behalf.
+#[tracing::instrument(name = "Authorize Consent", skip_all)]
+pub async fn authorize_consent(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(request): Form<ConsentRequest>,
+) -> Response {
+    let authorization = match validate_authorization_request(request.authorization, &state).await {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    let (user_id, login) = match get_authenticated_login(&jar, &state).await {
+        Ok(authenticated) => authenticated,
+        Err(e) => return e.into_response(),
+    };
+
+    match request.consent.as_str() {
+        "approve" => {}
+        answer => {
+            let error = if answer == "deny" { "access_denied" } else { "invalid_request" };
+            return redirect_with_error(&authorization.redirect_uri, error, authorization.state.as_deref());
+        }
+    }
+
+    let mut consent_store = state.consent_store.write().await;
+    let scope = match consent_store
+        .get_consent(&user_id, &authorization.client.id)
+        .await
+    {
+        Ok(scope) => scope.union(&authorization.scope),
+        Err(ConsentStoreError::ConsentNotFound) => authorization.scope.clone(),
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()).into_response(),
+    };
+    if let Err(e) = consent_store
+        .grant_consent(&user_id, &authorization.client.id, scope)
+        .await
+    {
+        return AuthAPIError::UnexpectedError(e.into()).into_response();
+    }
+    drop(consent_store);
+
+    issue_authorization_code(authorization, user_id, &login, &state).await
+}
+
+// Token endpoint (RFC 6749 section 3.2). Confidential clients authenticate with HTTP Basic,
+// public clients only pass their id and prove with PKCE that they started the flow.
+// Backend services get tokens for themselves with the client credentials grant.
+#[tracing::instrument(name = "Token", skip_all)]
+pub async fn token(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    origin: SessionOrigin,
+    Form(request): Form<TokenGrantRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let client = authenticate_token_client(&headers, request.client_id.as_deref(), &state).await?;
+
+    let response = match request.grant_type.as_str() {
+        "authorization_code" => exchange_authorization_code(&client, request, origin, &state).await?,
+        "refresh_token" => refresh_access_token(&client, request, &state).await?,
+        "client_credentials" => issue_client_token(&client, request)?,
+        _ => return Err(AuthAPIError::UnsupportedGrantType),
+    };
+
+    // Tokens must not end up in caches (RFC 6749 section 5.1)
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)))
+}
+
+// An authorization request whose client and redirect URI checked out
+struct ValidAuthorizationRequest {
+    client: OAuthClient,
+    redirect_uri: RedirectUri,
+    scope: Scope,
+    code_challenge: PkceChallenge,
+    state: Option<String>,
+    nonce: Option<String>,
+}
+
+// Problems with the client or redirect URI are shown to the user, never redirected,
+// or this would be an open redirector. Anything else goes back to the client
+// (RFC 6749 section 4.1.2.1).
+async fn validate_authorization_request(
+    request: AuthorizationRequest,
+    state: &AppState,
+) -> Result<ValidAuthorizationRequest, Response> {
+    let client = match state.client_store.read().await.get_client(&request.client_id).await {
+        Ok(client) => client,
+        Err(ClientStoreError::ClientNotFound) => {
+            return Err(AuthAPIError::InvalidClientRedirect.into_response())
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into()).into_response()),
+    };
+    if !client.has_redirect_uri(&request.redirect_uri) {
+        return Err(AuthAPIError::InvalidClientRedirect.into_response());
+    }
+    let redirect_uri = RedirectUri::parse(request.redirect_uri)
+        .map_err(|e| AuthAPIError::UnexpectedError(e).into_response())?;
+
+    let error = |error| redirect_with_error(&redirect_uri, error, request.state.as_deref());
+
+    if request.response_type.as_deref() != Some("code") {
+        return Err(error("unsupported_response_type"));
+    }
+    let scope = match Scope::parse(request.scope.as_deref().unwrap_or_default()) {
+        Ok(scope) => scope,
+        Err(_) => return Err(error("invalid_scope")),
+    };
+    // Without a published key the client could not verify the ID token
+    let id_tokens_supported =
+        id_tokens_supported().map_err(|e| AuthAPIError::UnexpectedError(e).into_response())?;
+    if scope.includes("openid") && !id_tokens_supported {
+        return Err(error("invalid_scope"));
+    }
+    let code_challenge = match request
+        .code_challenge
+        .map(|challenge| PkceChallenge::parse(challenge, request.code_challenge_method.as_deref()))
+    {
+        Some(Ok(code_challenge)) => code_challenge,
+        _ => return Err(error("invalid_request")),
+    };
+
+    Ok(ValidAuthorizationRequest {
+        client,
+        redirect_uri,
+        scope,
+        code_challenge,
+        state: request.state,
+        nonce: request.nonce,
+    })
+}
+
+// The logged in user and their login session, which tells how and when they logged in
+async fn get_authenticated_login(
+    jar: &CookieJar,
+    state: &AppState,
+) -> Result<(UserId, Session), AuthAPIError> {
+    let claims = get_authenticated_claims(
+        jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+    let user_id = UserId::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let session_id = claims
+        .sid
+        .and_then(|sid| TokenFamilyId::parse(sid).ok())
+        .ok_or(AuthAPIError::InvalidToken)?;
+    match state.session_store.read().await.get_session(&session_id).await {
+        Ok(session) => Ok((user_id, session)),
+        Err(SessionStoreError::SessionNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// The login page sends the user back to the authorization request once they are logged in
+fn redirect_to_login(uri: &Uri) -> Response {
+    let return_to: String = form_urlencoded::byte_serialize(uri.to_string().as_bytes()).collect();
+    Redirect::to(&format!("/?return_to={}", return_to)).into_response()
+}
+
+// The client's `state` is passed back with every answer on the redirect URI
+fn redirect_with_params<'a>(
+    redirect_uri: &RedirectUri,
+    mut params: Vec<(&'a str, &'a str)>,
+    state: Option<&'a str>,
+) -> Response {
+    params.extend(state.map(|state| ("state", state)));
+    Redirect::to(&redirect_uri.with_params(&params)).into_response()
+}
+
+fn redirect_with_error(redirect_uri: &RedirectUri, error: &str, state: Option<&str>) -> Response {
+    redirect_with_params(redirect_uri, vec![("error", error)], state)
+}
+
+async fn issue_authorization_code(
+    request: ValidAuthorizationRequest,
+    user_id: UserId,
+    login: &Session,
+    state: &AppState,
+) -> Response {
+    let code = AuthorizationCode::default();
+    let grant = AuthorizationGrant {
+        client_id: request.client.id,
+        redirect_uri: request.redirect_uri.clone(),
+        user_id,
+        scope: request.scope,
+        code_challenge: request.code_challenge,
+        nonce: request.nonce,
+        auth_time: login.created_at,
+        methods: login.methods.clone(),
+    };
+
+    if let Err(e) = state
+        .authorization_code_store
+        .write()
+        .await
+        .add_code(code.clone(), grant)
+        .await
+    {
+        return AuthAPIError::UnexpectedError(e.into()).into_response();
+    }
+
+    redirect_with_params(
+        &request.redirect_uri,
+        vec![("code", code.as_ref().expose_secret().as_str())],
+        request.state.as_deref(),
+    )
+}
+
+async fn authenticate_token_client(
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    state: &AppState,
+) -> Result<OAuthClient, AuthAPIError> {
+    let authenticated = headers.contains_key(header::AUTHORIZATION);
+    let client_id = match (authenticated, client_id) {
+        (true, _) => authenticate_client(headers, state.client_store.clone()).await?,
+        (false, Some(client_id)) => client_id.to_owned(),
+        (false, None) => return Err(AuthAPIError::InvalidClient),
+    };
+
+    let client = match state.client_store.read().await.get_client(&client_id).await {
+        Ok(client) => client,
+        Err(ClientStoreError::ClientNotFound) => return Err(AuthAPIError::InvalidClient),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // A confidential client's id alone is not enough, it has to present its secret
+    if client.confidential && !authenticated {
+        return Err(AuthAPIError::InvalidClient);
+    }
+    Ok(client)
+}
+
+async fn exchange_authorization_code(
+    client: &OAuthClient,
+    request: TokenGrantRequest,
+    origin: SessionOrigin,
+    state: &AppState,
+) -> Result<TokenResponse, AuthAPIError> {
+    let code = request
+        .code
+        .and_then(|code| AuthorizationCode::parse(code).ok())
+        .ok_or(AuthAPIError::InvalidGrant)?;
+
+    // Codes are single use, even a failed exchange burns it
+    let mut authorization_code_store = state.authorization_code_store.write().await;
+    let grant = match authorization_code_store.get_grant(&code).await {
+        Ok(grant) => grant,
+        Err(AuthorizationCodeStoreError::CodeNotFound) => return Err(AuthAPIError::InvalidGrant),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if let Err(e) = authorization_code_store.remove_code(&code).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(authorization_code_store);
+
+    let verified = grant.client_id == client.id
+        && request.redirect_uri.as_deref() == Some(grant.redirect_uri.as_ref())
+        && request
+            .code_verifier
+            .is_some_and(|verifier| grant.code_challenge.verify(&verifier));
+    if !verified {
+        return Err(AuthAPIError::InvalidGrant);
+    }
+
+    let user = match state.user_store.read().await.get_user_by_id(&grant.user_id).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidGrant),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let session_id = start_client_session(
+        &user.id,
+        origin,
+        &client.id,
+        grant.scope.clone(),
+        &grant.methods,
+        state,
+    )
+    .await?;
+
+    // OpenID Connect clients also learn who logged in
+    let id_token = if grant.scope.includes("openid") {
+        let id_token = generate_id_token(
+            &user,
+            &client.id,
+            &session_id,
+            &grant.scope,
+            grant.nonce,
+            grant.auth_time,
+            &grant.methods,
+        )
+        .map_err(AuthAPIError::UnexpectedError)?;
+        Some(id_token)
+    } else {
+        None
+    };
+
+    let response = issue_tokens(client, &user.id, session_id, grant.scope, state).await?;
+    Ok(TokenResponse { id_token, ..response })
+}
+
+async fn refresh_access_token(
+    client: &OAuthClient,
+    request: TokenGrantRequest,
+    state: &AppState,
+) -> Result<TokenResponse, AuthAPIError> {
+    let token = request
+        .refresh_token
+        .and_then(|token| RefreshToken::parse(token).ok())
+        .ok_or(AuthAPIError::InvalidGrant)?;
+
+    let (record, session) = match rotate_refresh_token(&token, Some(&client.id), state).await {
+        Ok(rotated) => rotated,
+        Err(AuthAPIError::InvalidToken) => return Err(AuthAPIError::InvalidGrant),
+        Err(e) => return Err(e),
+    };
+
+    let scope = session.scope.unwrap_or_default();
+    issue_tokens(client, &record.user_id, record.family_id, scope, state).await
+}
+
+// Client credentials grant (RFC 6749 section 4.4). Only service clients can use it, and
+// only for scopes they were registered with. Without a `scope` they get all of them.
+fn issue_client_token(
+    client: &OAuthClient,
+    request: TokenGrantRequest,
+) -> Result<TokenResponse, AuthAPIError> {
+    // Public clients have no credentials to prove who they are, applications act for users
+    if !client.confidential || client.kind != ClientKind::Service {
+        return Err(AuthAPIError::InvalidClient);
+    }
+
+    let scope = match request.scope {
+        Some(scope) => Scope::parse(&scope).map_err(|_| AuthAPIError::InvalidScope)?,
+        None => client.scope.clone(),
+    };
+    if !client.scope.contains(&scope) {
+        return Err(AuthAPIError::InvalidScope);
+    }
+
+    let access_token =
+        generate_client_token(&client.id, &scope).map_err(AuthAPIError::UnexpectedError)?;
+
+    // There is no refresh token, the client just asks again (RFC 6749 section 4.4.3)
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        refresh_token: None,
+        scope: scope.as_ref().to_owned(),
+        id_token: None,
+    })
+}
+
+async fn issue_tokens(
+    client: &OAuthClient,
+    user_id: &UserId,
+    session_id: TokenFamilyId,
+    scope: Scope,
+    state: &AppState,
+) -> Result<TokenResponse, AuthAPIError> {
+    let token_version = match state.user_store.read().await.get_token_version(user_id).await {
+        Ok(token_version) => token_version,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let access_token =
+        generate_access_token(user_id, &session_id, token_version, &client.id, &scope)
+            .map_err(AuthAPIError::UnexpectedError)?;
+    let refresh_token = generate_refresh_token(user_id, session_id, state.refresh_token_store.clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        refresh_token: Some(refresh_token.as_ref().expose_secret().to_owned()),
+        scope: scope.as_ref().to_owned(),
+        id_token: None,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct RegisterClientRequest {
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+    #[serde(rename = "redirectUris")]
+    pub redirect_uris: Vec<String>,
+    // Public clients (SPAs, mobile apps) can not keep a secret
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterClientResponse {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "clientSecret", skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+    #[serde(rename = "redirectUris")]
+    pub redirect_uris: Vec<String>,
+}
+
+// Parameter names are fixed by RFC 6749 and RFC 7636
+#[derive(Deserialize)]
+pub struct AuthorizationRequest {
+    pub response_type: Option<String>,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    // OpenID Connect clients bind the ID token to their login with it
+    pub nonce: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub authorization: AuthorizationRequest,
+    // `approve` or `deny`
+    pub consent: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentRequiredResponse {
+    pub message: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "clientName")]
+    pub client_name: String,
+    pub scope: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenGrantRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub client_id: Option<String>,
+    // Requested by clients acting on their own behalf
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    // Not issued to clients acting on their own behalf
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub scope: String,
+    // Only for authorization codes issued with the `openid` scope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
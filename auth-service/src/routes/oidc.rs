@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::IntoResponse,
+    Json,
+};
+use color_eyre::eyre::eyre;
+use jsonwebtoken::Algorithm;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Scope, UserId, UserStoreError},
+    utils::{
+        auth::{bearer_token, validate_token, SubjectType},
+        constants::{AUTH_SERVICE_URL, JWT_ISSUER, JWT_KEYRING},
+    },
+};
+
+// OpenID Provider metadata (OpenID Connect Discovery section 3), so client libraries
+// can find the endpoints and keys from the issuer alone
+#[tracing::instrument(name = "OpenID Configuration", skip_all)]
+pub async fn openid_configuration() -> Result<impl IntoResponse, AuthAPIError> {
+    let keyring = JWT_KEYRING
+        .read()
+        .map_err(|_| AuthAPIError::UnexpectedError(eyre!("JWT keyring lock poisoned")))?;
+    // With HS256 there are no ID tokens, so neither the scopes for them nor an algorithm
+    let (scopes_supported, id_token_algorithms) = if keyring.signs_id_tokens() {
+        (vec!["openid", "email"], vec![keyring.signing_key().algorithm()])
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    drop(keyring);
+
+    let url = |path: &str| format!("{}{}", AUTH_SERVICE_URL.as_str(), path);
+    Ok(Json(OpenIdConfiguration {
+        issuer: JWT_ISSUER.to_owned(),
+        authorization_endpoint: url("/authorize"),
+        token_endpoint: url("/token"),
+        userinfo_endpoint: url("/userinfo"),
+        jwks_uri: url("/.well-known/jwks.json"),
+        introspection_endpoint: url("/introspect"),
+        revocation_endpoint: url("/revoke"),
+        scopes_supported,
+        response_types_supported: vec!["code"],
+        grant_types_supported: vec!["authorization_code", "refresh_token", "client_credentials"],
+        subject_types_supported: vec!["public"],
+        id_token_signing_alg_values_supported: id_token_algorithms,
+        token_endpoint_auth_methods_supported: vec!["client_secret_basic", "none"],
+        code_challenge_methods_supported: vec!["S256"],
+        claims_supported: vec![
+            "iss", "sub", "aud", "exp", "iat", "auth_time", "nonce", "amr", "sid", "email",
+            "email_verified",
+        ],
+    }))
+}
+
+// Claims about the user an access token was issued for (OpenID Connect Core section 5.3).
+// Only tokens granted the `openid` scope are accepted, email claims need the `email` scope.
+#[tracing::instrument(name = "Userinfo", skip_all)]
+pub async fn userinfo(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = bearer_token(&headers).ok_or(AuthAPIError::MissingToken)?;
+
+    let claims = validate_token(
+        token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Client tokens have no user to describe
+    if claims.sub_type != SubjectType::User {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    let scope = match (&claims.client_id, &claims.scope) {
+        (Some(_), Some(scope)) => Scope::parse(scope).map_err(|_| AuthAPIError::InvalidToken)?,
+        _ => return Err(AuthAPIError::InvalidToken),
+    };
+    if !scope.includes("openid") {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let user_id = UserId::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)?;
+    let user = match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let email_scope = scope.includes("email");
+    Ok(Json(UserinfoResponse {
+        sub: user.id.as_ref().to_owned(),
+        email: email_scope.then(|| user.email.as_ref().expose_secret().to_owned()),
+        email_verified: email_scope.then_some(user.email_verified),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub scopes_supported: Vec<&'static str>,
+    pub response_types_supported: Vec<&'static str>,
+    pub grant_types_supported: Vec<&'static str>,
+    pub subject_types_supported: Vec<&'static str>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub token_endpoint_auth_methods_supported: Vec<&'static str>,
+    pub code_challenge_methods_supported: Vec<&'static str>,
+    pub claims_supported: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserinfoResponse {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
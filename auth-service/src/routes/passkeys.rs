@@ -0,0 +1,505 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, AuthenticationMethod, Email, LoginAttemptId, PasskeyAssertionCredential, PasskeyCeremony,
+        PasskeyChallenge, PasskeyChallengeStoreError, PasskeyCredential,
+        PasskeyRegistrationCredential, PasskeyStoreError, TwoFAMethod, User,
+    },
+    routes::{
+        check_login_lockout, complete_2fa_attempt, handle_no_2fa, issue_recovery_codes,
+        reauthenticate, record_failed_2fa_attempt, set_two_fa_method, Reauthentication,
+        ReauthenticationRequest, SecondFactorRequiredResponse, SessionOrigin,
+    },
+    utils::{
+        auth::get_authenticated_email,
+        constants::{PASSKEY_CHALLENGE_TTL_SECONDS, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID},
+    },
+};
+
+// Hands out the options for `navigator.credentials.create()`. Both steps of the
+// registration need the password and the current second factor, like TOTP enrollment.
+#[tracing::instrument(name = "Start Passkey Registration", skip_all)]
+pub async fn start_passkey_registration(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<ReauthenticationRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Keeps the same authenticator from being registered twice
+    let exclude_credentials = credential_descriptors(&email, &state).await?;
+
+    let challenge = PasskeyChallenge::default();
+    if let Err(e) = state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), PasskeyCeremony::Registration { email: email.clone() })
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let response = Json(PasskeyCreationOptionsResponse {
+        public_key: PasskeyCreationOptions {
+            challenge: challenge.as_ref().to_owned(),
+            rp: PasskeyRelyingParty {
+                id: WEBAUTHN_RP_ID.to_owned(),
+                name: WEBAUTHN_RP_NAME.to_owned(),
+            },
+            user: PasskeyUser {
+                id: URL_SAFE_NO_PAD.encode(user.id.as_ref()),
+                name: email.as_ref().expose_secret().to_owned(),
+                display_name: email.as_ref().expose_secret().to_owned(),
+            },
+            pub_key_cred_params: vec![PasskeyCredentialParameters {
+                credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+                alg: COSE_ALGORITHM_ES256,
+            }],
+            timeout: PASSKEY_CHALLENGE_TTL_SECONDS * 1000,
+            attestation: "none".to_owned(),
+            authenticator_selection: PasskeyAuthenticatorSelection {
+                resident_key: "required".to_owned(),
+                user_verification: "required".to_owned(),
+            },
+            exclude_credentials,
+        },
+    });
+
+    Ok((StatusCode::OK, response).into_response())
+}
+
+// Stores the new passkey and makes it the user's second factor, just like
+// confirming a TOTP enrollment does
+#[tracing::instrument(name = "Finish Passkey Registration", skip_all)]
+pub async fn finish_passkey_registration(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<FinishPasskeyRegistrationRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    // Checked before the challenge is taken, so it can be answered once the code is in
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    let request = request.credential;
+    let challenge = request.challenge().map_err(|_| AuthAPIError::InvalidCredentials)?;
+    match take_ceremony(&challenge, &state).await? {
+        PasskeyCeremony::Registration { email: expected } if expected == email => {}
+        _ => return Err(AuthAPIError::IncorrectCredentials),
+    }
+
+    let credential = request
+        .verify(&challenge, &email, &WEBAUTHN_RP_ID, &WEBAUTHN_ORIGIN)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    match state.passkey_store.write().await.add_credential(credential).await {
+        Ok(()) => {}
+        Err(PasskeyStoreError::CredentialAlreadyExists) => return Err(AuthAPIError::InvalidCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    set_two_fa_method(&email, TwoFAMethod::Passkey, &state).await?;
+
+    // Enrolling a new second factor comes with a fresh set of recovery codes
+    let recovery_codes = match issue_recovery_codes(&email, &state).await {
+        Ok(codes) => codes,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    let response = Json(PasskeyRegisteredResponse {
+        message: "Passkey registered".to_owned(),
+        recovery_codes,
+    });
+
+    Ok((StatusCode::OK, response).into_response())
+}
+
+// Hands out the options for `navigator.credentials.get()` without naming any
+// credentials, so the authenticator offers every passkey it holds for this site
+#[tracing::instrument(name = "Start Passkey Login", skip_all)]
+pub async fn start_passkey_login(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let challenge = PasskeyChallenge::default();
+    if let Err(e) = state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), PasskeyCeremony::Login)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok((StatusCode::OK, Json(request_options(challenge, Vec::new()))))
+}
+
+// A passkey checks both possession of the device and the user, so it stands in
+// for the password and the second factor alike
+#[tracing::instrument(name = "Finish Passkey Login", skip_all)]
+pub async fn finish_passkey_login(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    origin: SessionOrigin,
+    Json(request): Json<PasskeyAssertionCredential>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let challenge = match request.challenge() {
+        Ok(challenge) => challenge,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+    match take_ceremony(&challenge, &state).await {
+        Ok(PasskeyCeremony::Login) => {}
+        Ok(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(e)),
+    }
+
+    let credential = match verify_assertion(&request, &challenge, &state).await {
+        Ok(credential) => credential,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let user_store = state.user_store.read().await;
+    let user = match user_store.get_user(&credential.email).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Authenticators name the account they hold the passkey for, which has to be its owner
+    match request.user_handle() {
+        Ok(None) => {}
+        Ok(Some(user_handle)) if user_handle == user.id.as_ref() => {}
+        _ => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    }
+
+    if !user.email_verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
+    let token_version = match user_store.get_token_version(&user.id).await {
+        Ok(token_version) => token_version,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    handle_no_2fa(&user, token_version, &[AuthenticationMethod::Passkey], origin, &state, jar).await
+}
+
+// Hands out the options to answer the second step of a password login with a passkey
+#[tracing::instrument(name = "Start Passkey 2FA", skip_all)]
+pub async fn start_passkey_2fa(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<StartPasskey2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = check_login_attempt(request.email, request.login_attempt_id, &state).await?.email;
+
+    let allow_credentials = credential_descriptors(&email, &state).await?;
+
+    let challenge = PasskeyChallenge::default();
+    if let Err(e) = state
+        .passkey_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), PasskeyCeremony::SecondFactor { email })
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok((StatusCode::OK, Json(request_options(challenge, allow_credentials))))
+}
+
+#[tracing::instrument(name = "Verify Passkey 2FA", skip_all)]
+pub async fn verify_passkey_2fa(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    origin: SessionOrigin,
+    Json(request): Json<VerifyPasskey2FARequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let user = match check_login_attempt(request.email, request.login_attempt_id, &state).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let challenge = match request.credential.challenge() {
+        Ok(challenge) => challenge,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+    match take_ceremony(&challenge, &state).await {
+        Ok(PasskeyCeremony::SecondFactor { email: expected }) if expected == user.email => {}
+        Ok(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(e)),
+    }
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    match verify_assertion(&request.credential, &challenge, &state).await {
+        Ok(credential) if credential.email == user.email => {}
+        Ok(_) | Err(AuthAPIError::IncorrectCredentials) => {
+            let error = record_failed_2fa_attempt(&mut *two_fa_code_store, &user, &state).await;
+            return (jar, Err(error));
+        }
+        Err(e) => return (jar, Err(e)),
+    }
+
+    if let Err(e) = complete_2fa_attempt(&mut *two_fa_code_store, &user, &state).await {
+        return (jar, Err(e));
+    }
+    drop(two_fa_code_store);
+
+    let token_version = match state.user_store.read().await.get_token_version(&user.id).await {
+        Ok(token_version) => token_version,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    let methods = [AuthenticationMethod::Passkey, AuthenticationMethod::MultiFactor];
+    let (jar, result) = handle_no_2fa(&user, token_version, &methods, origin, &state, jar).await;
+    (jar, result.map(|(status, _)| status))
+}
+
+// Challenges complete a single ceremony, so they are removed as soon as they are used
+async fn take_ceremony(
+    challenge: &PasskeyChallenge,
+    state: &AppState,
+) -> Result<PasskeyCeremony, AuthAPIError> {
+    let mut passkey_challenge_store = state.passkey_challenge_store.write().await;
+    let ceremony = match passkey_challenge_store.get_ceremony(challenge).await {
+        Ok(ceremony) => ceremony,
+        Err(PasskeyChallengeStoreError::ChallengeNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    passkey_challenge_store
+        .remove_challenge(challenge)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(ceremony)
+}
+
+// Checks the assertion against the stored passkey and records its new sign count
+async fn verify_assertion(
+    assertion: &PasskeyAssertionCredential,
+    challenge: &PasskeyChallenge,
+    state: &AppState,
+) -> Result<PasskeyCredential, AuthAPIError> {
+    let mut passkey_store = state.passkey_store.write().await;
+    let credential = match passkey_store.get_credential(&assertion.id).await {
+        Ok(credential) => credential,
+        Err(PasskeyStoreError::CredentialNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let sign_count = assertion
+        .verify(challenge, &credential, &WEBAUTHN_RP_ID, &WEBAUTHN_ORIGIN)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    passkey_store
+        .update_sign_count(&credential.id, sign_count)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(credential)
+}
+
+// The passkey only replaces the second factor, the password step must have passed.
+// Returns the user the login attempt belongs to.
+async fn check_login_attempt(
+    email: String,
+    login_attempt_id: String,
+    state: &AppState,
+) -> Result<User, AuthAPIError> {
+    let email = Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let login_attempt_id =
+        LoginAttemptId::parse(login_attempt_id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Looked up before the code store is locked, login takes the same locks in this order
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.two_fa_method == TwoFAMethod::Passkey => user,
+        _ => return Err(AuthAPIError::IncorrectCredentials),
+    };
+    check_login_lockout(&user.id, state).await?;
+
+    match state.two_fa_code_store.read().await.get_code(&email).await {
+        Ok((stored_login_attempt_id, _)) if stored_login_attempt_id == login_attempt_id => {}
+        _ => return Err(AuthAPIError::IncorrectCredentials),
+    }
+
+    Ok(user)
+}
+
+async fn credential_descriptors(
+    email: &Email,
+    state: &AppState,
+) -> Result<Vec<PasskeyCredentialDescriptor>, AuthAPIError> {
+    let credentials = state
+        .passkey_store
+        .read()
+        .await
+        .get_user_credentials(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(credentials
+        .into_iter()
+        .map(|credential| PasskeyCredentialDescriptor {
+            credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+            id: credential.id,
+        })
+        .collect())
+}
+
+fn request_options(
+    challenge: PasskeyChallenge,
+    allow_credentials: Vec<PasskeyCredentialDescriptor>,
+) -> PasskeyRequestOptionsResponse {
+    PasskeyRequestOptionsResponse {
+        public_key: PasskeyRequestOptions {
+            challenge: challenge.as_ref().to_owned(),
+            rp_id: WEBAUTHN_RP_ID.to_owned(),
+            timeout: PASSKEY_CHALLENGE_TTL_SECONDS * 1000,
+            user_verification: "required".to_owned(),
+            allow_credentials,
+        },
+    }
+}
+
+const WEBAUTHN_RP_NAME: &str = "Auth Service";
+const PUBLIC_KEY_CREDENTIAL_TYPE: &str = "public-key";
+const COSE_ALGORITHM_ES256: i64 = -7;
+
+#[derive(Deserialize)]
+pub struct FinishPasskeyRegistrationRequest {
+    pub password: Secret<String>,
+    #[serde(rename = "2FACode")]
+    pub code: Option<String>,
+    pub credential: PasskeyRegistrationCredential,
+}
+
+#[derive(Deserialize)]
+pub struct StartPasskey2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPasskey2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    pub credential: PasskeyAssertionCredential,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasskeyRegisteredResponse {
+    pub message: String,
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+// Options are wrapped the way the browser APIs expect them
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasskeyCreationOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: PasskeyCreationOptions,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyCreationOptions {
+    pub challenge: String,
+    pub rp: PasskeyRelyingParty,
+    pub user: PasskeyUser,
+    pub pub_key_cred_params: Vec<PasskeyCredentialParameters>,
+    // Milliseconds
+    pub timeout: u64,
+    pub attestation: String,
+    pub authenticator_selection: PasskeyAuthenticatorSelection,
+    pub exclude_credentials: Vec<PasskeyCredentialDescriptor>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasskeyRequestOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: PasskeyRequestOptions,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    // Milliseconds
+    pub timeout: u64,
+    pub user_verification: String,
+    pub allow_credentials: Vec<PasskeyCredentialDescriptor>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasskeyRelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyUser {
+    // Base64url encoded user id, handed back as the user handle of assertions
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasskeyCredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyAuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasskeyCredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, Password, PasswordResetToken, PasswordResetTokenStoreError,
+        UserStoreError,
+    },
+    routes::end_all_sessions,
+};
+
+#[tracing::instrument(name = "Request Password Reset", skip_all)]
+pub async fn request_password_reset(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PasswordResetRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = match Email::parse(Secret::new(request.email)) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    // Respond the same way whether or not the account exists,
+    // so this route cannot be used to find out who is registered
+    let response = Json(PasswordResetResponse {
+        message: "If the account exists, a password reset email has been sent".to_owned(),
+    });
+
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let token = PasswordResetToken::default();
+
+    if let Err(e) = state
+        .password_reset_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let email_client = state.email_client.read().await;
+    if let Err(e) = email_client
+        .send_email(
+            &email,
+            "Reset your password",
+            &format!(
+                "Your password reset token is: {}. It expires in 15 minutes. If you did not request a password reset, you can ignore this email.",
+                token.as_ref().expose_secret()
+            ),
+        )
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Confirm Password Reset", skip_all)]
+pub async fn confirm_password_reset(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PasswordResetConfirmRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = match PasswordResetToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    let password = match Password::parse(request.new_password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let mut password_reset_token_store = state.password_reset_token_store.write().await;
+
+    let email = match password_reset_token_store.get_email(&token).await {
+        Ok(email) => email,
+        Err(PasswordResetTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Burn the token before doing anything else so it can only ever be used once
+    if let Err(e) = password_reset_token_store.remove_token(&token).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(password_reset_token_store);
+
+    let mut user_store = state.user_store.write().await;
+    let user_id = match user_store.get_user(&email).await {
+        Ok(user) => user.id,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if let Err(e) = user_store.update_password(&email, password).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(user_store);
+
+    // Log the user out everywhere, whoever knew the old password must not keep a session.
+    // Updating the password already bumped the token version, ending their auth tokens.
+    end_all_sessions(&user_id, &state).await?;
+
+    let response = Json(PasswordResetResponse {
+        message: "Password has been reset".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirmRequest {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
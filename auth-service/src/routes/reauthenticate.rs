@@ -0,0 +1,158 @@
+use axum::{http::StatusCode, response::{IntoResponse, Response}, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode, TwoFAMethod, UserId},
+    routes::{check_login_lockout, complete_2fa_attempt, record_failed_2fa_attempt, verify_totp_code},
+};
+
+// Outcome of asking a logged in user to confirm a sensitive action
+pub(crate) enum Reauthentication {
+    Confirmed,
+    // The password was correct, but the action also needs a code for the user's second
+    // factor. Emailed codes have just been sent, TOTP codes come from the authenticator app.
+    SecondFactorRequired(TwoFAMethod),
+}
+
+// Checks the password of a logged in user and, when 2FA is enabled, a fresh second
+// factor code. Calling this without a code sends an emailed code if the user needs one.
+// Wrong passwords and codes count towards the same lockout as failed logins.
+#[tracing::instrument(name = "Reauthenticate", skip_all)]
+pub(crate) async fn reauthenticate(
+    email: &Email,
+    password: Secret<String>,
+    code: Option<String>,
+    state: &AppState,
+) -> Result<Reauthentication, AuthAPIError> {
+    // A password that does not even pass the rules cannot be the right one
+    let password = Password::parse(password).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    let user_store = state.user_store.read().await;
+    let user = user_store
+        .get_user(email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    check_login_lockout(&user.id, state).await?;
+
+    if user_store.validate_user(email, &password).await.is_err() {
+        drop(user_store);
+        state
+            .login_attempt_store
+            .write()
+            .await
+            .record_failure(&user.id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    let two_fa_method = match user.two_fa_method {
+        // A passkey assertion can not be sent along with these requests, so users
+        // with a passkey confirm sensitive actions with an emailed code instead
+        TwoFAMethod::Passkey => TwoFAMethod::Email,
+        method => method,
+    };
+    drop(user_store);
+
+    let code = match (two_fa_method, code) {
+        (TwoFAMethod::None, _) => {
+            reset_failed_logins(&user.id, state).await?;
+            return Ok(Reauthentication::Confirmed);
+        }
+        (_, None) => {
+            if two_fa_method == TwoFAMethod::Email {
+                send_two_fa_code(email, state).await?;
+            }
+            return Ok(Reauthentication::SecondFactorRequired(two_fa_method));
+        }
+        (_, Some(code)) => TwoFACode::parse(code).map_err(|_| AuthAPIError::InvalidCredentials)?,
+    };
+
+    match two_fa_method {
+        TwoFAMethod::Totp => {
+            if !verify_totp_code(email, &code, state).await? {
+                let mut two_fa_code_store = state.two_fa_code_store.write().await;
+                return Err(record_failed_2fa_attempt(&mut *two_fa_code_store, &user, state).await);
+            }
+            reset_failed_logins(&user.id, state).await?;
+        }
+        _ => {
+            // Codes of login attempts are not accepted here and the other way round
+            let mut reauthentication_code_store = state.reauthentication_code_store.write().await;
+            let (_, expected_code) = reauthentication_code_store
+                .get_code(email)
+                .await
+                .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+            if expected_code != code {
+                return Err(
+                    record_failed_2fa_attempt(&mut *reauthentication_code_store, &user, state).await
+                );
+            }
+            // Each emailed code confirms a single action
+            complete_2fa_attempt(&mut *reauthentication_code_store, &user, state).await?;
+        }
+    }
+
+    Ok(Reauthentication::Confirmed)
+}
+
+async fn reset_failed_logins(user_id: &UserId, state: &AppState) -> Result<(), AuthAPIError> {
+    state
+        .login_attempt_store
+        .write()
+        .await
+        .reset(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+async fn send_two_fa_code(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    let two_fa_code = TwoFACode::default();
+    state
+        .reauthentication_code_store
+        .write()
+        .await
+        .add_code(email.clone(), LoginAttemptId::default(), two_fa_code.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .email_client
+        .read()
+        .await
+        .send_email(
+            email,
+            "Your 2FA code",
+            &format!("Your 2FA code is: {}", two_fa_code.as_ref()),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+// Body of requests that only need a logged in user to confirm who they are
+#[derive(Deserialize)]
+pub struct ReauthenticationRequest {
+    pub password: Secret<String>,
+    #[serde(rename = "2FACode")]
+    pub code: Option<String>,
+}
+
+// Tells the client to repeat the request with a code for the user's second factor
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SecondFactorRequiredResponse {
+    pub message: String,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
+}
+
+impl SecondFactorRequiredResponse {
+    pub(crate) fn into_response(two_fa_method: TwoFAMethod) -> Response {
+        let body = Json(Self {
+            message: "2FA required".to_owned(),
+            two_fa_method,
+        });
+        (StatusCode::PARTIAL_CONTENT, body).into_response()
+    }
+}
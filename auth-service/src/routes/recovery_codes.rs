@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, AuthenticationMethod, Email, LoginAttemptId, RecoveryCode, RecoveryCodeStoreError,
+    },
+    routes::{
+        check_login_lockout, complete_2fa_attempt, reauthenticate, record_failed_2fa_attempt,
+        start_session, Reauthentication, ReauthenticationRequest, SecondFactorRequiredResponse,
+        SessionOrigin,
+    },
+    utils::{
+        auth::{generate_refresh_cookie, generate_user_auth_cookie, get_authenticated_email},
+        constants::RECOVERY_CODE_COUNT,
+    },
+};
+
+#[tracing::instrument(name = "Regenerate Recovery Codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<ReauthenticationRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    // Recovery codes stand in for the second factor, so handing out new ones needs it too
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    let recovery_codes = match issue_recovery_codes(&email, &state).await {
+        Ok(codes) => codes,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse { recovery_codes })).into_response())
+}
+
+// Second login step for users who lost access to their 2FA method:
+// a recovery code stands in for the 2FA code and is burned on use
+#[tracing::instrument(name = "Verify Recovery Code", skip_all)]
+pub async fn verify_recovery_code(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    origin: SessionOrigin,
+    Json(request): Json<VerifyRecoveryCodeRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match Email::parse(Secret::new(request.email)) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let login_attempt_id = match LoginAttemptId::parse(request.login_attempt_id) {
+        Ok(login_attempt_id) => login_attempt_id,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let recovery_code = match RecoveryCode::parse(request.recovery_code) {
+        Ok(recovery_code) => recovery_code,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // Looked up before the code store is locked, login takes the same locks in this order
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    if let Err(e) = check_login_lockout(&user.id, &state).await {
+        return (jar, Err(e));
+    }
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    // The recovery code only replaces the second factor, the password step must have passed
+    match two_fa_code_store.get_code(&email).await {
+        Ok((stored_login_attempt_id, _)) if stored_login_attempt_id == login_attempt_id => {}
+        _ => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    }
+
+    match state
+        .recovery_code_store
+        .write()
+        .await
+        .use_code(&email, &recovery_code)
+        .await
+    {
+        Ok(()) => (),
+        Err(RecoveryCodeStoreError::CodeNotFound) => {
+            let error = record_failed_2fa_attempt(&mut *two_fa_code_store, &user, &state).await;
+            return (jar, Err(error));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    if let Err(e) = complete_2fa_attempt(&mut *two_fa_code_store, &user, &state).await {
+        return (jar, Err(e));
+    }
+    drop(two_fa_code_store);
+
+    let methods = [AuthenticationMethod::OneTimeCode, AuthenticationMethod::MultiFactor];
+    let family_id = match start_session(&user.id, &methods, origin, &state).await {
+        Ok(session_id) => session_id,
+        Err(e) => return (jar, Err(e)),
+    };
+    let auth_cookie = match generate_user_auth_cookie(
+        &user.id,
+        &family_id,
+        state.user_store.clone(),
+        state.role_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let refresh_cookie = match generate_refresh_cookie(
+        &user.id,
+        family_id,
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+// Generates a fresh set of recovery codes for the user, replacing any previous set,
+// and returns them in clear so they can be shown to the user this one time
+#[tracing::instrument(name = "Issue Recovery Codes", skip_all)]
+pub(crate) async fn issue_recovery_codes(email: &Email, state: &AppState) -> Result<Vec<String>> {
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| RecoveryCode::default())
+        .collect();
+    let recovery_codes = codes
+        .iter()
+        .map(|code| code.as_ref().expose_secret().to_owned())
+        .collect();
+
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(email, codes)
+        .await?;
+
+    Ok(recovery_codes)
+}
+
+#[derive(Deserialize)]
+pub struct VerifyRecoveryCodeRequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    #[serde(rename = "recoveryCode")]
+    pub recovery_code: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
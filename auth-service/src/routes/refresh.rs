@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, RefreshToken, RefreshTokenRecord, RefreshTokenStoreError, Session,
+        SessionStoreError,
+    },
+    routes::end_session,
+    utils::{
+        auth::{generate_refresh_cookie, generate_user_auth_cookie},
+        constants::REFRESH_TOKEN_COOKIE_NAME,
+    },
+};
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match RefreshToken::parse(cookie.value().to_owned()) {
+        Ok(token) => token,
+        Err(_) => return (jar.remove(REFRESH_TOKEN_COOKIE_NAME), Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Sessions of OAuth clients are only refreshed through the token endpoint
+    let record = match rotate_refresh_token(&token, None, &state).await {
+        Ok((record, _)) => record,
+        Err(AuthAPIError::InvalidToken) => {
+            return (jar.remove(REFRESH_TOKEN_COOKIE_NAME), Err(AuthAPIError::InvalidToken))
+        }
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let refresh_cookie = match generate_refresh_cookie(
+        &record.user_id,
+        record.family_id.clone(),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let auth_cookie = match generate_user_auth_cookie(
+        &record.user_id,
+        &record.family_id,
+        state.user_store.clone(),
+        state.role_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+// Marks the refresh token used so a new one can be issued in its family, and returns
+// the session it extends. The session has to belong to the given OAuth client, or to
+// none for logins to this service. Fails with `InvalidToken` for tokens that can not
+// be refreshed.
+pub(crate) async fn rotate_refresh_token(
+    token: &RefreshToken,
+    client_id: Option<&str>,
+    state: &AppState,
+) -> Result<(RefreshTokenRecord, Session), AuthAPIError> {
+    let mut refresh_token_store = state.refresh_token_store.write().await;
+
+    let record = match refresh_token_store.get_token(token).await {
+        Ok(record) => record,
+        Err(RefreshTokenStoreError::RefreshTokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match refresh_token_store.is_family_revoked(&record.family_id).await {
+        Ok(false) => {}
+        Ok(true) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // A token that was already rotated is being replayed, so either the
+    // legitimate client or an attacker holds a stolen copy. Kill the whole family.
+    if record.used {
+        tracing::warn!("refresh token reuse detected, revoking token family");
+        drop(refresh_token_store);
+        end_session(&record.family_id, state).await?;
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    // The session may have been revoked from another device
+    let mut session_store = state.session_store.write().await;
+    let session = match session_store.get_session(&record.family_id).await {
+        Ok(session) => session,
+        Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if session.client_id.as_deref() != client_id {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    if let Err(e) = session_store.touch_session(&record.family_id).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(session_store);
+
+    if let Err(e) = refresh_token_store.mark_token_used(token).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok((record, session))
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Form,
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, RefreshToken, RefreshTokenStoreError, TokenFamilyId},
+    routes::end_session,
+    utils::auth::{authenticate_service, validate_token},
+};
+
+// Token revocation for backend services (RFC 7009). Accepts an auth token or a refresh
+// token, either way the whole login session it belongs to is ended.
+#[tracing::instrument(name = "Revoke", skip_all)]
+pub async fn revoke(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(request): Form<RevocationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate_service(&headers, state.client_store.clone()).await?;
+
+    // Tokens that are already invalid need no revoking, which is not an error (RFC 7009 section 2.2)
+    let family_id = match RefreshToken::parse(request.token.clone()) {
+        Ok(refresh_token) => match state.refresh_token_store.read().await.get_token(&refresh_token).await {
+            Ok(record) => Some(record.family_id),
+            Err(RefreshTokenStoreError::RefreshTokenNotFound) => None,
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        },
+        Err(_) => match validate_token(
+            &request.token,
+            state.banned_token_store.clone(),
+            state.session_store.clone(),
+            state.user_store.clone(),
+        )
+        .await
+        {
+            Ok(claims) => {
+                state
+                    .banned_token_store
+                    .write()
+                    .await
+                    .store_token(claims.jti)
+                    .await
+                    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+                claims.sid.and_then(|sid| TokenFamilyId::parse(sid).ok())
+            }
+            Err(_) => None,
+        },
+    };
+
+    if let Some(family_id) = family_id {
+        end_session(&family_id, &state).await?;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct RevocationRequest {
+    pub token: String,
+}
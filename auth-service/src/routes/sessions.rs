@@ -0,0 +1,255 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, State},
+    http::{header, request::Parts, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, AuthenticationMethod, Scope, Session, SessionStoreError, TokenFamilyId, User, UserId},
+    utils::auth::{get_authenticated_claims, get_subject_user},
+};
+
+#[tracing::instrument(name = "List Sessions", skip_all)]
+pub async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, current_session_id) = get_authenticated_session(&jar, &state).await?;
+
+    let mut sessions: Vec<SessionResponse> = state
+        .session_store
+        .read()
+        .await
+        .get_user_sessions(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|session| SessionResponse::new(session, &current_session_id))
+        .collect();
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen));
+
+    Ok(Json(sessions))
+}
+
+#[tracing::instrument(name = "Revoke Session", skip_all)]
+pub async fn revoke_session(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<RevokeSessionRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = get_authenticated_session(&jar, &state).await?;
+
+    let session_id =
+        TokenFamilyId::parse(request.session_id).map_err(|_| AuthAPIError::SessionNotFound)?;
+
+    // Sessions of other users are treated as missing, so their ids can not be probed
+    let session = match state.session_store.read().await.get_session(&session_id).await {
+        Ok(session) if session.user_id == user.id => session,
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    end_session(&session.id, &state).await?;
+    Ok(StatusCode::OK)
+}
+
+#[tracing::instrument(name = "Revoke Other Sessions", skip_all)]
+pub async fn revoke_other_sessions(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, current_session_id) = get_authenticated_session(&jar, &state).await?;
+    end_other_sessions(&user.id, &current_session_id, &state).await?;
+    Ok(StatusCode::OK)
+}
+
+// Where a login came from, recorded with the session it starts
+pub struct SessionOrigin {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SessionOrigin
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let ip_address = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip().to_string());
+        Ok(Self { user_agent, ip_address })
+    }
+}
+
+// Records a new session for a completed login. Its id is used for the refresh token
+// family issued with it and is embedded in the auth tokens of the session.
+pub(crate) async fn start_session(
+    user_id: &UserId,
+    methods: &[AuthenticationMethod],
+    origin: SessionOrigin,
+    state: &AppState,
+) -> Result<TokenFamilyId, AuthAPIError> {
+    let session = Session::new(user_id.clone(), origin.user_agent, origin.ip_address)
+        .with_methods(methods);
+    add_session(session, state).await
+}
+
+// Records a session for tokens issued to an OAuth client. It is listed and revoked like
+// any other, and the granted scope is kept with it for refreshed access tokens.
+pub(crate) async fn start_client_session(
+    user_id: &UserId,
+    origin: SessionOrigin,
+    client_id: &str,
+    scope: Scope,
+    methods: &[AuthenticationMethod],
+    state: &AppState,
+) -> Result<TokenFamilyId, AuthAPIError> {
+    let session = Session::new(user_id.clone(), origin.user_agent, origin.ip_address)
+        .with_client(client_id.to_owned(), scope)
+        .with_methods(methods);
+    add_session(session, state).await
+}
+
+async fn add_session(session: Session, state: &AppState) -> Result<TokenFamilyId, AuthAPIError> {
+    let session_id = session.id.clone();
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(session_id)
+}
+
+// Revokes the session, which invalidates its auth tokens and its refresh token family
+pub(crate) async fn end_session(session_id: &TokenFamilyId, state: &AppState) -> Result<(), AuthAPIError> {
+    state
+        .session_store
+        .write()
+        .await
+        .remove_session(session_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_family(session_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Revokes every session of the user except the given one
+pub(crate) async fn end_other_sessions(
+    user_id: &UserId,
+    current_session_id: &TokenFamilyId,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_user_sessions(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    for session in sessions.iter().filter(|session| &session.id != current_session_id) {
+        end_session(&session.id, state).await?;
+    }
+    Ok(())
+}
+
+// Revokes every session of the user along with all of their refresh token families
+pub(crate) async fn end_all_sessions(user_id: &UserId, state: &AppState) -> Result<(), AuthAPIError> {
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_user_sessions(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    for session in sessions {
+        end_session(&session.id, state).await?;
+    }
+    // Families issued before sessions were tracked have no session to end them with
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_user_families(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+pub(crate) async fn get_authenticated_session(
+    jar: &CookieJar,
+    state: &AppState,
+) -> Result<(User, TokenFamilyId), AuthAPIError> {
+    let claims = get_authenticated_claims(
+        jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+    let user = get_subject_user(&claims, state.user_store.clone()).await?;
+    let session_id = claims
+        .sid
+        .and_then(|sid| TokenFamilyId::parse(sid).ok())
+        .ok_or(AuthAPIError::InvalidToken)?;
+    Ok((user, session_id))
+}
+
+#[derive(Deserialize)]
+pub struct RevokeSessionRequest {
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "lastSeen")]
+    pub last_seen: i64,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    // OAuth client the session was started for, none for logins to this service
+    #[serde(rename = "clientId", skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // Whether this is the session the request was made from
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: Session, current_session_id: &TokenFamilyId) -> Self {
+        Self {
+            current: session.id == *current_session_id,
+            id: session.id.as_ref().to_owned(),
+            created_at: session.created_at,
+            last_seen: session.last_seen,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            client_id: session.client_id,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{app_state::AppState, domain::*};
+use crate::{app_state::AppState, domain::*, routes::{issue_recovery_codes, send_verification_email}};
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use color_eyre::eyre::Result;
 use serde::{Deserialize, Serialize};
@@ -23,7 +23,13 @@ pub async fn signup(
         Err(_) => return Err(AuthAPIError::InvalidCredentials)
     };
 
-    let user = User::new(email, password, request.requires_2fa);
+    // Signup only offers emailed codes, TOTP is enrolled later from a logged in session
+    let two_fa_method = match request.requires_2fa {
+        true => TwoFAMethod::Email,
+        false => TwoFAMethod::None,
+    };
+
+    let user = User::new(email, password, two_fa_method);
 
     let mut user_store = state.user_store.write().await;
     
@@ -32,13 +38,37 @@ pub async fn signup(
         Err(_) => {} // User doesn't exist, continue with signup
     }
 
+    let email = user.email.clone();
+    let requires_2fa = user.two_fa_method != TwoFAMethod::None;
+
     match user_store.add_user(user).await {
         Ok(_) => {}
         Err(e) => return Err(AuthAPIError::UnexpectedError(e.into()))
     }
+    drop(user_store);
+
+    // The account already exists at this point, so a failed send is logged rather than
+    // surfaced; the user can ask for a new link through /verify-email/resend
+    if let Err(e) = send_verification_email(&email, &state).await {
+        tracing::error!("Failed to send verification email: {:?}", e);
+    }
+
+    // Like the verification email, a failure here should not fail the signup,
+    // the user can get a new set through /recovery-codes/regenerate
+    let recovery_codes = match requires_2fa {
+        true => match issue_recovery_codes(&email, &state).await {
+            Ok(codes) => Some(codes),
+            Err(e) => {
+                tracing::error!("Failed to issue recovery codes: {:?}", e);
+                None
+            }
+        },
+        false => None,
+    };
 
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
 
     Ok((StatusCode::CREATED, response))
@@ -55,4 +85,7 @@ pub struct SignupRequest {
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct SignupResponse {
     pub message: String,
+    // Only present when the user signed up with 2FA
+    #[serde(rename = "recoveryCodes", default, skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
 }
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, TotpSecret, TotpSecretStoreError, TwoFACode, TwoFAMethod},
+    routes::{
+        issue_recovery_codes, reauthenticate, set_two_fa_method, Reauthentication,
+        ReauthenticationRequest, SecondFactorRequiredResponse,
+    },
+    utils::{auth::get_authenticated_email, constants::TOTP_SKEW_STEPS},
+};
+
+// Both steps of the enrollment need the password and the current second factor, a
+// stolen session alone must not be able to replace the user's second factor
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<ReauthenticationRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    // The secret only becomes active once the user confirms it with a code
+    let secret = TotpSecret::default();
+    let otpauth_uri = match secret.provisioning_uri(&email) {
+        Ok(uri) => uri,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    if let Err(e) = state
+        .totp_secret_store
+        .write()
+        .await
+        .set_pending_secret(&email, secret.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let response = Json(TotpEnrollmentResponse {
+        secret: secret.as_ref().expose_secret().to_owned(),
+        otpauth_uri,
+    });
+
+    Ok((StatusCode::OK, response).into_response())
+}
+
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<TotpConfirmRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.current_code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    let code = match TwoFACode::parse(request.code) {
+        Ok(code) => code,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let mut totp_secret_store = state.totp_secret_store.write().await;
+
+    let secret = match totp_secret_store.get_pending_secret(&email).await {
+        Ok(secret) => secret,
+        Err(TotpSecretStoreError::SecretNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let step = match secret.verify(&code, *TOTP_SKEW_STEPS) {
+        Ok(Some(step)) => step,
+        Ok(None) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    if let Err(e) = totp_secret_store.confirm_pending_secret(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    // The code that confirmed the app can not be used to log in afterwards
+    if let Err(e) = totp_secret_store.record_used_step(&email, step).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(totp_secret_store);
+
+    set_two_fa_method(&email, TwoFAMethod::Totp, &state).await?;
+
+    // Enrolling a new second factor comes with a fresh set of recovery codes
+    let recovery_codes = match issue_recovery_codes(&email, &state).await {
+        Ok(codes) => codes,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    let response = Json(TotpConfirmResponse {
+        message: "TOTP 2FA enabled".to_owned(),
+        recovery_codes,
+    });
+
+    Ok((StatusCode::OK, response).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct TotpConfirmRequest {
+    pub password: Secret<String>,
+    // A code for the second factor the user has now, if any
+    #[serde(rename = "currentCode")]
+    pub current_code: Option<String>,
+    // A code from the authenticator app being enrolled
+    #[serde(rename = "2FACode")]
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TotpConfirmResponse {
+    pub message: String,
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, TwoFAMethod},
+    routes::{issue_recovery_codes, reauthenticate, Reauthentication, SecondFactorRequiredResponse},
+    utils::auth::get_authenticated_email,
+};
+
+// Turns on emailed 2FA codes. Users who use TOTP switch to emailed codes,
+// which needs a TOTP code just like any other change to an enabled second factor.
+#[tracing::instrument(name = "Enable 2FA", skip_all)]
+pub async fn enable_2fa(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<TwoFAToggleRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    set_two_fa_method(&email, TwoFAMethod::Email, &state).await?;
+
+    // Enabling a second factor comes with a fresh set of recovery codes
+    let recovery_codes = match issue_recovery_codes(&email, &state).await {
+        Ok(codes) => codes,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    let response = Json(TwoFAEnabledResponse {
+        message: "Email 2FA enabled".to_owned(),
+        recovery_codes,
+    });
+
+    Ok((StatusCode::OK, response).into_response())
+}
+
+#[tracing::instrument(name = "Disable 2FA", skip_all)]
+pub async fn disable_2fa(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Json(request): Json<TwoFAToggleRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = get_authenticated_email(
+        &jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await?;
+
+    if let Reauthentication::SecondFactorRequired(two_fa_method) =
+        reauthenticate(&email, request.password, request.code, &state).await?
+    {
+        return Ok(SecondFactorRequiredResponse::into_response(two_fa_method));
+    }
+
+    set_two_fa_method(&email, TwoFAMethod::None, &state).await?;
+
+    // Recovery codes stand in for the second factor, without one they must not work either
+    if let Err(e) = state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(&email, Vec::new())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    // Enabling 2FA again must not bring back an old authenticator app or passkey, and
+    // passkeys would otherwise still log the user in without their password
+    if let Err(e) = state.totp_secret_store.write().await.delete_secret(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    if let Err(e) = state.passkey_store.write().await.delete_user_credentials(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok(StatusCode::OK.into_response())
+}
+
+// Switches the second factor the user has to provide on login
+pub(crate) async fn set_two_fa_method(
+    email: &Email,
+    two_fa_method: TwoFAMethod,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+    let mut user = user_store
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    user.two_fa_method = two_fa_method;
+    user_store
+        .update_user(&user)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+#[derive(Deserialize)]
+pub struct TwoFAToggleRequest {
+    pub password: Secret<String>,
+    #[serde(rename = "2FACode")]
+    pub code: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TwoFAEnabledResponse {
+    pub message: String,
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
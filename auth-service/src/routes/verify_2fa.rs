@@ -6,14 +6,22 @@ use secrecy::Secret;
 use std::sync::Arc;
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, AuthenticationMethod, Email, LoginAttemptId, TotpSecretStoreError, TwoFACode,
+        TwoFACodeStore, TwoFACodeStoreError, TwoFAMethod, User,
+    },
+    routes::{check_login_lockout, start_session, SessionOrigin},
+    utils::{
+        auth::{generate_refresh_cookie, generate_user_auth_cookie},
+        constants::TOTP_SKEW_STEPS,
+    },
 };
 
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn verify_2fa(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    origin: SessionOrigin,
     Json(request): Json<Verify2FARequest>,
 ) -> impl IntoResponse {
     let email = match Email::parse(Secret::new(request.email)) {
@@ -31,6 +39,17 @@ pub async fn verify_2fa(
         Err(_) => return (jar, AuthAPIError::InvalidCredentials.into_response()),
     };
 
+    // Looked up before the code store is locked, login takes the same locks in this order
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, AuthAPIError::IncorrectCredentials.into_response()),
+    };
+
+    // Wrong second factors lock the account like wrong passwords
+    if let Err(e) = check_login_lockout(&user.id, &state).await {
+        return (jar, e.into_response());
+    }
+
     let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
     let code_tuple = match two_fa_code_store
@@ -41,26 +60,128 @@ pub async fn verify_2fa(
         Err(_) => return (jar, AuthAPIError::IncorrectCredentials.into_response()),
     };
 
-    if code_tuple.0 != login_attempt_id || code_tuple.1 != two_fa_code {
+    if code_tuple.0 != login_attempt_id {
         return (jar, AuthAPIError::IncorrectCredentials.into_response());
     }
 
-    if let Err(e) = two_fa_code_store
-        .remove_code(&email)
-        .await
-    {
-        return (jar, AuthAPIError::UnexpectedError(e.into()).into_response())
+    let code_is_valid = match user.two_fa_method {
+        TwoFAMethod::Totp => match verify_totp_code(&email, &two_fa_code, &state).await {
+            Ok(is_valid) => is_valid,
+            Err(e) => return (jar, e.into_response()),
+        },
+        // Passkey users answer with an assertion at /verify-2fa/passkey, never with a code
+        TwoFAMethod::Passkey => false,
+        _ => code_tuple.1 == two_fa_code,
+    };
+
+    if !code_is_valid {
+        let error = record_failed_2fa_attempt(&mut *two_fa_code_store, &user, &state).await;
+        return (jar, error.into_response());
     }
 
-    let auth_cookie = match generate_auth_cookie(&email) {
+    if let Err(e) = complete_2fa_attempt(&mut *two_fa_code_store, &user, &state).await {
+        return (jar, e.into_response());
+    }
+    drop(two_fa_code_store);
+
+    let methods = [AuthenticationMethod::OneTimeCode, AuthenticationMethod::MultiFactor];
+    let family_id = match start_session(&user.id, &methods, origin, &state).await {
+        Ok(session_id) => session_id,
+        Err(e) => return (jar, e.into_response()),
+    };
+    let auth_cookie = match generate_user_auth_cookie(
+        &user.id,
+        &family_id,
+        state.user_store.clone(),
+        state.role_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, AuthAPIError::UnexpectedError(e).into_response()),
+    };
+
+    let refresh_cookie = match generate_refresh_cookie(
+        &user.id,
+        family_id,
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
         Ok(cookie) => cookie,
-        Err(e) => return (jar, AuthAPIError::UnexpectedError(e.into()).into_response()),
+        Err(e) => return (jar, AuthAPIError::UnexpectedError(e).into_response()),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
     (updated_jar, StatusCode::OK.into_response())
 }
 
+// Checks a code from the user's authenticator app and uses it up, so a code that was
+// accepted once counts as wrong when it comes again
+pub(crate) async fn verify_totp_code(
+    email: &Email,
+    code: &TwoFACode,
+    state: &AppState,
+) -> Result<bool, AuthAPIError> {
+    let secret = state
+        .totp_secret_store
+        .read()
+        .await
+        .get_secret(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let step = match secret.verify(code, *TOTP_SKEW_STEPS) {
+        Ok(Some(step)) => step,
+        Ok(None) => return Ok(false),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    match state.totp_secret_store.write().await.record_used_step(email, step).await {
+        Ok(()) => Ok(true),
+        Err(TotpSecretStoreError::StepAlreadyUsed) => Ok(false),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Counts a wrong second factor against the user and picks the error to return,
+// which tells the client to start over once the attempt has been discarded
+pub(crate) async fn record_failed_2fa_attempt(
+    two_fa_code_store: &mut (dyn TwoFACodeStore + Send + Sync),
+    user: &User,
+    state: &AppState,
+) -> AuthAPIError {
+    if let Err(e) = state.login_attempt_store.write().await.record_failure(&user.id).await {
+        return AuthAPIError::UnexpectedError(e.into());
+    }
+    match two_fa_code_store.record_failed_attempt(&user.email).await {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            AuthAPIError::IncorrectCredentials
+        }
+        Err(TwoFACodeStoreError::TooManyAttempts) => AuthAPIError::TooMany2FAAttempts,
+        Err(e) => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// Only a successful second factor ends the login attempt and forgets earlier failures,
+// the password step alone does not
+pub(crate) async fn complete_2fa_attempt(
+    two_fa_code_store: &mut (dyn TwoFACodeStore + Send + Sync),
+    user: &User,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    two_fa_code_store
+        .remove_code(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .login_attempt_store
+        .write()
+        .await
+        .reset(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
 #[derive(Deserialize)]
 pub struct Verify2FARequest {
     email: String,
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, EmailVerificationToken, EmailVerificationTokenStoreError,
+        UserStoreError,
+    },
+    utils::constants::AUTH_SERVICE_URL,
+};
+
+#[tracing::instrument(name = "Verify Email", skip_all)]
+pub async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = match EmailVerificationToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    let mut email_verification_token_store = state.email_verification_token_store.write().await;
+
+    let email = match email_verification_token_store.get_email(&token).await {
+        Ok(email) => email,
+        Err(EmailVerificationTokenStoreError::TokenNotFound) => {
+            return Err(AuthAPIError::InvalidToken)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if let Err(e) = email_verification_token_store.remove_token(&token).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+    drop(email_verification_token_store);
+
+    match state.user_store.write().await.mark_email_verified(&email).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Resend Verification Email", skip_all)]
+pub async fn resend_verification_email(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ResendVerificationEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = match Email::parse(Secret::new(request.email)) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    // Throttle before looking the user up, so unknown addresses are throttled
+    // the same way and the route does not reveal who is registered
+    match state
+        .email_verification_token_store
+        .write()
+        .await
+        .register_resend(&email)
+        .await
+    {
+        Ok(_) => {}
+        Err(EmailVerificationTokenStoreError::ResendThrottled) => {
+            return Err(AuthAPIError::TooManyRequests)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "If the account exists and is unverified, a verification email has been sent"
+            .to_owned(),
+    });
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if user.email_verified {
+        return Ok((StatusCode::OK, response));
+    }
+
+    if let Err(e) = send_verification_email(&email, &state).await {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    Ok((StatusCode::OK, response))
+}
+
+// Issues a new verification token for the email and sends the verification link to it
+#[tracing::instrument(name = "Send Verification Email", skip_all)]
+pub(crate) async fn send_verification_email(email: &Email, state: &AppState) -> Result<()> {
+    let token = EmailVerificationToken::default();
+
+    state
+        .email_verification_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await?;
+
+    let link = format!(
+        "{}/verify-email?token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.as_ref().expose_secret()
+    );
+
+    state
+        .email_client
+        .read()
+        .await
+        .send_email(
+            email,
+            "Verify your email address",
+            &format!(
+                "Please confirm your email address by opening this link: {} The link expires in 24 hours.",
+                link
+            ),
+        )
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationEmailRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
@@ -6,8 +6,8 @@ use axum_extra::extract::CookieJar;
 use serde::Deserialize;
 use crate::app_state::AppState;
 
-use crate::domain::AuthAPIError;
-use crate::utils::auth::validate_token;
+use crate::domain::{AuthAPIError, Permission};
+use crate::utils::auth::{require_permission, validate_token};
 
 #[tracing::instrument(name = "Verify Token", skip_all)]
 pub async fn verify_token(
@@ -16,14 +16,34 @@ pub async fn verify_token(
     Json(request): Json<TokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     // Validate the JWT token
-    validate_token(&request.token, state.banned_token_store.clone())
+    let claims = validate_token(
+        &request.token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
         .await
         .map_err(|_| AuthAPIError::InvalidToken)?;
 
+    // Only tokens users got by logging in to this service, not OAuth access tokens or
+    // client tokens. Their scope is checked through /introspect instead.
+    if claims.client_id.is_some() {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    // A valid token is enough unless the caller asks for a permission. No role can hold
+    // a malformed one, so it is simply missing.
+    if let Some(permission) = request.permission {
+        let permission =
+            Permission::parse(permission).map_err(|_| AuthAPIError::MissingPermission)?;
+        require_permission(&claims, &permission, state.role_store.clone()).await?;
+    }
+
     Ok(StatusCode::OK.into_response())
 }
 
 #[derive(Deserialize)]
 pub struct TokenRequest {
     pub token: String,
+    pub permission: Option<String>,
 }
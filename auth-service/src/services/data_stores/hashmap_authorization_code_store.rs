@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::data_stores::{
+    AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError, AuthorizationGrant,
+};
+
+#[derive(Default)]
+pub struct HashmapAuthorizationCodeStore {
+    codes: HashMap<String, AuthorizationGrant>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        self.codes.insert(code.as_ref().expose_secret().to_owned(), grant);
+        Ok(())
+    }
+
+    async fn get_grant(
+        &self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        match self.codes.get(code.as_ref().expose_secret()) {
+            Some(grant) => Ok(grant.clone()),
+            None => Err(AuthorizationCodeStoreError::CodeNotFound),
+        }
+    }
+
+    async fn remove_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        self.codes.remove(code.as_ref().expose_secret());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuthenticationMethod, PkceChallenge, RedirectUri, Scope, UserId};
+
+    fn grant() -> AuthorizationGrant {
+        AuthorizationGrant {
+            client_id: "client".to_owned(),
+            redirect_uri: RedirectUri::parse("https://app.example.com/callback".to_owned()).unwrap(),
+            user_id: UserId::default(),
+            scope: Scope::parse("read").unwrap(),
+            code_challenge: PkceChallenge::parse(
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned(),
+                Some("S256"),
+            )
+            .unwrap(),
+            nonce: None,
+            auth_time: 0,
+            methods: vec![AuthenticationMethod::Password],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_grant() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+        store.add_code(code.clone(), grant()).await.unwrap();
+
+        assert_eq!(store.get_grant(&code).await.unwrap().client_id, "client");
+        assert_eq!(
+            store.get_grant(&AuthorizationCode::default()).await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_code() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+        store.add_code(code.clone(), grant()).await.unwrap();
+
+        store.remove_code(&code).await.unwrap();
+        assert_eq!(store.get_grant(&code).await, Err(AuthorizationCodeStoreError::CodeNotFound));
+    }
+}
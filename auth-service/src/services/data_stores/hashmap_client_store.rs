@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{
+    data_stores::{ClientStore, ClientStoreError},
+    ClientKind, OAuthClient, Scope,
+};
+
+#[derive(Default)]
+pub struct HashmapClientStore {
+    clients: HashMap<String, (OAuthClient, Option<Secret<String>>)>,
+}
+
+#[async_trait::async_trait]
+impl ClientStore for HashmapClientStore {
+    async fn register_client(
+        &mut self,
+        client_id: &str,
+        secret: Secret<String>,
+        scope: Scope,
+    ) -> Result<(), ClientStoreError> {
+        let client = match self.clients.remove(client_id) {
+            Some((client, _)) => OAuthClient { scope, kind: ClientKind::Service, ..client },
+            None => OAuthClient {
+                id: client_id.to_owned(),
+                name: client_id.to_owned(),
+                redirect_uris: Vec::new(),
+                confidential: true,
+                kind: ClientKind::Service,
+                scope,
+            },
+        };
+        self.clients.insert(client_id.to_owned(), (client, Some(secret)));
+        Ok(())
+    }
+
+    async fn add_client(&mut self, client: OAuthClient, secret: Option<Secret<String>>) -> Result<(), ClientStoreError> {
+        if self.clients.contains_key(&client.id) {
+            return Err(ClientStoreError::ClientAlreadyExists);
+        }
+        self.clients.insert(client.id.clone(), (client, secret));
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, ClientStoreError> {
+        match self.clients.get(client_id) {
+            Some((client, _)) => Ok(client.clone()),
+            None => Err(ClientStoreError::ClientNotFound),
+        }
+    }
+
+    async fn validate_client(&self, client_id: &str, secret: &Secret<String>) -> Result<(), ClientStoreError> {
+        match self.clients.get(client_id) {
+            Some((_, Some(expected))) if expected.expose_secret() == secret.expose_secret() => Ok(()),
+            _ => Err(ClientStoreError::InvalidCredentials),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::RedirectUri;
+
+    #[tokio::test]
+    async fn test_validate_client() {
+        let mut store = HashmapClientStore::default();
+        store
+            .register_client("app-service", Secret::new("secret".to_owned()), Scope::default())
+            .await
+            .unwrap();
+
+        assert!(store.validate_client("app-service", &Secret::new("secret".to_owned())).await.is_ok());
+        assert_eq!(
+            store.validate_client("app-service", &Secret::new("wrong".to_owned())).await,
+            Err(ClientStoreError::InvalidCredentials)
+        );
+        assert_eq!(
+            store.validate_client("unknown", &Secret::new("secret".to_owned())).await,
+            Err(ClientStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_register_client_replaces_secret_and_scope() {
+        let mut store = HashmapClientStore::default();
+        let old_scope = Scope::parse("users:read").unwrap();
+        let new_scope = Scope::parse("users:read users:write").unwrap();
+        store.register_client("app-service", Secret::new("old".to_owned()), old_scope).await.unwrap();
+        store
+            .register_client("app-service", Secret::new("new".to_owned()), new_scope.clone())
+            .await
+            .unwrap();
+
+        assert!(store.validate_client("app-service", &Secret::new("old".to_owned())).await.is_err());
+        assert!(store.validate_client("app-service", &Secret::new("new".to_owned())).await.is_ok());
+        assert_eq!(store.get_client("app-service").await.unwrap().scope, new_scope);
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapClientStore::default();
+        let client = OAuthClient::new(
+            "App".to_owned(),
+            vec![RedirectUri::parse("https://app.example.com/callback".to_owned()).unwrap()],
+            false,
+        );
+        store.add_client(client.clone(), None).await.unwrap();
+
+        assert_eq!(store.get_client(&client.id).await, Ok(client.clone()));
+        assert_eq!(
+            store.add_client(client.clone(), None).await,
+            Err(ClientStoreError::ClientAlreadyExists)
+        );
+        assert_eq!(store.get_client("unknown").await, Err(ClientStoreError::ClientNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_public_client_has_no_secret() {
+        let mut store = HashmapClientStore::default();
+        let client = OAuthClient::new("App".to_owned(), Vec::new(), false);
+        store.add_client(client.clone(), None).await.unwrap();
+
+        assert_eq!(
+            store.validate_client(&client.id, &Secret::new(String::new())).await,
+            Err(ClientStoreError::InvalidCredentials)
+        );
+    }
+}
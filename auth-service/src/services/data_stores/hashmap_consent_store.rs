@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{ConsentStore, ConsentStoreError},
+    Scope, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapConsentStore {
+    consents: HashMap<(UserId, String), Scope>,
+}
+
+#[async_trait::async_trait]
+impl ConsentStore for HashmapConsentStore {
+    async fn grant_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &str,
+        scope: Scope,
+    ) -> Result<(), ConsentStoreError> {
+        self.consents.insert((user_id.clone(), client_id.to_owned()), scope);
+        Ok(())
+    }
+
+    async fn get_consent(&self, user_id: &UserId, client_id: &str) -> Result<Scope, ConsentStoreError> {
+        match self.consents.get(&(user_id.clone(), client_id.to_owned())) {
+            Some(scope) => Ok(scope.clone()),
+            None => Err(ConsentStoreError::ConsentNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_grant_and_get_consent() {
+        let mut store = HashmapConsentStore::default();
+        let user_id = UserId::default();
+        assert_eq!(
+            store.get_consent(&user_id, "client").await,
+            Err(ConsentStoreError::ConsentNotFound)
+        );
+
+        store.grant_consent(&user_id, "client", Scope::parse("read").unwrap()).await.unwrap();
+        assert_eq!(store.get_consent(&user_id, "client").await, Ok(Scope::parse("read").unwrap()));
+        assert_eq!(
+            store.get_consent(&UserId::default(), "client").await,
+            Err(ConsentStoreError::ConsentNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grant_consent_replaces_scope() {
+        let mut store = HashmapConsentStore::default();
+        let user_id = UserId::default();
+        store.grant_consent(&user_id, "client", Scope::parse("read").unwrap()).await.unwrap();
+        store.grant_consent(&user_id, "client", Scope::parse("write").unwrap()).await.unwrap();
+
+        assert_eq!(store.get_consent(&user_id, "client").await, Ok(Scope::parse("write").unwrap()));
+    }
+}
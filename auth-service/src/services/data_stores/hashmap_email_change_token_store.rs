@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{EmailChangeToken, EmailChangeTokenStore, EmailChangeTokenStoreError},
+    Email, UserId,
+};
+use secrecy::ExposeSecret;
+
+#[derive(Default)]
+pub struct HashmapEmailChangeTokenStore {
+    tokens: HashMap<String, (UserId, Email)>,
+}
+
+#[async_trait::async_trait]
+impl EmailChangeTokenStore for HashmapEmailChangeTokenStore {
+    async fn add_token(
+        &mut self,
+        user_id: UserId,
+        new_email: Email,
+        token: EmailChangeToken,
+    ) -> Result<(), EmailChangeTokenStoreError> {
+        // Only the most recently requested change stays pending
+        self.tokens.retain(|_, (existing, _)| *existing != user_id);
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), (user_id, new_email));
+        Ok(())
+    }
+
+    async fn get_change(
+        &self,
+        token: &EmailChangeToken,
+    ) -> Result<(UserId, Email), EmailChangeTokenStoreError> {
+        match self.tokens.get(token.as_ref().expose_secret()) {
+            Some(change) => Ok(change.clone()),
+            None => Err(EmailChangeTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn remove_token(
+        &mut self,
+        token: &EmailChangeToken,
+    ) -> Result<(), EmailChangeTokenStoreError> {
+        self.tokens.remove(token.as_ref().expose_secret());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    #[tokio::test]
+    async fn test_add_and_get_token() {
+        let mut store = HashmapEmailChangeTokenStore::default();
+        let user_id = UserId::default();
+        let new_email = Email::parse(Secret::new("new@email.net".to_string())).unwrap();
+        let token = EmailChangeToken::default();
+        store
+            .add_token(user_id.clone(), new_email.clone(), token.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_change(&token).await.unwrap(), (user_id, new_email));
+    }
+
+    #[tokio::test]
+    async fn test_remove_token() {
+        let mut store = HashmapEmailChangeTokenStore::default();
+        let new_email = Email::parse(Secret::new("new@email.net".to_string())).unwrap();
+        let token = EmailChangeToken::default();
+        store.add_token(UserId::default(), new_email, token.clone()).await.unwrap();
+
+        store.remove_token(&token).await.unwrap();
+        let result = store.get_change(&token).await;
+        assert_eq!(result, Err(EmailChangeTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_new_token_replaces_old_token() {
+        let mut store = HashmapEmailChangeTokenStore::default();
+        let user_id = UserId::default();
+        let first_email = Email::parse(Secret::new("first@email.net".to_string())).unwrap();
+        let second_email = Email::parse(Secret::new("second@email.net".to_string())).unwrap();
+        let old_token = EmailChangeToken::default();
+        let new_token = EmailChangeToken::default();
+        store.add_token(user_id.clone(), first_email, old_token.clone()).await.unwrap();
+        store
+            .add_token(user_id.clone(), second_email.clone(), new_token.clone())
+            .await
+            .unwrap();
+
+        let result = store.get_change(&old_token).await;
+        assert_eq!(result, Err(EmailChangeTokenStoreError::TokenNotFound));
+        assert_eq!(store.get_change(&new_token).await.unwrap(), (user_id, second_email));
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::{
+    domain::{
+        data_stores::{
+            EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError,
+        },
+        Email,
+    },
+    utils::constants::EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS,
+};
+use secrecy::ExposeSecret;
+
+#[derive(Default)]
+pub struct HashmapEmailVerificationTokenStore {
+    tokens: HashMap<String, Email>,
+    last_resends: HashMap<Email, Instant>,
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for HashmapEmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        // Only the most recently sent link stays valid
+        self.tokens.retain(|_, existing| *existing != email);
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), email);
+        Ok(())
+    }
+
+    async fn get_email(
+        &self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        match self.tokens.get(token.as_ref().expose_secret()) {
+            Some(email) => Ok(email.clone()),
+            None => Err(EmailVerificationTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn remove_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        self.tokens.remove(token.as_ref().expose_secret());
+        Ok(())
+    }
+
+    async fn register_resend(&mut self, email: &Email) -> Result<(), EmailVerificationTokenStoreError> {
+        let interval = Duration::from_secs(EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS);
+        if let Some(last_resend) = self.last_resends.get(email) {
+            if last_resend.elapsed() < interval {
+                return Err(EmailVerificationTokenStoreError::ResendThrottled);
+            }
+        }
+        self.last_resends.insert(email.clone(), Instant::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    #[tokio::test]
+    async fn test_add_and_get_token() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse(Secret::new("test@email.net".to_string())).unwrap();
+        let token = EmailVerificationToken::default();
+        store.add_token(email.clone(), token.clone()).await.unwrap();
+
+        let stored_email = store.get_email(&token).await.unwrap();
+        assert_eq!(stored_email, email);
+    }
+
+    #[tokio::test]
+    async fn test_remove_token() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse(Secret::new("remove@email.net".to_string())).unwrap();
+        let token = EmailVerificationToken::default();
+        store.add_token(email, token.clone()).await.unwrap();
+
+        store.remove_token(&token).await.unwrap();
+        let result = store.get_email(&token).await;
+        assert_eq!(result, Err(EmailVerificationTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_new_token_replaces_old_token() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse(Secret::new("replace@email.net".to_string())).unwrap();
+        let old_token = EmailVerificationToken::default();
+        let new_token = EmailVerificationToken::default();
+        store.add_token(email.clone(), old_token.clone()).await.unwrap();
+        store.add_token(email.clone(), new_token.clone()).await.unwrap();
+
+        let result = store.get_email(&old_token).await;
+        assert_eq!(result, Err(EmailVerificationTokenStoreError::TokenNotFound));
+        assert_eq!(store.get_email(&new_token).await.unwrap(), email);
+    }
+
+    #[tokio::test]
+    async fn test_register_resend_is_throttled() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse(Secret::new("throttle@email.net".to_string())).unwrap();
+        let other_email = Email::parse(Secret::new("other@email.net".to_string())).unwrap();
+
+        assert!(store.register_resend(&email).await.is_ok());
+        assert_eq!(
+            store.register_resend(&email).await,
+            Err(EmailVerificationTokenStoreError::ResendThrottled)
+        );
+        // Throttling is tracked per address
+        assert!(store.register_resend(&other_email).await.is_ok());
+    }
+}
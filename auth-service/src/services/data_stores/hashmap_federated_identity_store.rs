@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{FederatedIdentityStore, FederatedIdentityStoreError},
+    UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapFederatedIdentityStore {
+    identities: HashMap<(String, String), UserId>,
+}
+
+#[async_trait::async_trait]
+impl FederatedIdentityStore for HashmapFederatedIdentityStore {
+    async fn link_identity(
+        &mut self,
+        provider: &str,
+        subject: &str,
+        user_id: &UserId,
+    ) -> Result<(), FederatedIdentityStoreError> {
+        self.identities
+            .insert((provider.to_owned(), subject.to_owned()), user_id.clone());
+        Ok(())
+    }
+
+    async fn get_user_id(&self, provider: &str, subject: &str) -> Result<UserId, FederatedIdentityStoreError> {
+        match self.identities.get(&(provider.to_owned(), subject.to_owned())) {
+            Some(user_id) => Ok(user_id.clone()),
+            None => Err(FederatedIdentityStoreError::IdentityNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_link_and_get_identity() {
+        let mut store = HashmapFederatedIdentityStore::default();
+        let user_id = UserId::default();
+        store.link_identity("corp", "248289761001", &user_id).await.unwrap();
+
+        assert_eq!(store.get_user_id("corp", "248289761001").await, Ok(user_id));
+        assert_eq!(
+            store.get_user_id("other", "248289761001").await,
+            Err(FederatedIdentityStoreError::IdentityNotFound)
+        );
+        assert_eq!(
+            store.get_user_id("corp", "other").await,
+            Err(FederatedIdentityStoreError::IdentityNotFound)
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::data_stores::{
+    FederatedLogin, FederatedLoginState, FederatedLoginStore, FederatedLoginStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapFederatedLoginStore {
+    logins: HashMap<String, FederatedLogin>,
+}
+
+#[async_trait::async_trait]
+impl FederatedLoginStore for HashmapFederatedLoginStore {
+    async fn add_login(
+        &mut self,
+        state: FederatedLoginState,
+        login: FederatedLogin,
+    ) -> Result<(), FederatedLoginStoreError> {
+        self.logins.insert(state.as_ref().expose_secret().to_owned(), login);
+        Ok(())
+    }
+
+    async fn get_login(
+        &self,
+        state: &FederatedLoginState,
+    ) -> Result<FederatedLogin, FederatedLoginStoreError> {
+        match self.logins.get(state.as_ref().expose_secret()) {
+            Some(login) => Ok(login.clone()),
+            None => Err(FederatedLoginStoreError::LoginNotFound),
+        }
+    }
+
+    async fn remove_login(&mut self, state: &FederatedLoginState) -> Result<(), FederatedLoginStoreError> {
+        self.logins.remove(state.as_ref().expose_secret());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_login() {
+        let mut store = HashmapFederatedLoginStore::default();
+        let state = FederatedLoginState::default();
+        let login = FederatedLogin::new("corp".to_owned());
+        store.add_login(state.clone(), login.clone()).await.unwrap();
+
+        let stored = store.get_login(&state).await.unwrap();
+        assert_eq!(stored.provider, "corp");
+        assert_eq!(stored.nonce, login.nonce);
+        assert_eq!(stored.code_challenge(), login.code_challenge());
+        assert_eq!(
+            store.get_login(&FederatedLoginState::default()).await.unwrap_err(),
+            FederatedLoginStoreError::LoginNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_login() {
+        let mut store = HashmapFederatedLoginStore::default();
+        let state = FederatedLoginState::default();
+        store.add_login(state.clone(), FederatedLogin::new("corp".to_owned())).await.unwrap();
+
+        store.remove_login(&state).await.unwrap();
+        assert_eq!(
+            store.get_login(&state).await.unwrap_err(),
+            FederatedLoginStoreError::LoginNotFound
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::{
+    domain::{
+        data_stores::{login_lockout_seconds, LoginAttemptStore, LoginAttemptStoreError},
+        UserId,
+    },
+    utils::constants::LOGIN_FAILURE_WINDOW_SECONDS,
+};
+
+struct FailedLogins {
+    count: u64,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct HashmapLoginAttemptStore {
+    failures: HashMap<UserId, FailedLogins>,
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for HashmapLoginAttemptStore {
+    async fn check_lockout(&self, user_id: &UserId) -> Result<(), LoginAttemptStoreError> {
+        let locked_until = match self.failures.get(user_id).and_then(|f| f.locked_until) {
+            Some(locked_until) => locked_until,
+            None => return Ok(()),
+        };
+
+        let now = Instant::now();
+        if locked_until <= now {
+            return Ok(());
+        }
+        Err(LoginAttemptStoreError::LockedOut {
+            retry_after_seconds: (locked_until - now).as_secs_f64().ceil() as u64,
+        })
+    }
+
+    async fn record_failure(&mut self, user_id: &UserId) -> Result<(), LoginAttemptStoreError> {
+        let now = Instant::now();
+        let failures = self.failures.entry(user_id.clone()).or_insert(FailedLogins {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+
+        if now.duration_since(failures.last_failure) > Duration::from_secs(LOGIN_FAILURE_WINDOW_SECONDS) {
+            failures.count = 0;
+        }
+        failures.count += 1;
+        failures.last_failure = now;
+        failures.locked_until =
+            login_lockout_seconds(failures.count).map(|seconds| now + Duration::from_secs(seconds));
+        Ok(())
+    }
+
+    async fn reset(&mut self, user_id: &UserId) -> Result<(), LoginAttemptStoreError> {
+        self.failures.remove(user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::constants::{
+        LOGIN_LOCKOUT_BASE_SECONDS, LOGIN_LOCKOUT_MAX_SECONDS, LOGIN_LOCKOUT_THRESHOLD,
+    };
+
+    #[tokio::test]
+    async fn test_locks_out_after_threshold() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let user_id = UserId::default();
+
+        for _ in 1..LOGIN_LOCKOUT_THRESHOLD {
+            store.record_failure(&user_id).await.unwrap();
+            assert!(store.check_lockout(&user_id).await.is_ok());
+        }
+
+        store.record_failure(&user_id).await.unwrap();
+        assert_eq!(
+            store.check_lockout(&user_id).await,
+            Err(LoginAttemptStoreError::LockedOut {
+                retry_after_seconds: LOGIN_LOCKOUT_BASE_SECONDS
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lockout_is_per_user() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let user_id = UserId::default();
+        let other_user_id = UserId::default();
+
+        for _ in 0..LOGIN_LOCKOUT_THRESHOLD {
+            store.record_failure(&user_id).await.unwrap();
+        }
+        assert!(store.check_lockout(&user_id).await.is_err());
+        assert!(store.check_lockout(&other_user_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reset_clears_lockout() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let user_id = UserId::default();
+
+        for _ in 0..LOGIN_LOCKOUT_THRESHOLD {
+            store.record_failure(&user_id).await.unwrap();
+        }
+        store.reset(&user_id).await.unwrap();
+        assert!(store.check_lockout(&user_id).await.is_ok());
+
+        // The count starts over as well
+        store.record_failure(&user_id).await.unwrap();
+        assert!(store.check_lockout(&user_id).await.is_ok());
+    }
+
+    #[test]
+    fn test_lockout_backs_off_exponentially() {
+        assert_eq!(login_lockout_seconds(LOGIN_LOCKOUT_THRESHOLD - 1), None);
+        assert_eq!(login_lockout_seconds(LOGIN_LOCKOUT_THRESHOLD), Some(LOGIN_LOCKOUT_BASE_SECONDS));
+        assert_eq!(
+            login_lockout_seconds(LOGIN_LOCKOUT_THRESHOLD + 2),
+            Some(LOGIN_LOCKOUT_BASE_SECONDS * 4)
+        );
+        assert_eq!(login_lockout_seconds(u64::MAX), Some(LOGIN_LOCKOUT_MAX_SECONDS));
+    }
+}
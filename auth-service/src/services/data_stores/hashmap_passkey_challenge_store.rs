@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{PasskeyChallengeStore, PasskeyChallengeStoreError},
+    PasskeyCeremony, PasskeyChallenge,
+};
+
+#[derive(Default)]
+pub struct HashmapPasskeyChallengeStore {
+    challenges: HashMap<PasskeyChallenge, PasskeyCeremony>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for HashmapPasskeyChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: PasskeyChallenge,
+        ceremony: PasskeyCeremony,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        self.challenges.insert(challenge, ceremony);
+        Ok(())
+    }
+
+    async fn get_ceremony(
+        &self,
+        challenge: &PasskeyChallenge,
+    ) -> Result<PasskeyCeremony, PasskeyChallengeStoreError> {
+        match self.challenges.get(challenge) {
+            Some(ceremony) => Ok(ceremony.clone()),
+            None => Err(PasskeyChallengeStoreError::ChallengeNotFound),
+        }
+    }
+
+    async fn remove_challenge(
+        &mut self,
+        challenge: &PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        self.challenges.remove(challenge);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use secrecy::Secret;
+
+    #[tokio::test]
+    async fn test_add_and_get_challenge() {
+        let mut store = HashmapPasskeyChallengeStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let challenge = PasskeyChallenge::default();
+        let ceremony = PasskeyCeremony::Registration { email };
+        store.add_challenge(challenge.clone(), ceremony.clone()).await.unwrap();
+
+        assert_eq!(store.get_ceremony(&challenge).await.unwrap(), ceremony);
+    }
+
+    #[tokio::test]
+    async fn test_remove_challenge() {
+        let mut store = HashmapPasskeyChallengeStore::default();
+        let challenge = PasskeyChallenge::default();
+        store.add_challenge(challenge.clone(), PasskeyCeremony::Login).await.unwrap();
+
+        store.remove_challenge(&challenge).await.unwrap();
+        let result = store.get_ceremony(&challenge).await;
+        assert_eq!(result, Err(PasskeyChallengeStoreError::ChallengeNotFound));
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{PasskeyStore, PasskeyStoreError},
+    Email, PasskeyCredential,
+};
+
+#[derive(Default)]
+pub struct HashmapPasskeyStore {
+    credentials: HashMap<String, PasskeyCredential>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for HashmapPasskeyStore {
+    async fn add_credential(&mut self, credential: PasskeyCredential) -> Result<(), PasskeyStoreError> {
+        if self.credentials.contains_key(&credential.id) {
+            return Err(PasskeyStoreError::CredentialAlreadyExists);
+        }
+        self.credentials.insert(credential.id.clone(), credential);
+        Ok(())
+    }
+
+    async fn get_credential(&self, credential_id: &str) -> Result<PasskeyCredential, PasskeyStoreError> {
+        match self.credentials.get(credential_id) {
+            Some(credential) => Ok(credential.clone()),
+            None => Err(PasskeyStoreError::CredentialNotFound),
+        }
+    }
+
+    async fn get_user_credentials(&self, email: &Email) -> Result<Vec<PasskeyCredential>, PasskeyStoreError> {
+        Ok(self
+            .credentials
+            .values()
+            .filter(|credential| credential.email == *email)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), PasskeyStoreError> {
+        match self.credentials.get_mut(credential_id) {
+            Some(credential) => {
+                credential.sign_count = sign_count;
+                Ok(())
+            }
+            None => Err(PasskeyStoreError::CredentialNotFound),
+        }
+    }
+
+    async fn delete_user_credentials(&mut self, email: &Email) -> Result<(), PasskeyStoreError> {
+        self.credentials.retain(|_, credential| credential.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn credential(id: &str, email: &str) -> PasskeyCredential {
+        PasskeyCredential {
+            id: id.to_owned(),
+            email: Email::parse(Secret::new(email.to_owned())).unwrap(),
+            public_key: vec![4; 65],
+            sign_count: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_credential() {
+        let mut store = HashmapPasskeyStore::default();
+        let credential = credential("credential-1", "test@example.com");
+        store.add_credential(credential.clone()).await.unwrap();
+
+        assert_eq!(store.get_credential("credential-1").await.unwrap(), credential);
+        let result = store.get_credential("credential-2").await;
+        assert_eq!(result, Err(PasskeyStoreError::CredentialNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_add_existing_credential() {
+        let mut store = HashmapPasskeyStore::default();
+        store.add_credential(credential("credential-1", "test@example.com")).await.unwrap();
+
+        let result = store.add_credential(credential("credential-1", "other@example.com")).await;
+        assert_eq!(result, Err(PasskeyStoreError::CredentialAlreadyExists));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_credentials() {
+        let mut store = HashmapPasskeyStore::default();
+        store.add_credential(credential("credential-1", "test@example.com")).await.unwrap();
+        store.add_credential(credential("credential-2", "test@example.com")).await.unwrap();
+        store.add_credential(credential("credential-3", "other@example.com")).await.unwrap();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let mut ids: Vec<String> = store
+            .get_user_credentials(&email)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|credential| credential.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["credential-1", "credential-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashmapPasskeyStore::default();
+        store.add_credential(credential("credential-1", "test@example.com")).await.unwrap();
+
+        store.update_sign_count("credential-1", 7).await.unwrap();
+        assert_eq!(store.get_credential("credential-1").await.unwrap().sign_count, 7);
+
+        let result = store.update_sign_count("credential-2", 7).await;
+        assert_eq!(result, Err(PasskeyStoreError::CredentialNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_credentials() {
+        let mut store = HashmapPasskeyStore::default();
+        store.add_credential(credential("credential-1", "test@example.com")).await.unwrap();
+        store.add_credential(credential("credential-2", "other@example.com")).await.unwrap();
+
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        store.delete_user_credentials(&email).await.unwrap();
+        assert!(store.get_user_credentials(&email).await.unwrap().is_empty());
+        assert!(store.get_credential("credential-2").await.is_ok());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+    Email,
+};
+use secrecy::ExposeSecret;
+
+#[derive(Default)]
+pub struct HashmapPasswordResetTokenStore {
+    tokens: HashMap<String, Email>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        // Only the most recently requested token stays valid
+        self.tokens.retain(|_, existing| *existing != email);
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), email);
+        Ok(())
+    }
+
+    async fn get_email(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        match self.tokens.get(token.as_ref().expose_secret()) {
+            Some(email) => Ok(email.clone()),
+            None => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn remove_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        self.tokens.remove(token.as_ref().expose_secret());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    #[tokio::test]
+    async fn test_add_and_get_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("test@email.net".to_string())).unwrap();
+        let token = PasswordResetToken::default();
+        store.add_token(email.clone(), token.clone()).await.unwrap();
+
+        let stored_email = store.get_email(&token).await.unwrap();
+        assert_eq!(stored_email, email);
+    }
+
+    #[tokio::test]
+    async fn test_get_token_nonexistent() {
+        let store = HashmapPasswordResetTokenStore::default();
+        let result = store.get_email(&PasswordResetToken::default()).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_remove_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("remove@email.net".to_string())).unwrap();
+        let token = PasswordResetToken::default();
+        store.add_token(email, token.clone()).await.unwrap();
+
+        store.remove_token(&token).await.unwrap();
+        let result = store.get_email(&token).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_new_token_replaces_old_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("replace@email.net".to_string())).unwrap();
+        let old_token = PasswordResetToken::default();
+        let new_token = PasswordResetToken::default();
+        store.add_token(email.clone(), old_token.clone()).await.unwrap();
+        store.add_token(email.clone(), new_token.clone()).await.unwrap();
+
+        let result = store.get_email(&old_token).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+        assert_eq!(store.get_email(&new_token).await.unwrap(), email);
+    }
+}
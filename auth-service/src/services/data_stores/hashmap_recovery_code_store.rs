@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapRecoveryCodeStore {
+    codes: HashMap<Email, Vec<RecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn replace_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError> {
+        let codes = self
+            .codes
+            .get_mut(email)
+            .ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+
+        match codes.iter().position(|existing| existing == code) {
+            Some(index) => {
+                codes.remove(index);
+                Ok(())
+            }
+            None => Err(RecoveryCodeStoreError::CodeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::{ExposeSecret, Secret};
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_use_code_burns_code() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let code = RecoveryCode::default();
+        store.replace_codes(&email(), vec![code.clone(), RecoveryCode::default()]).await.unwrap();
+
+        assert!(store.use_code(&email(), &code).await.is_ok());
+        assert_eq!(store.use_code(&email(), &code).await, Err(RecoveryCodeStoreError::CodeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_use_unknown_code() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        store.replace_codes(&email(), vec![RecoveryCode::default()]).await.unwrap();
+
+        let result = store.use_code(&email(), &RecoveryCode::default()).await;
+        assert_eq!(result, Err(RecoveryCodeStoreError::CodeNotFound));
+
+        let other_email = Email::parse(Secret::new("other@example.com".to_string())).unwrap();
+        let result = store.use_code(&other_email, &RecoveryCode::default()).await;
+        assert_eq!(result, Err(RecoveryCodeStoreError::CodeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_replace_codes_invalidates_old_codes() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let old_code = RecoveryCode::default();
+        let new_code = RecoveryCode::default();
+        store.replace_codes(&email(), vec![old_code.clone()]).await.unwrap();
+        store.replace_codes(&email(), vec![new_code.clone()]).await.unwrap();
+
+        assert_eq!(store.use_code(&email(), &old_code).await, Err(RecoveryCodeStoreError::CodeNotFound));
+        assert!(store.use_code(&email(), &new_code).await.is_ok());
+    }
+
+    #[test]
+    fn test_recovery_code_parse_normalizes_input() {
+        let code = RecoveryCode::default();
+        let typed = code.as_ref().expose_secret().replace('-', "").to_lowercase();
+        assert_eq!(RecoveryCode::parse(format!(" {} ", typed)).unwrap(), code);
+    }
+
+    #[test]
+    fn test_recovery_code_parse_rejects_invalid_codes() {
+        for code in ["", "ABCDE-FGHJ", "ABCDE-FGHJKL", "ABCDE-FGHJ0", "ABCDE-FGH!K"] {
+            assert!(RecoveryCode::parse(code.to_owned()).is_err(), "Accepted: {}", code);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::{
+    data_stores::{
+        RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError, TokenFamilyId,
+    },
+    UserId,
+};
+use secrecy::ExposeSecret;
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<String, RefreshTokenRecord>,
+    revoked_families: HashSet<TokenFamilyId>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), record);
+        Ok(())
+    }
+
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        match self.tokens.get(token.as_ref().expose_secret()) {
+            Some(record) => Ok(record.clone()),
+            None => Err(RefreshTokenStoreError::RefreshTokenNotFound),
+        }
+    }
+
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        match self.tokens.get_mut(token.as_ref().expose_secret()) {
+            Some(record) => {
+                record.used = true;
+                Ok(())
+            }
+            None => Err(RefreshTokenStoreError::RefreshTokenNotFound),
+        }
+    }
+
+    async fn revoke_family(&mut self, family_id: &TokenFamilyId) -> Result<(), RefreshTokenStoreError> {
+        self.revoked_families.insert(family_id.clone());
+        Ok(())
+    }
+
+    async fn is_family_revoked(&self, family_id: &TokenFamilyId) -> Result<bool, RefreshTokenStoreError> {
+        Ok(self.revoked_families.contains(family_id))
+    }
+
+    async fn revoke_user_families(&mut self, user_id: &UserId) -> Result<(), RefreshTokenStoreError> {
+        let families = self
+            .tokens
+            .values()
+            .filter(|record| record.user_id == *user_id)
+            .map(|record| record.family_id.clone());
+        self.revoked_families.extend(families);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn record(user_id: &UserId) -> RefreshTokenRecord {
+        RefreshTokenRecord::new(user_id.clone(), TokenFamilyId::default())
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let record = record(&UserId::default());
+        store.add_token(token.clone(), record.clone()).await.unwrap();
+
+        let stored = store.get_token(&token).await.unwrap();
+        assert_eq!(stored, record);
+        assert!(!stored.used);
+    }
+
+    #[tokio::test]
+    async fn test_get_token_nonexistent() {
+        let store = HashmapRefreshTokenStore::default();
+        let result = store.get_token(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::RefreshTokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_token_used() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        store.add_token(token.clone(), record(&UserId::default())).await.unwrap();
+
+        store.mark_token_used(&token).await.unwrap();
+        assert!(store.get_token(&token).await.unwrap().used);
+
+        let result = store.mark_token_used(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::RefreshTokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let revoked = TokenFamilyId::default();
+        let other = TokenFamilyId::default();
+
+        store.revoke_family(&revoked).await.unwrap();
+        assert!(store.is_family_revoked(&revoked).await.unwrap());
+        assert!(!store.is_family_revoked(&other).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_user_families() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let user_id = UserId::default();
+        let first = record(&user_id);
+        let second = record(&user_id);
+        let other = record(&UserId::default());
+        store.add_token(RefreshToken::default(), first.clone()).await.unwrap();
+        store.add_token(RefreshToken::default(), second.clone()).await.unwrap();
+        store.add_token(RefreshToken::default(), other.clone()).await.unwrap();
+
+        store.revoke_user_families(&user_id).await.unwrap();
+        assert!(store.is_family_revoked(&first.family_id).await.unwrap());
+        assert!(store.is_family_revoked(&second.family_id).await.unwrap());
+        assert!(!store.is_family_revoked(&other.family_id).await.unwrap());
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::domain::{
+    data_stores::{RoleStore, RoleStoreError},
+    Permission, Role, UserId, MANAGE_CLIENTS_PERMISSION, MANAGE_ROLES_PERMISSION,
+};
+
+pub struct HashmapRoleStore {
+    permissions: HashMap<Role, HashSet<Permission>>,
+    user_roles: HashMap<UserId, BTreeSet<Role>>,
+}
+
+// Starts with the same roles the migrations create
+impl Default for HashmapRoleStore {
+    fn default() -> Self {
+        let admin = Role::parse("admin".to_owned()).expect("admin is a valid role");
+        let permissions = [MANAGE_ROLES_PERMISSION, MANAGE_CLIENTS_PERMISSION]
+            .map(|permission| Permission::parse(permission.to_owned()).expect("valid permission"));
+        Self {
+            permissions: HashMap::from([(admin, HashSet::from(permissions))]),
+            user_roles: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for HashmapRoleStore {
+    async fn get_roles(&self, user_id: &UserId) -> Result<Vec<Role>, RoleStoreError> {
+        Ok(self
+            .user_roles
+            .get(user_id)
+            .map(|roles| roles.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn grant_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        if !self.permissions.contains_key(role) {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+        self.user_roles.entry(user_id.clone()).or_default().insert(role.clone());
+        Ok(())
+    }
+
+    async fn revoke_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        if let Some(roles) = self.user_roles.get_mut(user_id) {
+            roles.remove(role);
+        }
+        Ok(())
+    }
+
+    async fn has_permission(&self, roles: &[Role], permission: &Permission) -> Result<bool, RoleStoreError> {
+        Ok(roles.iter().any(|role| {
+            self.permissions
+                .get(role)
+                .is_some_and(|permissions| permissions.contains(permission))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(name: &str) -> Role {
+        Role::parse(name.to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_grant_and_revoke_role() {
+        let mut store = HashmapRoleStore::default();
+        let user_id = UserId::default();
+        assert_eq!(store.get_roles(&user_id).await, Ok(vec![]));
+
+        store.grant_role(&user_id, &role("admin")).await.unwrap();
+        store.grant_role(&user_id, &role("admin")).await.unwrap();
+        assert_eq!(store.get_roles(&user_id).await, Ok(vec![role("admin")]));
+        assert_eq!(store.get_roles(&UserId::default()).await, Ok(vec![]));
+
+        store.revoke_role(&user_id, &role("admin")).await.unwrap();
+        store.revoke_role(&user_id, &role("admin")).await.unwrap();
+        assert_eq!(store.get_roles(&user_id).await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_grant_unknown_role() {
+        let mut store = HashmapRoleStore::default();
+        assert_eq!(
+            store.grant_role(&UserId::default(), &role("superuser")).await,
+            Err(RoleStoreError::RoleNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_has_permission() {
+        let store = HashmapRoleStore::default();
+        let manage_roles = Permission::parse(MANAGE_ROLES_PERMISSION.to_owned()).unwrap();
+        let other = Permission::parse("reports:read".to_owned()).unwrap();
+
+        assert_eq!(store.has_permission(&[role("admin")], &manage_roles).await, Ok(true));
+        assert_eq!(store.has_permission(&[role("admin")], &other).await, Ok(false));
+        assert_eq!(store.has_permission(&[role("superuser")], &manage_roles).await, Ok(false));
+        assert_eq!(store.has_permission(&[], &manage_roles).await, Ok(false));
+    }
+}
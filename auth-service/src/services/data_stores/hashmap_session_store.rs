@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{Session, SessionStore, SessionStoreError, TokenFamilyId},
+    UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<TokenFamilyId, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &TokenFamilyId) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(session_id)
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_user_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        Ok(self
+            .sessions
+            .values()
+            .filter(|session| session.user_id == *user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn touch_session(&mut self, session_id: &TokenFamilyId) -> Result<(), SessionStoreError> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen = chrono::Utc::now().timestamp();
+        Ok(())
+    }
+
+    async fn remove_session(&mut self, session_id: &TokenFamilyId) -> Result<(), SessionStoreError> {
+        self.sessions.remove(session_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = Session::new(
+            UserId::default(),
+            Some("Firefox".to_owned()),
+            Some("127.0.0.1".to_owned()),
+        );
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await.unwrap(), session);
+        assert_eq!(
+            store.get_session(&TokenFamilyId::default()).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_user_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let user_id = UserId::default();
+        let first = Session::new(user_id.clone(), None, None);
+        let second = Session::new(user_id.clone(), None, None);
+        let other = Session::new(UserId::default(), None, None);
+        for session in [&first, &second, &other] {
+            store.add_session(session.clone()).await.unwrap();
+        }
+
+        let sessions = store.get_user_sessions(&user_id).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.contains(&first));
+        assert!(sessions.contains(&second));
+    }
+
+    #[tokio::test]
+    async fn test_removed_session_can_not_be_touched() {
+        let mut store = HashmapSessionStore::default();
+        let session = Session::new(UserId::default(), None, None);
+        store.add_session(session.clone()).await.unwrap();
+        assert!(store.touch_session(&session.id).await.is_ok());
+
+        store.remove_session(&session.id).await.unwrap();
+        assert_eq!(
+            store.touch_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert!(store.get_user_sessions(&session.user_id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{TotpSecretStore, TotpSecretStoreError},
+    Email, TotpSecret,
+};
+
+#[derive(Default)]
+pub struct HashmapTotpSecretStore {
+    secrets: HashMap<Email, TotpSecret>,
+    pending_secrets: HashMap<Email, TotpSecret>,
+    last_used_steps: HashMap<Email, u64>,
+}
+
+#[async_trait::async_trait]
+impl TotpSecretStore for HashmapTotpSecretStore {
+    async fn set_pending_secret(&mut self, email: &Email, secret: TotpSecret) -> Result<(), TotpSecretStoreError> {
+        self.pending_secrets.insert(email.clone(), secret);
+        Ok(())
+    }
+
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, TotpSecretStoreError> {
+        match self.pending_secrets.get(email) {
+            Some(secret) => Ok(secret.clone()),
+            None => Err(TotpSecretStoreError::SecretNotFound),
+        }
+    }
+
+    async fn confirm_pending_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        match self.pending_secrets.remove(email) {
+            Some(secret) => {
+                // Steps used with the old secret say nothing about the new one
+                self.secrets.insert(email.clone(), secret);
+                self.last_used_steps.remove(email);
+                Ok(())
+            }
+            None => Err(TotpSecretStoreError::SecretNotFound),
+        }
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, TotpSecretStoreError> {
+        match self.secrets.get(email) {
+            Some(secret) => Ok(secret.clone()),
+            None => Err(TotpSecretStoreError::SecretNotFound),
+        }
+    }
+
+    async fn record_used_step(&mut self, email: &Email, step: u64) -> Result<(), TotpSecretStoreError> {
+        if !self.secrets.contains_key(email) {
+            return Err(TotpSecretStoreError::SecretNotFound);
+        }
+        if self.last_used_steps.get(email).is_some_and(|last_step| *last_step >= step) {
+            return Err(TotpSecretStoreError::StepAlreadyUsed);
+        }
+        self.last_used_steps.insert(email.clone(), step);
+        Ok(())
+    }
+
+    async fn delete_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        self.secrets.remove(email);
+        self.pending_secrets.remove(email);
+        self.last_used_steps.remove(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::{ExposeSecret, Secret};
+
+    #[tokio::test]
+    async fn test_pending_secret_is_not_active() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let secret = TotpSecret::default();
+        store.set_pending_secret(&email, secret.clone()).await.unwrap();
+
+        let pending = store.get_pending_secret(&email).await.unwrap();
+        assert_eq!(pending.as_ref().expose_secret(), secret.as_ref().expose_secret());
+        assert_eq!(store.get_secret(&email).await.unwrap_err(), TotpSecretStoreError::SecretNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_pending_secret() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let secret = TotpSecret::default();
+        store.set_pending_secret(&email, secret.clone()).await.unwrap();
+        store.confirm_pending_secret(&email).await.unwrap();
+
+        let active = store.get_secret(&email).await.unwrap();
+        assert_eq!(active.as_ref().expose_secret(), secret.as_ref().expose_secret());
+        assert_eq!(store.get_pending_secret(&email).await.unwrap_err(), TotpSecretStoreError::SecretNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_without_pending_secret() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let result = store.confirm_pending_secret(&email).await;
+        assert_eq!(result, Err(TotpSecretStoreError::SecretNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_reenrolling_keeps_active_secret() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let active = TotpSecret::default();
+        store.set_pending_secret(&email, active.clone()).await.unwrap();
+        store.confirm_pending_secret(&email).await.unwrap();
+
+        store.set_pending_secret(&email, TotpSecret::default()).await.unwrap();
+        let current = store.get_secret(&email).await.unwrap();
+        assert_eq!(current.as_ref().expose_secret(), active.as_ref().expose_secret());
+    }
+
+    #[tokio::test]
+    async fn test_record_used_step() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        assert_eq!(store.record_used_step(&email, 10).await, Err(TotpSecretStoreError::SecretNotFound));
+
+        store.set_pending_secret(&email, TotpSecret::default()).await.unwrap();
+        store.confirm_pending_secret(&email).await.unwrap();
+        store.record_used_step(&email, 10).await.unwrap();
+        assert_eq!(store.record_used_step(&email, 10).await, Err(TotpSecretStoreError::StepAlreadyUsed));
+        assert_eq!(store.record_used_step(&email, 9).await, Err(TotpSecretStoreError::StepAlreadyUsed));
+        store.record_used_step(&email, 11).await.unwrap();
+
+        // A new secret starts over
+        store.set_pending_secret(&email, TotpSecret::default()).await.unwrap();
+        store.confirm_pending_secret(&email).await.unwrap();
+        store.record_used_step(&email, 11).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_secret() {
+        let mut store = HashmapTotpSecretStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        store.set_pending_secret(&email, TotpSecret::default()).await.unwrap();
+        store.confirm_pending_secret(&email).await.unwrap();
+        store.set_pending_secret(&email, TotpSecret::default()).await.unwrap();
+
+        store.delete_secret(&email).await.unwrap();
+        assert_eq!(store.get_secret(&email).await.unwrap_err(), TotpSecretStoreError::SecretNotFound);
+        assert_eq!(store.get_pending_secret(&email).await.unwrap_err(), TotpSecretStoreError::SecretNotFound);
+    }
+}
@@ -1,7 +1,12 @@
 use std::collections::HashMap;
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    email::Email,
+use std::time::{Duration, Instant};
+
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        email::Email,
+    },
+    utils::constants::{TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS, TWO_FA_MAX_FAILED_ATTEMPTS},
 };
 
 use secrecy::{ExposeSecret, Secret};
@@ -9,6 +14,7 @@ use secrecy::{ExposeSecret, Secret};
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
     codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    failed_attempts: HashMap<Email, (u64, Instant)>,
 }
 
 #[async_trait::async_trait]
@@ -19,12 +25,14 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
+        // Failed attempts are kept, starting a new login must not buy more guesses
         self.codes.insert(email, (login_attempt_id, code));
         Ok(())
     }
 
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
         self.codes.remove(email);
+        self.failed_attempts.remove(email);
         Ok(())
     }
 
@@ -37,6 +45,27 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        if !self.codes.contains_key(email) {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        let now = Instant::now();
+        let (failed_attempts, last_failure) =
+            self.failed_attempts.entry(email.clone()).or_insert((0, now));
+        if now.duration_since(*last_failure) > Duration::from_secs(TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS) {
+            *failed_attempts = 0;
+        }
+        *failed_attempts += 1;
+        *last_failure = now;
+        if *failed_attempts >= TWO_FA_MAX_FAILED_ATTEMPTS {
+            // The count stays until a second factor succeeds
+            self.codes.remove(email);
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -114,4 +143,92 @@ mod tests {
         let result = store.remove_code(&email).await;
         assert!(result.is_ok());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_record_failed_attempt_discards_code_after_limit() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(Secret::new("attempts@email.net".to_string())).unwrap();
+        store
+            .add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+
+        for _ in 1..TWO_FA_MAX_FAILED_ATTEMPTS {
+            assert!(store.record_failed_attempt(&email).await.is_ok());
+        }
+        assert_eq!(
+            store.record_failed_attempt(&email).await,
+            Err(TwoFACodeStoreError::TooManyAttempts)
+        );
+        let result = store.get_code(&email).await;
+        assert!(matches!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_new_code_keeps_failed_attempts() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(Secret::new("fresh@email.net".to_string())).unwrap();
+        store
+            .add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        for _ in 1..TWO_FA_MAX_FAILED_ATTEMPTS {
+            store.record_failed_attempt(&email).await.unwrap();
+        }
+
+        store
+            .add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            store.record_failed_attempt(&email).await,
+            Err(TwoFACodeStoreError::TooManyAttempts)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_code_resets_failed_attempts() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(Secret::new("success@email.net".to_string())).unwrap();
+        store
+            .add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        for _ in 1..TWO_FA_MAX_FAILED_ATTEMPTS {
+            store.record_failed_attempt(&email).await.unwrap();
+        }
+        store.remove_code(&email).await.unwrap();
+
+        store
+            .add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        assert!(store.record_failed_attempt(&email).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_failed_attempts_expire() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(Secret::new("expire@email.net".to_string())).unwrap();
+        store
+            .add_code(email.clone(), LoginAttemptId::default(), TwoFACode::default())
+            .await
+            .unwrap();
+        for _ in 1..TWO_FA_MAX_FAILED_ATTEMPTS {
+            store.record_failed_attempt(&email).await.unwrap();
+        }
+
+        // Same window as the Redis store, the count starts over once it has passed
+        let window = Duration::from_secs(TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS + 1);
+        store.failed_attempts.get_mut(&email).unwrap().1 -= window;
+        assert!(store.record_failed_attempt(&email).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_record_failed_attempt_nonexistent_email() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let email = Email::parse(Secret::new("missing@email.net".to_string())).unwrap();
+        let result = store.record_failed_attempt(&email).await;
+        assert!(matches!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound)));
+    }
+}
@@ -1,14 +1,15 @@
 use std::collections::HashMap;
 use crate::domain::Email;
 use crate::domain::Password;
-use crate::domain::User;
+use crate::domain::{User, UserId};
 use crate::domain::data_stores::*;
 use secrecy::Secret;
 
 
 #[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>
+    users: HashMap<Email, User>,
+    token_versions: HashMap<UserId, u64>,
 }
 
 #[async_trait::async_trait]
@@ -26,13 +27,22 @@ impl UserStore for HashmapUserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         match self.users.get(&email) {
             Some(user) => Ok(User {
+                id: user.id.clone(),
                 email: user.email.clone(),
                 password: user.password.clone(),
-                requires_2fa: user.requires_2fa,
+                two_fa_method: user.two_fa_method,
+                email_verified: user.email_verified,
             }),
             None => Err(UserStoreError::UserNotFound)
         }
     }
+
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        match self.users.values().find(|user| user.id == *id) {
+            Some(user) => Ok(user.clone()),
+            None => Err(UserStoreError::UserNotFound)
+        }
+    }
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
         // Check if user exists in the HashMap using the email
         match self.users.get(&email) {
@@ -48,11 +58,79 @@ impl UserStore for HashmapUserStore {
             None => Err(UserStoreError::UserNotFound)
         }
     }
+
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+        let id = match self.users.get_mut(email) {
+            Some(user) => {
+                user.password = password;
+                user.id.clone()
+            },
+            None => return Err(UserStoreError::UserNotFound)
+        };
+        self.bump_token_version(&id).await
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.email_verified = true;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound)
+        }
+    }
+
+    async fn update_user(&mut self, user: &User) -> Result<(), UserStoreError> {
+        match self.users.values_mut().find(|stored| stored.id == user.id) {
+            Some(stored) => {
+                stored.two_fa_method = user.two_fa_method;
+                stored.email_verified = user.email_verified;
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound)
+        }
+    }
+
+    async fn get_token_version(&self, id: &UserId) -> Result<u64, UserStoreError> {
+        if !self.users.values().any(|user| user.id == *id) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.token_versions.get(id).copied().unwrap_or_default())
+    }
+
+    async fn bump_token_version(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        if !self.users.values().any(|user| user.id == *id) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        *self.token_versions.entry(id.clone()).or_default() += 1;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.remove(email) {
+            Some(user) => {
+                self.token_versions.remove(&user.id);
+                Ok(())
+            },
+            None => Err(UserStoreError::UserNotFound)
+        }
+    }
+
+    async fn update_email(&mut self, email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        if self.users.contains_key(&new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self.users.remove(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email = new_email.clone();
+        self.users.insert(new_email, user);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::TwoFAMethod;
     use crate::domain::User;
     use crate::domain::Email;
     use crate::domain::Password;
@@ -61,7 +139,7 @@ mod tests {
     #[tokio::test]
     async fn test_add_user() {
         let mut store = HashmapUserStore::default();
-        let user = User::new(Email::parse(Secret::new("test@example.com".to_string())).unwrap(), Password::parse(Secret::new("Password123!".to_string())).unwrap(), true );
+        let user = User::new(Email::parse(Secret::new("test@example.com".to_string())).unwrap(), Password::parse(Secret::new("Password123!".to_string())).unwrap(), TwoFAMethod::Email);
 
         // Test adding a new user
         let result = store.add_user(user.clone());
@@ -75,7 +153,7 @@ mod tests {
     #[tokio::test]
     async fn test_get_user() {
         let mut store = HashmapUserStore::default();
-        let user = User::new(Email::parse(Secret::new("test@example.com".to_string())).unwrap(), Password::parse(Secret::new("Password123!".to_string())).unwrap(), true );
+        let user = User::new(Email::parse(Secret::new("test@example.com".to_string())).unwrap(), Password::parse(Secret::new("Password123!".to_string())).unwrap(), TwoFAMethod::Email);
 
 
         // Add a user first
@@ -95,9 +173,11 @@ mod tests {
     async fn test_validate_user() {
         let mut store = HashmapUserStore::default();
         let user = User {
+            id: UserId::default(),
             email: Email::parse(Secret::new("test@example.com".to_string())).unwrap(),
             password: Password(Secret::new("Password123!".to_string())),
-            requires_2fa: true
+            two_fa_method: TwoFAMethod::Email,
+            email_verified: false,
         };
 
         // Add a user first
@@ -115,4 +195,143 @@ mod tests {
         let result = store.validate_user(&Email::parse(Secret::new("nonexistent@example.com".to_string())).unwrap(), &Password::parse(Secret::new("Password123!".to_string())).unwrap()).await;
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let old_password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let new_password = Password::parse(Secret::new("NewPassword123!".to_string())).unwrap();
+        let _ = store.add_user(User::new(email.clone(), old_password.clone(), TwoFAMethod::None)).await;
+
+        // Test updating the password of an existing user
+        let result = store.update_password(&email, new_password.clone()).await;
+        assert!(result.is_ok());
+        assert_eq!(store.validate_user(&email, &new_password).await, Ok(()));
+        assert_eq!(store.validate_user(&email, &old_password).await, Err(UserStoreError::InvalidCredentials));
+
+        // Test updating the password of a non-existent user
+        let result = store.update_password(&Email::parse(Secret::new("nonexistent@example.com".to_string())).unwrap(), new_password).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_token_version() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let user = User::new(email.clone(), password.clone(), TwoFAMethod::None);
+        let _ = store.add_user(user.clone()).await;
+        assert_eq!(store.get_token_version(&user.id).await, Ok(0));
+
+        store.bump_token_version(&user.id).await.unwrap();
+        assert_eq!(store.get_token_version(&user.id).await, Ok(1));
+
+        // Changing the password bumps it as well
+        store.update_password(&email, password).await.unwrap();
+        assert_eq!(store.get_token_version(&user.id).await, Ok(2));
+
+        let nonexistent = UserId::default();
+        assert_eq!(store.get_token_version(&nonexistent).await, Err(UserStoreError::UserNotFound));
+        assert_eq!(store.bump_token_version(&nonexistent).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let _ = store.add_user(User::new(email.clone(), password, TwoFAMethod::None)).await;
+        assert!(!store.get_user(&email).await.unwrap().email_verified);
+
+        // Test verifying an existing user
+        let result = store.mark_email_verified(&email).await;
+        assert!(result.is_ok());
+        assert!(store.get_user(&email).await.unwrap().email_verified);
+
+        // Test verifying a non-existent user
+        let result = store.mark_email_verified(&Email::parse(Secret::new("nonexistent@example.com".to_string())).unwrap()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let _ = store.add_user(User::new(email.clone(), password, TwoFAMethod::Email)).await;
+
+        // Test switching an existing user to TOTP
+        let mut user = store.get_user(&email).await.unwrap();
+        user.two_fa_method = TwoFAMethod::Totp;
+        let result = store.update_user(&user).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_user(&email).await.unwrap().two_fa_method, TwoFAMethod::Totp);
+
+        // Test updating a non-existent user
+        let other = User::new(
+            Email::parse(Secret::new("nonexistent@example.com".to_string())).unwrap(),
+            Password::parse(Secret::new("Password123!".to_string())).unwrap(),
+            TwoFAMethod::Totp,
+        );
+        let result = store.update_user(&other).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let _ = store.add_user(User::new(email.clone(), password, TwoFAMethod::None)).await;
+
+        // Test deleting an existing user
+        let result = store.delete_user(&email).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_user(&email).await, Err(UserStoreError::UserNotFound));
+
+        // Test deleting a user that no longer exists
+        let result = store.delete_user(&email).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_id() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let user = User::new(email, password, TwoFAMethod::None);
+        let _ = store.add_user(user.clone()).await;
+
+        assert_eq!(store.get_user_by_id(&user.id).await, Ok(user));
+        assert_eq!(store.get_user_by_id(&UserId::default()).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_email() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let new_email = Email::parse(Secret::new("new@example.com".to_string())).unwrap();
+        let taken_email = Email::parse(Secret::new("taken@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let user = User::new(email.clone(), password.clone(), TwoFAMethod::None);
+        let _ = store.add_user(user.clone()).await;
+        let _ = store.add_user(User::new(taken_email.clone(), password, TwoFAMethod::None)).await;
+        store.bump_token_version(&user.id).await.unwrap();
+
+        // Test moving to an address of another user
+        let result = store.update_email(&email, taken_email).await;
+        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
+
+        // Test moving to a free address, which keeps the id and the token version
+        let result = store.update_email(&email, new_email.clone()).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_user(&email).await, Err(UserStoreError::UserNotFound));
+        assert_eq!(store.get_user_by_id(&user.id).await.unwrap().email, new_email);
+        assert_eq!(store.get_token_version(&user.id).await, Ok(1));
+
+        // Test updating a non-existent user
+        let result = store.update_email(&email, Email::parse(Secret::new("other@example.com".to_string())).unwrap()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+}
@@ -1,6 +1,40 @@
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
 pub mod hashmap_two_fa_code_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_email_verification_token_store;
+pub mod hashmap_email_change_token_store;
+pub mod hashmap_magic_link_token_store;
+pub mod hashmap_totp_secret_store;
+pub mod hashmap_recovery_code_store;
+pub mod hashmap_login_attempt_store;
+pub mod hashmap_client_store;
+pub mod hashmap_session_store;
+pub mod hashmap_passkey_store;
+pub mod hashmap_passkey_challenge_store;
+pub mod hashmap_authorization_code_store;
+pub mod hashmap_consent_store;
+pub mod hashmap_federated_login_store;
+pub mod hashmap_federated_identity_store;
+pub mod hashmap_role_store;
 pub mod postgres_user_store;
+pub mod postgres_totp_secret_store;
+pub mod postgres_recovery_code_store;
+pub mod postgres_client_store;
+pub mod postgres_passkey_store;
+pub mod postgres_consent_store;
+pub mod postgres_federated_identity_store;
+pub mod postgres_role_store;
 pub mod redis_banned_token_store;
-pub mod redis_two_fa_code_store;
\ No newline at end of file
+pub mod redis_two_fa_code_store;
+pub mod redis_refresh_token_store;
+pub mod redis_password_reset_token_store;
+pub mod redis_email_verification_token_store;
+pub mod redis_email_change_token_store;
+pub mod redis_magic_link_token_store;
+pub mod redis_login_attempt_store;
+pub mod redis_session_store;
+pub mod redis_passkey_challenge_store;
+pub mod redis_authorization_code_store;
+pub mod redis_federated_login_store;
\ No newline at end of file
@@ -0,0 +1,139 @@
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use super::postgres_user_store::{compute_password_hash, verify_password_hash};
+use crate::domain::{
+    data_stores::{ClientStore, ClientStoreError},
+    ClientKind, OAuthClient, RedirectUri, Scope,
+};
+
+pub struct PostgresClientStore {
+    pool: PgPool,
+}
+
+impl PostgresClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientStore for PostgresClientStore {
+    #[tracing::instrument(name = "Registering client in PostgreSQL", skip_all)]
+    async fn register_client(
+        &mut self,
+        client_id: &str,
+        secret: Secret<String>,
+        scope: Scope,
+    ) -> Result<(), ClientStoreError> {
+        let client_secret_hash = compute_password_hash(secret.expose_secret().to_owned())
+            .await
+            .map_err(ClientStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO clients (client_id, client_secret_hash, name, scope, kind)
+            VALUES ($1, $2, $1, $3, 'service')
+            ON CONFLICT (client_id) DO UPDATE
+            SET client_secret_hash = EXCLUDED.client_secret_hash, scope = EXCLUDED.scope,
+                kind = EXCLUDED.kind
+            "#,
+            client_id,
+            client_secret_hash,
+            scope.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ClientStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding OAuth client to PostgreSQL", skip_all)]
+    async fn add_client(&mut self, client: OAuthClient, secret: Option<Secret<String>>) -> Result<(), ClientStoreError> {
+        let client_secret_hash = match secret {
+            Some(secret) => Some(
+                compute_password_hash(secret.expose_secret().to_owned())
+                    .await
+                    .map_err(ClientStoreError::UnexpectedError)?,
+            ),
+            None => None,
+        };
+        let redirect_uris: Vec<String> = client
+            .redirect_uris
+            .iter()
+            .map(|uri| uri.as_ref().to_owned())
+            .collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO clients (client_id, client_secret_hash, name, redirect_uris, kind)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            client.id,
+            client_secret_hash,
+            client.name,
+            &redirect_uris,
+            client.kind.as_str(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                ClientStoreError::ClientAlreadyExists
+            }
+            e => ClientStoreError::UnexpectedError(e.into()),
+        })?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth client from PostgreSQL", skip_all)]
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, ClientStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT client_id, name, redirect_uris, scope, kind, client_secret_hash IS NOT NULL AS "confidential!"
+            FROM clients
+            WHERE client_id = $1
+            "#,
+            client_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClientStoreError::UnexpectedError(e.into()))?
+        .ok_or(ClientStoreError::ClientNotFound)?;
+
+        let redirect_uris = row
+            .redirect_uris
+            .into_iter()
+            .map(RedirectUri::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ClientStoreError::UnexpectedError)?;
+
+        Ok(OAuthClient {
+            id: row.client_id,
+            name: row.name,
+            redirect_uris,
+            confidential: row.confidential,
+            kind: ClientKind::parse(&row.kind).map_err(ClientStoreError::UnexpectedError)?,
+            scope: Scope::parse(&row.scope).map_err(ClientStoreError::UnexpectedError)?,
+        })
+    }
+
+    #[tracing::instrument(name = "Validating client credentials in PostgreSQL", skip_all)]
+    async fn validate_client(&self, client_id: &str, secret: &Secret<String>) -> Result<(), ClientStoreError> {
+        let row = sqlx::query!(
+            "SELECT client_secret_hash FROM clients WHERE client_id = $1",
+            client_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ClientStoreError::UnexpectedError(e.into()))?
+        .ok_or(ClientStoreError::InvalidCredentials)?;
+
+        // Public clients have no secret to authenticate with
+        let client_secret_hash = row.client_secret_hash.ok_or(ClientStoreError::InvalidCredentials)?;
+
+        verify_password_hash(Secret::new(client_secret_hash), secret.clone())
+            .await
+            .map_err(|_| ClientStoreError::InvalidCredentials)
+    }
+}
@@ -0,0 +1,62 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{ConsentStore, ConsentStoreError},
+    Scope, UserId,
+};
+
+pub struct PostgresConsentStore {
+    pool: PgPool,
+}
+
+impl PostgresConsentStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ConsentStore for PostgresConsentStore {
+    #[tracing::instrument(name = "Granting consent in PostgreSQL", skip_all)]
+    async fn grant_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &str,
+        scope: Scope,
+    ) -> Result<(), ConsentStoreError> {
+        let user_id =
+            Uuid::parse_str(user_id.as_ref()).map_err(|e| ConsentStoreError::UnexpectedError(e.into()))?;
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_consents (user_id, client_id, scope)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, client_id) DO UPDATE SET scope = EXCLUDED.scope, granted_at = now()
+            "#,
+            user_id,
+            client_id,
+            scope.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ConsentStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving consent from PostgreSQL", skip_all)]
+    async fn get_consent(&self, user_id: &UserId, client_id: &str) -> Result<Scope, ConsentStoreError> {
+        let user_id =
+            Uuid::parse_str(user_id.as_ref()).map_err(|e| ConsentStoreError::UnexpectedError(e.into()))?;
+        let row = sqlx::query!(
+            "SELECT scope FROM oauth_consents WHERE user_id = $1 AND client_id = $2",
+            user_id,
+            client_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ConsentStoreError::UnexpectedError(e.into()))?
+        .ok_or(ConsentStoreError::ConsentNotFound)?;
+
+        Scope::parse(&row.scope).map_err(ConsentStoreError::UnexpectedError)
+    }
+}
@@ -0,0 +1,60 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{FederatedIdentityStore, FederatedIdentityStoreError},
+    UserId,
+};
+
+pub struct PostgresFederatedIdentityStore {
+    pool: PgPool,
+}
+
+impl PostgresFederatedIdentityStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl FederatedIdentityStore for PostgresFederatedIdentityStore {
+    #[tracing::instrument(name = "Linking federated identity in PostgreSQL", skip_all)]
+    async fn link_identity(
+        &mut self,
+        provider: &str,
+        subject: &str,
+        user_id: &UserId,
+    ) -> Result<(), FederatedIdentityStoreError> {
+        let user_id = Uuid::parse_str(user_id.as_ref())
+            .map_err(|e| FederatedIdentityStoreError::UnexpectedError(e.into()))?;
+        sqlx::query!(
+            r#"
+            INSERT INTO federated_identities (provider, subject, user_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (provider, subject) DO UPDATE SET user_id = EXCLUDED.user_id, linked_at = now()
+            "#,
+            provider,
+            subject,
+            user_id,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| FederatedIdentityStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving federated identity from PostgreSQL", skip_all)]
+    async fn get_user_id(&self, provider: &str, subject: &str) -> Result<UserId, FederatedIdentityStoreError> {
+        let row = sqlx::query!(
+            "SELECT user_id FROM federated_identities WHERE provider = $1 AND subject = $2",
+            provider,
+            subject,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| FederatedIdentityStoreError::UnexpectedError(e.into()))?
+        .ok_or(FederatedIdentityStoreError::IdentityNotFound)?;
+
+        UserId::parse(row.user_id.to_string()).map_err(FederatedIdentityStoreError::UnexpectedError)
+    }
+}
@@ -0,0 +1,120 @@
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{PasskeyStore, PasskeyStoreError},
+    Email, PasskeyCredential,
+};
+
+pub struct PostgresPasskeyStore {
+    pool: PgPool,
+}
+
+impl PostgresPasskeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for PostgresPasskeyStore {
+    #[tracing::instrument(name = "Adding passkey to PostgreSQL", skip_all)]
+    async fn add_credential(&mut self, credential: PasskeyCredential) -> Result<(), PasskeyStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO passkey_credentials (credential_id, email, public_key, sign_count)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            credential.id,
+            credential.email.as_ref().expose_secret(),
+            credential.public_key,
+            i64::from(credential.sign_count),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => PasskeyStoreError::CredentialAlreadyExists,
+            e => PasskeyStoreError::UnexpectedError(e.into()),
+        })?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving passkey from PostgreSQL", skip_all)]
+    async fn get_credential(&self, credential_id: &str) -> Result<PasskeyCredential, PasskeyStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT credential_id, email, public_key, sign_count
+            FROM passkey_credentials
+            WHERE credential_id = $1
+            "#,
+            credential_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(PasskeyStoreError::CredentialNotFound)?;
+
+        Ok(PasskeyCredential {
+            id: row.credential_id,
+            email: Email::parse(Secret::new(row.email)).map_err(PasskeyStoreError::UnexpectedError)?,
+            public_key: row.public_key,
+            sign_count: u32::try_from(row.sign_count).map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user passkeys from PostgreSQL", skip_all)]
+    async fn get_user_credentials(&self, email: &Email) -> Result<Vec<PasskeyCredential>, PasskeyStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT credential_id, public_key, sign_count
+            FROM passkey_credentials
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(PasskeyCredential {
+                    id: row.credential_id,
+                    email: email.clone(),
+                    public_key: row.public_key,
+                    sign_count: u32::try_from(row.sign_count)
+                        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Updating passkey sign count in PostgreSQL", skip_all)]
+    async fn update_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), PasskeyStoreError> {
+        let result = sqlx::query!(
+            "UPDATE passkey_credentials SET sign_count = $2 WHERE credential_id = $1",
+            credential_id,
+            i64::from(sign_count),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(PasskeyStoreError::CredentialNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting passkeys of user from PostgreSQL", skip_all)]
+    async fn delete_user_credentials(&mut self, email: &Email) -> Result<(), PasskeyStoreError> {
+        sqlx::query!(
+            "DELETE FROM passkey_credentials WHERE email = $1",
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
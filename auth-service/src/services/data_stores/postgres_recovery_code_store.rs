@@ -0,0 +1,103 @@
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use super::postgres_user_store::{compute_password_hash, verify_password_hash};
+use crate::domain::{
+    data_stores::{RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError},
+    Email,
+};
+
+pub struct PostgresRecoveryCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+    #[tracing::instrument(name = "Replacing recovery codes in PostgreSQL", skip_all)]
+    async fn replace_codes(&mut self, email: &Email, codes: Vec<RecoveryCode>) -> Result<(), RecoveryCodeStoreError> {
+        // Codes are hashed like passwords, only the user ever sees them in clear.
+        // Hashing is slow by design, so the codes are hashed concurrently.
+        let hash_tasks: Vec<_> = codes
+            .into_iter()
+            .map(|code| tokio::spawn(compute_password_hash(code.as_ref().expose_secret().to_owned())))
+            .collect();
+
+        let mut code_hashes = Vec::with_capacity(hash_tasks.len());
+        for task in hash_tasks {
+            let code_hash = task
+                .await
+                .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?
+                .map_err(RecoveryCodeStoreError::UnexpectedError)?;
+            code_hashes.push(code_hash);
+        }
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            "DELETE FROM recovery_codes WHERE email = $1",
+            email.as_ref().expose_secret(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            "INSERT INTO recovery_codes (email, code_hash) SELECT $1, UNNEST($2::TEXT[])",
+            email.as_ref().expose_secret(),
+            &code_hashes,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using recovery code in PostgreSQL", skip_all)]
+    async fn use_code(&mut self, email: &Email, code: &RecoveryCode) -> Result<(), RecoveryCodeStoreError> {
+        let rows = sqlx::query!(
+            "SELECT id, code_hash FROM recovery_codes WHERE email = $1",
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        // Salted hashes can not be looked up directly, so each one has to be checked
+        for row in rows {
+            if verify_password_hash(Secret::new(row.code_hash), code.as_ref().clone())
+                .await
+                .is_err()
+            {
+                continue;
+            }
+
+            // Deleting by id makes concurrent uses of the same code race for a single row
+            let result = sqlx::query!("DELETE FROM recovery_codes WHERE id = $1", row.id)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+            return match result.rows_affected() {
+                0 => Err(RecoveryCodeStoreError::CodeNotFound),
+                _ => Ok(()),
+            };
+        }
+
+        Err(RecoveryCodeStoreError::CodeNotFound)
+    }
+}
@@ -0,0 +1,106 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{RoleStore, RoleStoreError},
+    Permission, Role, UserId,
+};
+
+pub struct PostgresRoleStore {
+    pool: PgPool,
+}
+
+impl PostgresRoleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for PostgresRoleStore {
+    #[tracing::instrument(name = "Retrieving roles from PostgreSQL", skip_all)]
+    async fn get_roles(&self, user_id: &UserId) -> Result<Vec<Role>, RoleStoreError> {
+        let user_id =
+            Uuid::parse_str(user_id.as_ref()).map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        let rows = sqlx::query!(
+            "SELECT role FROM user_roles WHERE user_id = $1 ORDER BY role",
+            user_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| Role::parse(row.role).map_err(RoleStoreError::UnexpectedError))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Granting role in PostgreSQL", skip_all)]
+    async fn grant_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        let user_id =
+            Uuid::parse_str(user_id.as_ref()).map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        // Selecting from `roles` inserts nothing for roles that are not defined
+        let granted = sqlx::query!(
+            r#"
+            INSERT INTO user_roles (user_id, role)
+            SELECT $1, name FROM roles WHERE name = $2
+            ON CONFLICT (user_id, role) DO NOTHING
+            "#,
+            user_id,
+            role.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?
+        .rows_affected();
+        if granted > 0 {
+            return Ok(());
+        }
+
+        // Nothing inserted, either the user already holds the role or it does not exist
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1) AS "exists!""#,
+            role.as_ref(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        if exists {
+            Ok(())
+        } else {
+            Err(RoleStoreError::RoleNotFound)
+        }
+    }
+
+    #[tracing::instrument(name = "Revoking role in PostgreSQL", skip_all)]
+    async fn revoke_role(&mut self, user_id: &UserId, role: &Role) -> Result<(), RoleStoreError> {
+        let user_id =
+            Uuid::parse_str(user_id.as_ref()).map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        sqlx::query!(
+            "DELETE FROM user_roles WHERE user_id = $1 AND role = $2",
+            user_id,
+            role.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking permission in PostgreSQL", skip_all)]
+    async fn has_permission(&self, roles: &[Role], permission: &Permission) -> Result<bool, RoleStoreError> {
+        let roles: Vec<String> = roles.iter().map(|role| role.as_ref().to_owned()).collect();
+        sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM role_permissions WHERE role = ANY($1) AND permission = $2
+            ) AS "exists!"
+            "#,
+            &roles,
+            permission.as_ref(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))
+    }
+}
@@ -0,0 +1,167 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{
+        data_stores::{TotpSecretStore, TotpSecretStoreError},
+        Email, TotpSecret,
+    },
+    utils::constants::TOTP_ENCRYPTION_KEY,
+};
+
+pub struct PostgresTotpSecretStore {
+    pool: PgPool,
+}
+
+impl PostgresTotpSecretStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpSecretStore for PostgresTotpSecretStore {
+    #[tracing::instrument(name = "Storing pending TOTP secret in PostgreSQL", skip_all)]
+    async fn set_pending_secret(&mut self, email: &Email, secret: TotpSecret) -> Result<(), TotpSecretStoreError> {
+        let encrypted_secret = encrypt_secret(&secret).map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        let query = sqlx::query!(
+            r#"
+            INSERT INTO totp_secrets (email, pending_secret) VALUES ($1, $2)
+            ON CONFLICT (email) DO UPDATE SET pending_secret = EXCLUDED.pending_secret
+            "#,
+            email.as_ref().expose_secret(),
+            encrypted_secret,
+        );
+
+        query.execute(&self.pool).await.map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving pending TOTP secret from PostgreSQL", skip_all)]
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, TotpSecretStoreError> {
+        let query = sqlx::query!(
+            "SELECT pending_secret FROM totp_secrets WHERE email = $1",
+            email.as_ref().expose_secret(),
+        );
+
+        let row = query
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+
+        match row.and_then(|row| row.pending_secret) {
+            Some(encrypted_secret) => decrypt_secret(&encrypted_secret).map_err(TotpSecretStoreError::UnexpectedError),
+            None => Err(TotpSecretStoreError::SecretNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Confirming pending TOTP secret in PostgreSQL", skip_all)]
+    async fn confirm_pending_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        // Steps used with the old secret say nothing about the new one
+        let query = sqlx::query!(
+            r#"
+            UPDATE totp_secrets SET secret = pending_secret, pending_secret = NULL, last_used_step = NULL
+            WHERE email = $1 AND pending_secret IS NOT NULL
+            "#,
+            email.as_ref().expose_secret(),
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(TotpSecretStoreError::SecretNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP secret from PostgreSQL", skip_all)]
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, TotpSecretStoreError> {
+        let query = sqlx::query!(
+            "SELECT secret FROM totp_secrets WHERE email = $1",
+            email.as_ref().expose_secret(),
+        );
+
+        let row = query
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+
+        match row.and_then(|row| row.secret) {
+            Some(encrypted_secret) => decrypt_secret(&encrypted_secret).map_err(TotpSecretStoreError::UnexpectedError),
+            None => Err(TotpSecretStoreError::SecretNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Recording used TOTP step in PostgreSQL", skip_all)]
+    async fn record_used_step(&mut self, email: &Email, step: u64) -> Result<(), TotpSecretStoreError> {
+        let step = i64::try_from(step).map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+
+        // Checked and updated in one statement, so two requests can not both use a code
+        let query = sqlx::query!(
+            r#"
+            UPDATE totp_secrets SET last_used_step = $2
+            WHERE email = $1 AND secret IS NOT NULL AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            email.as_ref().expose_secret(),
+            step,
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(TotpSecretStoreError::StepAlreadyUsed);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting TOTP secret from PostgreSQL", skip_all)]
+    async fn delete_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        sqlx::query!(
+            "DELETE FROM totp_secrets WHERE email = $1",
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+// The configured key can be any string, it is stretched to the 256 bits AES needs
+fn cipher() -> Aes256Gcm {
+    let key = Sha256::digest(TOTP_ENCRYPTION_KEY.expose_secret().as_bytes());
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+// Returns the random nonce followed by the ciphertext
+#[tracing::instrument(name = "Encrypt TOTP secret", skip_all)]
+fn encrypt_secret(secret: &TotpSecret) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher()
+        .encrypt(&nonce, secret.as_ref().expose_secret().as_bytes())
+        .map_err(|_| eyre!("failed to encrypt TOTP secret"))?;
+
+    let mut encrypted_secret = nonce.to_vec();
+    encrypted_secret.extend_from_slice(&ciphertext);
+    Ok(encrypted_secret)
+}
+
+#[tracing::instrument(name = "Decrypt TOTP secret", skip_all)]
+fn decrypt_secret(encrypted_secret: &[u8]) -> Result<TotpSecret> {
+    if encrypted_secret.len() < NONCE_LENGTH {
+        return Err(eyre!("encrypted TOTP secret is too short"));
+    }
+    let (nonce, ciphertext) = encrypted_secret.split_at(NONCE_LENGTH);
+    let plaintext = cipher()
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| eyre!("failed to decrypt TOTP secret"))?;
+
+    TotpSecret::parse(Secret::new(String::from_utf8(plaintext)?))
+}
+
+const NONCE_LENGTH: usize = 12;
@@ -5,10 +5,11 @@ use argon2::{
 use color_eyre::eyre::{ Context, Result};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
+use uuid::Uuid;
 
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+    Email, Password, TwoFAMethod, User, UserId,
 };
 
 
@@ -50,16 +51,41 @@ impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         let query = sqlx::query!(
-            "SELECT email, password_hash, requires_2fa FROM users WHERE email = $1",
+            "SELECT id, email, password_hash, two_fa_method, email_verified FROM users WHERE email = $1",
             email.as_ref().expose_secret(),
         );
 
         let user = query.fetch_one(&self.pool).await.map_err(|_| UserStoreError::UserNotFound)?;
 
         Ok(User {
+            id: UserId::parse(user.id.to_string()).map_err(UserStoreError::UnexpectedError)?,
             email: Email::parse(Secret::new(user.email)).unwrap(),
             password: Password::parse(Secret::new(user.password_hash)).unwrap(), // Treating the stored value as a hash
-            requires_2fa: user.requires_2fa,
+            two_fa_method: TwoFAMethod::parse(&user.two_fa_method).map_err(UserStoreError::UnexpectedError)?,
+            email_verified: user.email_verified,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user by id from PostgreSQL", skip_all)]
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        let id = Uuid::parse_str(id.as_ref()).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let query = sqlx::query!(
+            "SELECT id, email, password_hash, two_fa_method, email_verified FROM users WHERE id = $1",
+            id,
+        );
+
+        let user = query
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        Ok(User {
+            id: UserId::parse(user.id.to_string()).map_err(UserStoreError::UnexpectedError)?,
+            email: Email::parse(Secret::new(user.email)).map_err(UserStoreError::UnexpectedError)?,
+            password: Password::parse(Secret::new(user.password_hash)).unwrap(), // Treating the stored value as a hash
+            two_fa_method: TwoFAMethod::parse(&user.two_fa_method).map_err(UserStoreError::UnexpectedError)?,
+            email_verified: user.email_verified,
         })
     }
 
@@ -70,20 +96,139 @@ impl UserStore for PostgresUserStore {
             .await
             .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
         
+        let id = Uuid::parse_str(user.id.as_ref()).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
         let query = sqlx::query!(
-            "INSERT INTO users (email, password_hash, requires_2fa) VALUES ($1, $2, $3)",
+            "INSERT INTO users (id, email, password_hash, two_fa_method, email_verified) VALUES ($1, $2, $3, $4, $5)",
+            id,
             user.email.as_ref().expose_secret(),
             password_hash,  // Store the hash, not the raw password
-            user.requires_2fa,
+            user.two_fa_method.as_str(),
+            user.email_verified,
         );
 
         query.execute(&self.pool).await.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
         Ok(())
     }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref().expose_secret().to_string())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let query = sqlx::query!(
+            "UPDATE users SET password_hash = $1, token_version = token_version + 1 WHERE email = $2",
+            password_hash,
+            email.as_ref().expose_secret(),
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user email as verified in PostgreSQL", skip_all)]
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let query = sqlx::query!(
+            "UPDATE users SET email_verified = TRUE WHERE email = $1",
+            email.as_ref().expose_secret(),
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving token version from PostgreSQL", skip_all)]
+    async fn get_token_version(&self, id: &UserId) -> Result<u64, UserStoreError> {
+        let id = Uuid::parse_str(id.as_ref()).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let row = sqlx::query!(
+            "SELECT token_version FROM users WHERE id = $1",
+            id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        Ok(row.token_version as u64)
+    }
+
+    #[tracing::instrument(name = "Bumping token version in PostgreSQL", skip_all)]
+    async fn bump_token_version(&mut self, id: &UserId) -> Result<(), UserStoreError> {
+        let id = Uuid::parse_str(id.as_ref()).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let query = sqlx::query!(
+            "UPDATE users SET token_version = token_version + 1 WHERE id = $1",
+            id,
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user in PostgreSQL", skip_all)]
+    async fn update_user(&mut self, user: &User) -> Result<(), UserStoreError> {
+        let id = Uuid::parse_str(user.id.as_ref()).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let query = sqlx::query!(
+            "UPDATE users SET two_fa_method = $1, email_verified = $2 WHERE id = $3",
+            user.two_fa_method.as_str(),
+            user.email_verified,
+            id,
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    // TOTP secrets and recovery codes of the user are removed along with the row
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let query = sqlx::query!(
+            "DELETE FROM users WHERE email = $1",
+            email.as_ref().expose_secret(),
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    // TOTP secrets and recovery codes of the user follow the new address through ON UPDATE CASCADE
+    #[tracing::instrument(name = "Updating user email in PostgreSQL", skip_all)]
+    async fn update_email(&mut self, email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        let query = sqlx::query!(
+            "UPDATE users SET email = $1 WHERE email = $2",
+            new_email.as_ref().expose_secret(),
+            email.as_ref().expose_secret(),
+        );
+
+        let result = query.execute(&self.pool).await.map_err(|e| match e {
+            sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
 }
 
 #[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
+pub(crate) async fn verify_password_hash(
     expected_password_hash: Secret<String>, // Updated!
     password_candidate: Secret<String>, // Updated!
 ) -> Result<()> {
@@ -107,7 +252,7 @@ async fn verify_password_hash(
 }
 
 #[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: String) -> Result<String> { // Changed!
+pub(crate) async fn compute_password_hash(password: String) -> Result<String> { // Changed!
     let current_span: tracing::Span = tracing::Span::current();
 
     let result = tokio::task::spawn_blocking(move || {
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError,
+            AuthorizationGrant,
+        },
+        AuthenticationMethod, PkceChallenge, RedirectUri, Scope, UserId,
+    },
+    utils::constants::AUTHORIZATION_CODE_TTL_SECONDS,
+};
+
+pub struct RedisAuthorizationCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for RedisAuthorizationCodeStore {
+    #[tracing::instrument(name = "Add Authorization Code", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let grant = serde_json::to_string(&StoredGrant::from(grant))
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(&code), grant, AUTHORIZATION_CODE_TTL_SECONDS)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Authorization Grant", skip_all)]
+    async fn get_grant(
+        &self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        let grant: String = self
+            .conn
+            .write()
+            .await
+            .get(get_key(code))
+            .map_err(|_| AuthorizationCodeStoreError::CodeNotFound)?;
+        let grant: StoredGrant = serde_json::from_str(&grant)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+        grant.try_into()
+    }
+
+    #[tracing::instrument(name = "Remove Authorization Code", skip_all)]
+    async fn remove_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(get_key(code))
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredGrant {
+    client_id: String,
+    redirect_uri: String,
+    user_id: String,
+    scope: String,
+    code_challenge: String,
+    nonce: Option<String>,
+    auth_time: i64,
+    methods: Vec<AuthenticationMethod>,
+}
+
+impl From<AuthorizationGrant> for StoredGrant {
+    fn from(grant: AuthorizationGrant) -> Self {
+        Self {
+            client_id: grant.client_id,
+            redirect_uri: grant.redirect_uri.as_ref().to_owned(),
+            user_id: grant.user_id.as_ref().to_owned(),
+            scope: grant.scope.as_ref().to_owned(),
+            code_challenge: grant.code_challenge.as_ref().to_owned(),
+            nonce: grant.nonce,
+            auth_time: grant.auth_time,
+            methods: grant.methods,
+        }
+    }
+}
+
+impl TryFrom<StoredGrant> for AuthorizationGrant {
+    type Error = AuthorizationCodeStoreError;
+
+    fn try_from(grant: StoredGrant) -> Result<Self, Self::Error> {
+        Ok(Self {
+            client_id: grant.client_id,
+            redirect_uri: RedirectUri::parse(grant.redirect_uri)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            user_id: UserId::parse(grant.user_id)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            scope: Scope::parse(&grant.scope).map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            code_challenge: PkceChallenge::parse(grant.code_challenge, Some("S256"))
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            nonce: grant.nonce,
+            auth_time: grant.auth_time,
+            methods: grant.methods,
+        })
+    }
+}
+
+const AUTHORIZATION_CODE_PREFIX: &str = "authorization_code:";
+
+fn get_key(code: &AuthorizationCode) -> String {
+    format!("{}{}", AUTHORIZATION_CODE_PREFIX, code.as_ref().expose_secret())
+}
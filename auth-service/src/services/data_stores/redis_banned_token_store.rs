@@ -5,9 +5,8 @@ use tokio::sync::RwLock;
 
 use crate::{
     domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+    utils::{auth::TOKEN_TTL_SECONDS, constants::JWT_LEEWAY_SECONDS},
 };
-use secrecy::{ExposeSecret, Secret};
 pub struct RedisBannedTokenStore {
     conn: Arc<RwLock<Connection>>,
 }
@@ -21,7 +20,7 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Store Token", skip_all)]
-    async fn store_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+    async fn store_token(&mut self, jti: String) -> Result<(), BannedTokenStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
         // 2. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL). 
@@ -30,16 +29,17 @@ impl BannedTokenStore for RedisBannedTokenStore {
         // NOTE: The TTL is expected to be a u64 so you will have to cast TOKEN_TTL_SECONDS to a u64. 
         // Return BannedTokenStoreError::UnexpectedError if casting fails or the call to set_ex fails.
 
-        let key = get_key(&token.expose_secret());
-        let ttl = TOKEN_TTL_SECONDS as u64;
+        let key = get_key(&jti);
+        // Outlive the token, including the leeway it may still be accepted with
+        let ttl = TOKEN_TTL_SECONDS as u64 + JWT_LEEWAY_SECONDS;
         let _: () = self.conn.write().await.set_ex(key, "true", ttl).map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
         Ok(())
     }
 
     #[tracing::instrument(name = "Check Banned Token", skip_all)]
-    async fn check_banned_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
+    async fn check_banned_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
         // Check if the token exists by calling the exists method on the Redis connection
-        let key = get_key(&token.expose_secret());
+        let key = get_key(jti);
         let mut conn = self.conn.write().await;
         let exists: bool = conn.exists(key).map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
         Ok(exists)
@@ -52,6 +52,6 @@ impl BannedTokenStore for RedisBannedTokenStore {
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn get_key(jti: &str) -> String {
+    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, jti)
 }
\ No newline at end of file
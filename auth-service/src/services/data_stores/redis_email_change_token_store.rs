@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{EmailChangeToken, EmailChangeTokenStore, EmailChangeTokenStoreError},
+    Email, UserId,
+};
+
+pub struct RedisEmailChangeTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailChangeTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailChangeTokenStore for RedisEmailChangeTokenStore {
+    #[tracing::instrument(name = "Add Email Change Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        user_id: UserId,
+        new_email: Email,
+        token: EmailChangeToken,
+    ) -> Result<(), EmailChangeTokenStoreError> {
+        let user_key = get_user_key(&user_id);
+        let mut conn = self.conn.write().await;
+
+        // Only the most recently requested change stays pending
+        let previous_token: Option<String> = conn
+            .get(&user_key)
+            .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(previous_token) = previous_token {
+            let _: () = conn
+                .del(format!("{}{}", EMAIL_CHANGE_TOKEN_PREFIX, previous_token))
+                .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+        }
+
+        let change = serde_json::to_string(&StoredEmailChange {
+            user_id: user_id.as_ref().to_owned(),
+            new_email: new_email.as_ref().expose_secret().to_owned(),
+        })
+        .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+
+        let _: () = conn
+            .set_ex(get_token_key(&token), change, ONE_DAY_IN_SECONDS)
+            .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+        let _: () = conn
+            .set_ex(user_key, token.as_ref().expose_secret(), ONE_DAY_IN_SECONDS)
+            .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Email Change", skip_all)]
+    async fn get_change(
+        &self,
+        token: &EmailChangeToken,
+    ) -> Result<(UserId, Email), EmailChangeTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let change: String = conn
+            .get(get_token_key(token))
+            .map_err(|_| EmailChangeTokenStoreError::TokenNotFound)?;
+        let change: StoredEmailChange = serde_json::from_str(&change)
+            .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+
+        let user_id =
+            UserId::parse(change.user_id).map_err(EmailChangeTokenStoreError::UnexpectedError)?;
+        let new_email = Email::parse(Secret::new(change.new_email))
+            .map_err(EmailChangeTokenStoreError::UnexpectedError)?;
+        Ok((user_id, new_email))
+    }
+
+    #[tracing::instrument(name = "Remove Email Change Token", skip_all)]
+    async fn remove_token(
+        &mut self,
+        token: &EmailChangeToken,
+    ) -> Result<(), EmailChangeTokenStoreError> {
+        let key = get_token_key(token);
+        let mut conn = self.conn.write().await;
+        let change: Option<String> = conn
+            .get(&key)
+            .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(change) = change {
+            let change: StoredEmailChange = serde_json::from_str(&change)
+                .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+            let _: () = conn
+                .del(format!("{}{}", EMAIL_CHANGE_USER_PREFIX, change.user_id))
+                .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+        }
+        let _: () = conn
+            .del(key)
+            .map_err(|e| EmailChangeTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredEmailChange {
+    #[serde(rename = "userId")]
+    user_id: String,
+    #[serde(rename = "newEmail")]
+    new_email: String,
+}
+
+const ONE_DAY_IN_SECONDS: u64 = 60 * 60 * 24;
+const EMAIL_CHANGE_TOKEN_PREFIX: &str = "email_change_token:";
+const EMAIL_CHANGE_USER_PREFIX: &str = "email_change_user:";
+
+fn get_token_key(token: &EmailChangeToken) -> String {
+    format!("{}{}", EMAIL_CHANGE_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_user_key(user_id: &UserId) -> String {
+    format!("{}{}", EMAIL_CHANGE_USER_PREFIX, user_id.as_ref())
+}
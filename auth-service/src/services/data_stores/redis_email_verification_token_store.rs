@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError,
+        },
+        Email,
+    },
+    utils::constants::EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS,
+};
+
+pub struct RedisEmailVerificationTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailVerificationTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for RedisEmailVerificationTokenStore {
+    #[tracing::instrument(name = "Add Email Verification Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let email_key = get_email_key(&email);
+        let mut conn = self.conn.write().await;
+
+        // Only the most recently sent link stays valid
+        let previous_token: Option<String> = conn
+            .get(&email_key)
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(previous_token) = previous_token {
+            let _: () = conn
+                .del(format!("{}{}", EMAIL_VERIFICATION_TOKEN_PREFIX, previous_token))
+                .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        }
+
+        let _: () = conn
+            .set_ex(
+                get_token_key(&token),
+                email.as_ref().expose_secret(),
+                ONE_DAY_IN_SECONDS,
+            )
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        let _: () = conn
+            .set_ex(email_key, token.as_ref().expose_secret(), ONE_DAY_IN_SECONDS)
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Email Verification Token", skip_all)]
+    async fn get_email(
+        &self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let email: String = conn
+            .get(get_token_key(token))
+            .map_err(|_| EmailVerificationTokenStoreError::TokenNotFound)?;
+        Email::parse(Secret::new(email)).map_err(EmailVerificationTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Remove Email Verification Token", skip_all)]
+    async fn remove_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let key = get_token_key(token);
+        let mut conn = self.conn.write().await;
+        let email: Option<String> = conn
+            .get(&key)
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(email) = email {
+            let _: () = conn
+                .del(format!("{}{}", EMAIL_VERIFICATION_EMAIL_PREFIX, email))
+                .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        }
+        let _: () = conn
+            .del(key)
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Register Email Verification Resend", skip_all)]
+    async fn register_resend(&mut self, email: &Email) -> Result<(), EmailVerificationTokenStoreError> {
+        // SET NX EX only succeeds when no resend happened within the interval
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(get_resend_key(email))
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS)
+            .query(&mut *self.conn.write().await)
+            .map_err(|e| EmailVerificationTokenStoreError::UnexpectedError(e.into()))?;
+
+        match acquired {
+            Some(_) => Ok(()),
+            None => Err(EmailVerificationTokenStoreError::ResendThrottled),
+        }
+    }
+}
+
+const ONE_DAY_IN_SECONDS: u64 = 60 * 60 * 24;
+const EMAIL_VERIFICATION_TOKEN_PREFIX: &str = "email_verification_token:";
+const EMAIL_VERIFICATION_EMAIL_PREFIX: &str = "email_verification_email:";
+const EMAIL_VERIFICATION_RESEND_PREFIX: &str = "email_verification_resend:";
+
+fn get_token_key(token: &EmailVerificationToken) -> String {
+    format!("{}{}", EMAIL_VERIFICATION_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_email_key(email: &Email) -> String {
+    format!("{}{}", EMAIL_VERIFICATION_EMAIL_PREFIX, email.as_ref().expose_secret())
+}
+
+fn get_resend_key(email: &Email) -> String {
+    format!("{}{}", EMAIL_VERIFICATION_RESEND_PREFIX, email.as_ref().expose_secret())
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::data_stores::{
+        FederatedLogin, FederatedLoginState, FederatedLoginStore, FederatedLoginStoreError,
+    },
+    utils::constants::FEDERATED_LOGIN_TTL_SECONDS,
+};
+
+pub struct RedisFederatedLoginStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisFederatedLoginStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl FederatedLoginStore for RedisFederatedLoginStore {
+    #[tracing::instrument(name = "Add Federated Login", skip_all)]
+    async fn add_login(
+        &mut self,
+        state: FederatedLoginState,
+        login: FederatedLogin,
+    ) -> Result<(), FederatedLoginStoreError> {
+        let login = serde_json::to_string(&StoredLogin::from(login))
+            .map_err(|e| FederatedLoginStoreError::UnexpectedError(e.into()))?;
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(&state), login, FEDERATED_LOGIN_TTL_SECONDS)
+            .map_err(|e| FederatedLoginStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Federated Login", skip_all)]
+    async fn get_login(
+        &self,
+        state: &FederatedLoginState,
+    ) -> Result<FederatedLogin, FederatedLoginStoreError> {
+        let login: String = self
+            .conn
+            .write()
+            .await
+            .get(get_key(state))
+            .map_err(|_| FederatedLoginStoreError::LoginNotFound)?;
+        let login: StoredLogin = serde_json::from_str(&login)
+            .map_err(|e| FederatedLoginStoreError::UnexpectedError(e.into()))?;
+        Ok(login.into())
+    }
+
+    #[tracing::instrument(name = "Remove Federated Login", skip_all)]
+    async fn remove_login(&mut self, state: &FederatedLoginState) -> Result<(), FederatedLoginStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(get_key(state))
+            .map_err(|e| FederatedLoginStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredLogin {
+    provider: String,
+    nonce: String,
+    code_verifier: String,
+}
+
+impl From<FederatedLogin> for StoredLogin {
+    fn from(login: FederatedLogin) -> Self {
+        Self {
+            provider: login.provider,
+            nonce: login.nonce,
+            code_verifier: login.code_verifier.expose_secret().to_owned(),
+        }
+    }
+}
+
+impl From<StoredLogin> for FederatedLogin {
+    fn from(login: StoredLogin) -> Self {
+        Self {
+            provider: login.provider,
+            nonce: login.nonce,
+            code_verifier: Secret::new(login.code_verifier),
+        }
+    }
+}
+
+const FEDERATED_LOGIN_PREFIX: &str = "federated_login:";
+
+fn get_key(state: &FederatedLoginState) -> String {
+    format!("{}{}", FEDERATED_LOGIN_PREFIX, state.as_ref().expose_secret())
+}
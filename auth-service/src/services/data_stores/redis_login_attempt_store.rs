@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{login_lockout_seconds, LoginAttemptStore, LoginAttemptStoreError},
+        UserId,
+    },
+    utils::constants::LOGIN_FAILURE_WINDOW_SECONDS,
+};
+
+pub struct RedisLoginAttemptStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisLoginAttemptStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for RedisLoginAttemptStore {
+    #[tracing::instrument(name = "Check Login Lockout", skip_all)]
+    async fn check_lockout(&self, user_id: &UserId) -> Result<(), LoginAttemptStoreError> {
+        // TTL is negative when the key does not exist
+        let ttl: i64 = self
+            .conn
+            .write()
+            .await
+            .ttl(get_lockout_key(user_id))
+            .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        match ttl {
+            ttl if ttl > 0 => Err(LoginAttemptStoreError::LockedOut {
+                retry_after_seconds: ttl as u64,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Record Failed Login", skip_all)]
+    async fn record_failure(&mut self, user_id: &UserId) -> Result<(), LoginAttemptStoreError> {
+        let failures_key = get_failures_key(user_id);
+        let mut conn = self.conn.write().await;
+
+        let count: u64 = conn
+            .incr(&failures_key, 1)
+            .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+        // Every failure pushes the expiry back, so the count only resets after a quiet window
+        let _: () = conn
+            .expire(&failures_key, LOGIN_FAILURE_WINDOW_SECONDS as i64)
+            .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+
+        if let Some(seconds) = login_lockout_seconds(count) {
+            let _: () = conn
+                .set_ex(get_lockout_key(user_id), true, seconds)
+                .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Reset Failed Logins", skip_all)]
+    async fn reset(&mut self, user_id: &UserId) -> Result<(), LoginAttemptStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(&[get_failures_key(user_id), get_lockout_key(user_id)])
+            .map_err(|e| LoginAttemptStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+const LOGIN_FAILURES_PREFIX: &str = "login_failures:";
+const LOGIN_LOCKOUT_PREFIX: &str = "login_lockout:";
+
+fn get_failures_key(user_id: &UserId) -> String {
+    format!("{}{}", LOGIN_FAILURES_PREFIX, user_id.as_ref())
+}
+
+fn get_lockout_key(user_id: &UserId) -> String {
+    format!("{}{}", LOGIN_LOCKOUT_PREFIX, user_id.as_ref())
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{MagicLinkToken, MagicLinkTokenStore, MagicLinkTokenStoreError},
+    Email,
+};
+
+pub struct RedisMagicLinkTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisMagicLinkTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkTokenStore for RedisMagicLinkTokenStore {
+    #[tracing::instrument(name = "Add Magic Link Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: MagicLinkToken,
+    ) -> Result<(), MagicLinkTokenStoreError> {
+        let email_key = get_email_key(&email);
+        let mut conn = self.conn.write().await;
+
+        // Only the most recently sent link stays valid
+        let previous_token: Option<String> = conn
+            .get(&email_key)
+            .map_err(|e| MagicLinkTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(previous_token) = previous_token {
+            let _: () = conn
+                .del(format!("{}{}", MAGIC_LINK_TOKEN_PREFIX, previous_token))
+                .map_err(|e| MagicLinkTokenStoreError::UnexpectedError(e.into()))?;
+        }
+
+        let _: () = conn
+            .set_ex(
+                get_token_key(&token),
+                email.as_ref().expose_secret(),
+                FIFTEEN_MINUTES_IN_SECONDS,
+            )
+            .map_err(|e| MagicLinkTokenStoreError::UnexpectedError(e.into()))?;
+        let _: () = conn
+            .set_ex(
+                email_key,
+                token.as_ref().expose_secret(),
+                FIFTEEN_MINUTES_IN_SECONDS,
+            )
+            .map_err(|e| MagicLinkTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Magic Link Token", skip_all)]
+    async fn get_email(
+        &self,
+        token: &MagicLinkToken,
+    ) -> Result<Email, MagicLinkTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let email: String = conn
+            .get(get_token_key(token))
+            .map_err(|_| MagicLinkTokenStoreError::TokenNotFound)?;
+        Email::parse(Secret::new(email)).map_err(MagicLinkTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Remove Magic Link Token", skip_all)]
+    async fn remove_token(
+        &mut self,
+        token: &MagicLinkToken,
+    ) -> Result<(), MagicLinkTokenStoreError> {
+        let key = get_token_key(token);
+        let mut conn = self.conn.write().await;
+        let email: Option<String> = conn
+            .get(&key)
+            .map_err(|e| MagicLinkTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(email) = email {
+            let _: () = conn
+                .del(format!("{}{}", MAGIC_LINK_EMAIL_PREFIX, email))
+                .map_err(|e| MagicLinkTokenStoreError::UnexpectedError(e.into()))?;
+        }
+        let _: () = conn
+            .del(key)
+            .map_err(|e| MagicLinkTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+const FIFTEEN_MINUTES_IN_SECONDS: u64 = 900;
+const MAGIC_LINK_TOKEN_PREFIX: &str = "magic_link_token:";
+const MAGIC_LINK_EMAIL_PREFIX: &str = "magic_link_email:";
+
+fn get_token_key(token: &MagicLinkToken) -> String {
+    format!("{}{}", MAGIC_LINK_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_email_key(email: &Email) -> String {
+    format!("{}{}", MAGIC_LINK_EMAIL_PREFIX, email.as_ref().expose_secret())
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{PasskeyChallengeStore, PasskeyChallengeStoreError},
+        Email, PasskeyCeremony, PasskeyChallenge,
+    },
+    utils::constants::PASSKEY_CHALLENGE_TTL_SECONDS,
+};
+
+pub struct RedisPasskeyChallengeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasskeyChallengeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyChallengeStore for RedisPasskeyChallengeStore {
+    #[tracing::instrument(name = "Add Passkey Challenge", skip_all)]
+    async fn add_challenge(
+        &mut self,
+        challenge: PasskeyChallenge,
+        ceremony: PasskeyCeremony,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        let ceremony = serde_json::to_string(&StoredCeremony::from(ceremony))
+            .map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?;
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(&challenge), ceremony, PASSKEY_CHALLENGE_TTL_SECONDS)
+            .map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Passkey Ceremony", skip_all)]
+    async fn get_ceremony(
+        &self,
+        challenge: &PasskeyChallenge,
+    ) -> Result<PasskeyCeremony, PasskeyChallengeStoreError> {
+        let ceremony: String = self
+            .conn
+            .write()
+            .await
+            .get(get_key(challenge))
+            .map_err(|_| PasskeyChallengeStoreError::ChallengeNotFound)?;
+        let ceremony: StoredCeremony = serde_json::from_str(&ceremony)
+            .map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?;
+        ceremony.try_into()
+    }
+
+    #[tracing::instrument(name = "Remove Passkey Challenge", skip_all)]
+    async fn remove_challenge(
+        &mut self,
+        challenge: &PasskeyChallenge,
+    ) -> Result<(), PasskeyChallengeStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(get_key(challenge))
+            .map_err(|e| PasskeyChallengeStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "ceremony", rename_all = "camelCase")]
+enum StoredCeremony {
+    Registration { email: String },
+    Login,
+    SecondFactor { email: String },
+}
+
+impl From<PasskeyCeremony> for StoredCeremony {
+    fn from(ceremony: PasskeyCeremony) -> Self {
+        match ceremony {
+            PasskeyCeremony::Registration { email } => Self::Registration {
+                email: email.as_ref().expose_secret().to_owned(),
+            },
+            PasskeyCeremony::Login => Self::Login,
+            PasskeyCeremony::SecondFactor { email } => Self::SecondFactor {
+                email: email.as_ref().expose_secret().to_owned(),
+            },
+        }
+    }
+}
+
+impl TryFrom<StoredCeremony> for PasskeyCeremony {
+    type Error = PasskeyChallengeStoreError;
+
+    fn try_from(ceremony: StoredCeremony) -> Result<Self, Self::Error> {
+        let parse_email = |email: String| {
+            Email::parse(Secret::new(email)).map_err(PasskeyChallengeStoreError::UnexpectedError)
+        };
+        Ok(match ceremony {
+            StoredCeremony::Registration { email } => Self::Registration {
+                email: parse_email(email)?,
+            },
+            StoredCeremony::Login => Self::Login,
+            StoredCeremony::SecondFactor { email } => Self::SecondFactor {
+                email: parse_email(email)?,
+            },
+        })
+    }
+}
+
+const PASSKEY_CHALLENGE_PREFIX: &str = "passkey_challenge:";
+
+fn get_key(challenge: &PasskeyChallenge) -> String {
+    format!("{}{}", PASSKEY_CHALLENGE_PREFIX, challenge.as_ref())
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError},
+    Email,
+};
+
+pub struct RedisPasswordResetTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    #[tracing::instrument(name = "Add Password Reset Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let email_key = get_email_key(&email);
+        let mut conn = self.conn.write().await;
+
+        // Only the most recently requested token stays valid
+        let previous_token: Option<String> = conn
+            .get(&email_key)
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(previous_token) = previous_token {
+            let _: () = conn
+                .del(format!("{}{}", PASSWORD_RESET_TOKEN_PREFIX, previous_token))
+                .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        }
+
+        let _: () = conn
+            .set_ex(
+                get_token_key(&token),
+                email.as_ref().expose_secret(),
+                FIFTEEN_MINUTES_IN_SECONDS,
+            )
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        let _: () = conn
+            .set_ex(
+                email_key,
+                token.as_ref().expose_secret(),
+                FIFTEEN_MINUTES_IN_SECONDS,
+            )
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Password Reset Token", skip_all)]
+    async fn get_email(
+        &self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let email: String = conn
+            .get(get_token_key(token))
+            .map_err(|_| PasswordResetTokenStoreError::TokenNotFound)?;
+        Email::parse(Secret::new(email)).map_err(PasswordResetTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Remove Password Reset Token", skip_all)]
+    async fn remove_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let key = get_token_key(token);
+        let mut conn = self.conn.write().await;
+        let email: Option<String> = conn
+            .get(&key)
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        if let Some(email) = email {
+            let _: () = conn
+                .del(format!("{}{}", PASSWORD_RESET_EMAIL_PREFIX, email))
+                .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        }
+        let _: () = conn
+            .del(key)
+            .map_err(|e| PasswordResetTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+const FIFTEEN_MINUTES_IN_SECONDS: u64 = 900;
+const PASSWORD_RESET_TOKEN_PREFIX: &str = "password_reset_token:";
+const PASSWORD_RESET_EMAIL_PREFIX: &str = "password_reset_email:";
+
+fn get_token_key(token: &PasswordResetToken) -> String {
+    format!("{}{}", PASSWORD_RESET_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_email_key(email: &Email) -> String {
+    format!("{}{}", PASSWORD_RESET_EMAIL_PREFIX, email.as_ref().expose_secret())
+}
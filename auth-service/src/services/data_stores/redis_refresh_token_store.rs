@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError,
+            TokenFamilyId,
+        },
+        UserId,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Add Refresh Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let key = get_token_key(&token);
+        let user_key = get_user_families_key(&record.user_id);
+        let family_id = record.family_id.as_ref().to_owned();
+        let serialized = serde_json::to_string(&StoredRefreshToken::from(record))
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        let mut conn = self.conn.write().await;
+        let _: () = conn
+            .set_ex(key, serialized, REFRESH_TOKEN_TTL_SECONDS as u64)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        // Track the families of each user so that they can all be revoked at once
+        let _: () = conn
+            .sadd(&user_key, family_id)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        let _: () = conn
+            .expire(&user_key, REFRESH_TOKEN_TTL_SECONDS)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Refresh Token", skip_all)]
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let key = get_token_key(token);
+        let mut conn = self.conn.write().await;
+        let value: String = conn
+            .get(&key)
+            .map_err(|_| RefreshTokenStoreError::RefreshTokenNotFound)?;
+        let stored: StoredRefreshToken = serde_json::from_str(&value)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        stored.try_into()
+    }
+
+    #[tracing::instrument(name = "Mark Refresh Token Used", skip_all)]
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        let mut record = self.get_token(token).await?;
+        record.used = true;
+        // Used tokens are kept until they expire so that a replay can still be detected
+        self.add_token(token.clone(), record).await
+    }
+
+    #[tracing::instrument(name = "Revoke Refresh Token Family", skip_all)]
+    async fn revoke_family(&mut self, family_id: &TokenFamilyId) -> Result<(), RefreshTokenStoreError> {
+        let key = get_family_key(family_id);
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(key, true, REFRESH_TOKEN_TTL_SECONDS as u64)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Check Revoked Refresh Token Family", skip_all)]
+    async fn is_family_revoked(&self, family_id: &TokenFamilyId) -> Result<bool, RefreshTokenStoreError> {
+        let key = get_family_key(family_id);
+        let mut conn = self.conn.write().await;
+        let exists: bool = conn
+            .exists(key)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(exists)
+    }
+
+    #[tracing::instrument(name = "Revoke User Refresh Token Families", skip_all)]
+    async fn revoke_user_families(&mut self, user_id: &UserId) -> Result<(), RefreshTokenStoreError> {
+        let user_key = get_user_families_key(user_id);
+        let families: Vec<String> = self
+            .conn
+            .write()
+            .await
+            .smembers(&user_key)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        for family_id in families {
+            let family_id = TokenFamilyId::parse(family_id)
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+            self.revoke_family(&family_id).await?;
+        }
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(&user_key)
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredRefreshToken {
+    user_id: String,
+    family_id: String,
+    used: bool,
+}
+
+impl From<RefreshTokenRecord> for StoredRefreshToken {
+    fn from(record: RefreshTokenRecord) -> Self {
+        Self {
+            user_id: record.user_id.as_ref().to_owned(),
+            family_id: record.family_id.as_ref().to_owned(),
+            used: record.used,
+        }
+    }
+}
+
+impl TryFrom<StoredRefreshToken> for RefreshTokenRecord {
+    type Error = RefreshTokenStoreError;
+
+    fn try_from(stored: StoredRefreshToken) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user_id: UserId::parse(stored.user_id)
+                .map_err(RefreshTokenStoreError::UnexpectedError)?,
+            family_id: TokenFamilyId::parse(stored.family_id)
+                .map_err(RefreshTokenStoreError::UnexpectedError)?,
+            used: stored.used,
+        })
+    }
+}
+
+const REFRESH_TOKEN_PREFIX: &str = "refresh_token:";
+const REVOKED_FAMILY_PREFIX: &str = "refresh_token_family_revoked:";
+const USER_FAMILIES_PREFIX: &str = "refresh_token_user_families:";
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_family_key(family_id: &TokenFamilyId) -> String {
+    format!("{}{}", REVOKED_FAMILY_PREFIX, family_id.as_ref())
+}
+
+fn get_user_families_key(user_id: &UserId) -> String {
+    format!("{}{}", USER_FAMILIES_PREFIX, user_id.as_ref())
+}
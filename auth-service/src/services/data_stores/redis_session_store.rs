@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{Session, SessionStore, SessionStoreError, TokenFamilyId},
+        AuthenticationMethod, Scope, UserId,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Add Session", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let user_key = get_user_sessions_key(&session.user_id);
+        let mut conn = self.conn.write().await;
+        save_session(&mut conn, &session)?;
+
+        // Track the sessions of each user so that they can be listed
+        let _: () = conn
+            .sadd(&user_key, session.id.as_ref())
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+        let _: () = conn
+            .expire(&user_key, REFRESH_TOKEN_TTL_SECONDS)
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Get Session", skip_all)]
+    async fn get_session(&self, session_id: &TokenFamilyId) -> Result<Session, SessionStoreError> {
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(get_session_key(session_id))
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+        let value = value.ok_or(SessionStoreError::SessionNotFound)?;
+        let stored: StoredSession = serde_json::from_str(&value)
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+        stored.try_into()
+    }
+
+    #[tracing::instrument(name = "Get User Sessions", skip_all)]
+    async fn get_user_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        let user_key = get_user_sessions_key(user_id);
+        let session_ids: Vec<String> = self
+            .conn
+            .write()
+            .await
+            .smembers(&user_key)
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        let mut sessions = Vec::with_capacity(session_ids.len());
+        for session_id in session_ids {
+            let parsed_id = TokenFamilyId::parse(session_id.clone())
+                .map_err(SessionStoreError::UnexpectedError)?;
+            match self.get_session(&parsed_id).await {
+                Ok(session) => sessions.push(session),
+                // Expired or revoked, forget about it
+                Err(SessionStoreError::SessionNotFound) => {
+                    let _: () = self
+                        .conn
+                        .write()
+                        .await
+                        .srem(&user_key, session_id)
+                        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "Touch Session", skip_all)]
+    async fn touch_session(&mut self, session_id: &TokenFamilyId) -> Result<(), SessionStoreError> {
+        let mut session = self.get_session(session_id).await?;
+        session.last_seen = chrono::Utc::now().timestamp();
+        let mut conn = self.conn.write().await;
+        save_session(&mut conn, &session)?;
+        let _: () = conn
+            .expire(get_user_sessions_key(&session.user_id), REFRESH_TOKEN_TTL_SECONDS)
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Remove Session", skip_all)]
+    async fn remove_session(&mut self, session_id: &TokenFamilyId) -> Result<(), SessionStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(get_session_key(session_id))
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    id: String,
+    user_id: String,
+    created_at: i64,
+    last_seen: i64,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+    client_id: Option<String>,
+    scope: Option<String>,
+    // Missing from sessions stored before it was recorded
+    #[serde(default)]
+    methods: Vec<AuthenticationMethod>,
+}
+
+impl From<&Session> for StoredSession {
+    fn from(session: &Session) -> Self {
+        Self {
+            id: session.id.as_ref().to_owned(),
+            user_id: session.user_id.as_ref().to_owned(),
+            created_at: session.created_at,
+            last_seen: session.last_seen,
+            user_agent: session.user_agent.clone(),
+            ip_address: session.ip_address.clone(),
+            client_id: session.client_id.clone(),
+            scope: session.scope.as_ref().map(|scope| scope.as_ref().to_owned()),
+            methods: session.methods.clone(),
+        }
+    }
+}
+
+impl TryFrom<StoredSession> for Session {
+    type Error = SessionStoreError;
+
+    fn try_from(stored: StoredSession) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: TokenFamilyId::parse(stored.id).map_err(SessionStoreError::UnexpectedError)?,
+            user_id: UserId::parse(stored.user_id).map_err(SessionStoreError::UnexpectedError)?,
+            created_at: stored.created_at,
+            last_seen: stored.last_seen,
+            user_agent: stored.user_agent,
+            ip_address: stored.ip_address,
+            client_id: stored.client_id,
+            scope: stored
+                .scope
+                .map(|scope| Scope::parse(&scope))
+                .transpose()
+                .map_err(SessionStoreError::UnexpectedError)?,
+            methods: stored.methods,
+        })
+    }
+}
+
+// Sessions live as long as the refresh tokens that extend them
+fn save_session(conn: &mut Connection, session: &Session) -> Result<(), SessionStoreError> {
+    let serialized = serde_json::to_string(&StoredSession::from(session))
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+    let _: () = conn
+        .set_ex(get_session_key(&session.id), serialized, REFRESH_TOKEN_TTL_SECONDS as u64)
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+    Ok(())
+}
+
+const SESSION_PREFIX: &str = "session:";
+const USER_SESSIONS_PREFIX: &str = "user_sessions:";
+
+fn get_session_key(session_id: &TokenFamilyId) -> String {
+    format!("{}{}", SESSION_PREFIX, session_id.as_ref())
+}
+
+fn get_user_sessions_key(user_id: &UserId) -> String {
+    format!("{}{}", USER_SESSIONS_PREFIX, user_id.as_ref())
+}
@@ -6,18 +6,27 @@ use tokio::sync::RwLock;
 use color_eyre::eyre::Context;
 use secrecy::{ExposeSecret, Secret};
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        Email,
+    },
+    utils::constants::{TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS, TWO_FA_MAX_FAILED_ATTEMPTS},
 };
 
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    namespace: &'static str,
 }
 
 impl RedisTwoFACodeStore {
     pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+        Self { conn, namespace: "" }
+    }
+
+    // Codes that confirm sensitive actions, kept apart from the codes of login attempts
+    pub fn for_reauthentication(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn, namespace: REAUTHENTICATION_NAMESPACE }
     }
 }
 
@@ -40,19 +49,21 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
         // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
 
-        let key = get_key(&email);
+        let key = get_key(self.namespace, &email);
         let two_fa_tuple = TwoFATuple(login_attempt_id.as_ref().to_string(), code.as_ref().to_string());
         let serialized = serde_json::to_string(&two_fa_tuple).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
-        let _: () = self.conn.write().await.set_ex(key, serialized, TEN_MINUTES_IN_SECONDS).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let mut conn = self.conn.write().await;
+        // Failed attempts are kept, starting a new login must not buy more guesses
+        let _: () = conn.set_ex(key, serialized, TEN_MINUTES_IN_SECONDS).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
         Ok(())
     }
 
     #[tracing::instrument(name = "Remove Code", skip_all)]
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
 
-        let key = get_key(&email);
-        let _: () = self.conn.write().await.del(key).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let keys = [get_key(self.namespace, email), get_attempts_key(self.namespace, email)];
+        let _: () = self.conn.write().await.del(&keys).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
         Ok(())
     }
 
@@ -62,7 +73,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
 
-        let key = get_key(&email);
+        let key = get_key(self.namespace, &email);
         let mut conn = self.conn.write().await;
         let value: String = conn.get(&key).map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
         let two_fa_tuple: TwoFATuple = serde_json::from_str(&value).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
@@ -70,6 +81,28 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         let two_fa_code = TwoFACode::parse(two_fa_tuple.1).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
         Ok((login_attempt_id, two_fa_code))
     }
+
+    #[tracing::instrument(name = "Record Failed 2FA Attempt", skip_all)]
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let attempts_key = get_attempts_key(self.namespace, email);
+        let mut conn = self.conn.write().await;
+
+        let exists: bool = conn.exists(get_key(self.namespace, email)).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        if !exists {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        // INCR keeps concurrent guesses from sharing a count
+        let failed_attempts: u64 = conn.incr(&attempts_key, 1).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let _: () = conn.expire(&attempts_key, TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS as i64).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        // The count stays until a second factor succeeds
+        if failed_attempts >= TWO_FA_MAX_FAILED_ATTEMPTS {
+            let _: () = conn.del(get_key(self.namespace, email)).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -77,7 +110,13 @@ struct TwoFATuple(pub String, pub String);
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const REAUTHENTICATION_NAMESPACE: &str = "reauthentication:";
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
-}   
\ No newline at end of file
+fn get_key(namespace: &str, email: &Email) -> String {
+    format!("{}{}{}", namespace, TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+}
+
+fn get_attempts_key(namespace: &str, email: &Email) -> String {
+    format!("{}{}{}", namespace, TWO_FA_ATTEMPTS_PREFIX, email.as_ref().expose_secret())
+}
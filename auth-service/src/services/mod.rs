@@ -1,3 +1,4 @@
 pub mod data_stores;
 pub mod mock_email_client;
+pub mod oidc_provider_client;
 pub mod postmark_email_client;
\ No newline at end of file
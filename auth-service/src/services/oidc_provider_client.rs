@@ -0,0 +1,90 @@
+use color_eyre::eyre::{eyre, Result};
+use jsonwebtoken::jwk::JwkSet;
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::domain::{IdentityProvider, IdentityProviderClient, ProviderMetadata};
+
+pub struct OidcProviderClient {
+    http_client: Client,
+}
+
+impl OidcProviderClient {
+    pub fn new(http_client: Client) -> Self {
+        Self { http_client }
+    }
+}
+
+#[async_trait::async_trait]
+impl IdentityProviderClient for OidcProviderClient {
+    // Provider metadata (OpenID Connect Discovery section 4), fetched for every login so
+    // endpoint and key changes at the provider are picked up
+    #[tracing::instrument(name = "Discovering identity provider", skip_all)]
+    async fn discover(&self, provider: &IdentityProvider) -> Result<ProviderMetadata> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self
+            .http_client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Metadata for another issuer could point the login anywhere (section 4.3)
+        if metadata.issuer != provider.issuer {
+            return Err(eyre!("provider metadata names another issuer"));
+        }
+        Ok(metadata)
+    }
+
+    #[tracing::instrument(name = "Exchanging code with identity provider", skip_all)]
+    async fn exchange_code(
+        &self,
+        provider: &IdentityProvider,
+        metadata: &ProviderMetadata,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &Secret<String>,
+    ) -> Result<String> {
+        let response: TokenResponse = self
+            .http_client
+            .post(&metadata.token_endpoint)
+            .basic_auth(&provider.client_id, Some(provider.client_secret.expose_secret()))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", code_verifier.expose_secret()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.id_token)
+    }
+
+    #[tracing::instrument(name = "Fetching identity provider keys", skip_all)]
+    async fn get_jwks(&self, metadata: &ProviderMetadata) -> Result<JwkSet> {
+        let jwks = self
+            .http_client
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(jwks)
+    }
+}
+
+// Only the ID token is used, the provider's access token is of no use to this service
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
@@ -1,18 +1,56 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum::http::{header, HeaderMap};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Validation};
 use serde::{Deserialize, Serialize};
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
+use crate::{
+    app_state::{
+        BannedTokenStoreType, ClientStoreType, RefreshTokenStoreType, RoleStoreType,
+        SessionStoreType, UserStoreType,
+    },
+    domain::{
+        email::Email, AuthAPIError, AuthenticationMethod, ClientKind, ClientStoreError, Permission, RefreshToken, RefreshTokenRecord,
+        Role, Scope, SessionStoreError, TokenFamilyId, User, UserId, UserStoreError,
+    },
+};
 use secrecy::{ExposeSecret, Secret};
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::constants::{
+    JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, JWT_KEYRING, JWT_LEEWAY_SECONDS,
+    REFRESH_TOKEN_COOKIE_NAME,
+};
 
+// The session id ties the token to the refresh token family issued alongside it,
+// the token version is the user's current one from the `UserStore` and the roles are
+// the ones the user holds in the `RoleStore`
 #[tracing::instrument(name = "Generate Auth Cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    user_id: &UserId,
+    session_id: &TokenFamilyId,
+    token_version: u64,
+    roles: &[Role],
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user_id, session_id, token_version, roles)?;
     Ok(create_auth_cookie(token))
 }
 
+// Looks up the current token version and roles of the user to issue the auth cookie for
+#[tracing::instrument(name = "Generate User Auth Cookie", skip_all)]
+pub async fn generate_user_auth_cookie(
+    user_id: &UserId,
+    session_id: &TokenFamilyId,
+    user_store: UserStoreType,
+    role_store: RoleStoreType,
+) -> Result<Cookie<'static>> {
+    let token_version = user_store.read().await.get_token_version(user_id).await?;
+    let roles = role_store.read().await.get_roles(user_id).await?;
+    generate_auth_cookie(user_id, session_id, token_version, &roles)
+}
+
 #[tracing::instrument(name = "Create Auth Cookie", skip_all)]
 fn create_auth_cookie(token: String) -> Cookie<'static> {
     let cookie = Cookie::build((JWT_COOKIE_NAME, token))
@@ -24,15 +62,163 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+// Issues a new refresh token in the given family and wraps it in a cookie.
+// Pass `TokenFamilyId::default()` to start a new family on login.
+#[tracing::instrument(name = "Generate Refresh Cookie", skip_all)]
+pub async fn generate_refresh_cookie(
+    user_id: &UserId,
+    family_id: TokenFamilyId,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>> {
+    let token = generate_refresh_token(user_id, family_id, refresh_token_store).await?;
+    Ok(create_refresh_cookie(token))
+}
+
+// Issues a new refresh token in the given family, OAuth clients get it in the token response
+#[tracing::instrument(name = "Generate Refresh Token", skip_all)]
+pub async fn generate_refresh_token(
+    user_id: &UserId,
+    family_id: TokenFamilyId,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<RefreshToken> {
+    let token = RefreshToken::default();
+    refresh_token_store
+        .write()
+        .await
+        .add_token(token.clone(), RefreshTokenRecord::new(user_id.clone(), family_id))
+        .await
+        .wrap_err("failed to store refresh token")?;
+    Ok(token)
+}
+
+#[tracing::instrument(name = "Create Refresh Cookie", skip_all)]
+fn create_refresh_cookie(token: RefreshToken) -> Cookie<'static> {
+    Cookie::build((REFRESH_TOKEN_COOKIE_NAME, token.as_ref().expose_secret().to_owned()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build()
+}
 
 pub const TOKEN_TTL_SECONDS: i64 = 600;
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 14;
 
 #[tracing::instrument(name = "Generate Auth Token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
+fn generate_auth_token(
+    user_id: &UserId,
+    session_id: &TokenFamilyId,
+    token_version: u64,
+    roles: &[Role],
+) -> Result<String> {
+    let claims = Claims {
+        roles: roles.iter().map(|role| role.as_ref().to_owned()).collect(),
+        ..new_claims(user_id, session_id, token_version)?
+    };
+    create_token(&claims)
+}
+
+// Access tokens of OAuth clients are auth tokens that also name the client and its scope
+#[tracing::instrument(name = "Generate Access Token", skip_all)]
+pub fn generate_access_token(
+    user_id: &UserId,
+    session_id: &TokenFamilyId,
+    token_version: u64,
+    client_id: &str,
+    scope: &Scope,
+) -> Result<String> {
+    let claims = Claims {
+        client_id: Some(client_id.to_owned()),
+        scope: Some(scope.as_ref().to_owned()),
+        ..new_claims(user_id, session_id, token_version)?
+    };
+    create_token(&claims)
+}
+
+// ID tokens tell an OAuth client who logged in and how (OpenID Connect Core section 2).
+// Their audience is the client, so they are never accepted where auth tokens are.
+#[tracing::instrument(name = "Generate ID Token", skip_all)]
+pub fn generate_id_token(
+    user: &User,
+    client_id: &str,
+    session_id: &TokenFamilyId,
+    scope: &Scope,
+    nonce: Option<String>,
+    auth_time: i64,
+    methods: &[AuthenticationMethod],
+) -> Result<String> {
+    let (iat, exp) = token_lifetime()?;
+    // Email claims are only released with the `email` scope
+    let email_scope = scope.includes("email");
+    let claims = IdTokenClaims {
+        iss: JWT_ISSUER.to_owned(),
+        sub: user.id.as_ref().to_owned(),
+        aud: client_id.to_owned(),
+        exp,
+        iat,
+        auth_time,
+        nonce,
+        amr: methods.iter().map(|method| method.as_str().to_owned()).collect(),
+        sid: session_id.as_ref().to_owned(),
+        email: email_scope.then(|| user.email.as_ref().expose_secret().to_owned()),
+        email_verified: email_scope.then_some(user.email_verified),
+    };
+    create_token(&claims)
+}
+
+fn new_claims(user_id: &UserId, session_id: &TokenFamilyId, token_version: u64) -> Result<Claims> {
+    let (iat, exp) = token_lifetime()?;
+    let sub = user_id.as_ref().to_owned();
+
+    let claims = Claims {
+        sub,
+        exp,
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
+        iat,
+        nbf: iat,
+        jti: uuid::Uuid::new_v4().to_string(),
+        sub_type: SubjectType::User,
+        sid: Some(session_id.as_ref().to_owned()),
+        ver: Some(token_version),
+        scope: None,
+        client_id: None,
+        roles: Vec::new(),
+    };
+
+    Ok(claims)
+}
+
+// Tokens backend services get for themselves with the client credentials grant. No user
+// is involved, so there is no session or token version to tie them to.
+#[tracing::instrument(name = "Generate Client Token", skip_all)]
+pub fn generate_client_token(client_id: &str, scope: &Scope) -> Result<String> {
+    let (iat, exp) = token_lifetime()?;
+    let claims = Claims {
+        sub: client_id.to_owned(),
+        exp,
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
+        iat,
+        nbf: iat,
+        jti: uuid::Uuid::new_v4().to_string(),
+        sub_type: SubjectType::Client,
+        sid: None,
+        ver: None,
+        scope: Some(scope.as_ref().to_owned()),
+        client_id: Some(client_id.to_owned()),
+        roles: Vec::new(),
+    };
+    create_token(&claims)
+}
+
+// Issue and expiry time of a token issued now
+fn token_lifetime() -> Result<(usize, usize)> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
-    let exp = Utc::now()
+    let now = Utc::now();
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(eyre!("failed to add 10 minutes to current time"))?
         .timestamp();
@@ -42,19 +228,140 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().to_owned();
+    let iat: usize = now.timestamp().try_into().wrap_err(format!(
+        "failed to cast iat time to usize. iat time: {}",
+        now.timestamp()
+    ))?;
 
-    let claims = Claims { sub, exp };
+    Ok((iat, exp))
+}
 
-    create_token(&claims)
+// Resolves the user behind the JWT cookie, for routes acting on the logged in account
+#[tracing::instrument(name = "Get Authenticated Email", skip_all)]
+pub async fn get_authenticated_email(
+    jar: &CookieJar,
+    banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
+) -> std::result::Result<Email, AuthAPIError> {
+    let claims =
+        get_authenticated_claims(jar, banned_token_store, session_store, user_store.clone())
+            .await?;
+    Ok(get_subject_user(&claims, user_store).await?.email)
+}
+
+// Looks up the user the token was issued to, along with their current email address
+pub async fn get_subject_user(
+    claims: &Claims,
+    user_store: UserStoreType,
+) -> std::result::Result<User, AuthAPIError> {
+    let user_id = UserId::parse(claims.sub.clone()).map_err(|_| AuthAPIError::InvalidToken)?;
+    match user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => Ok(user),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[tracing::instrument(name = "Get Authenticated Claims", skip_all)]
+pub async fn get_authenticated_claims(
+    jar: &CookieJar,
+    banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
+) -> std::result::Result<Claims, AuthAPIError> {
+    let token = match jar.get(JWT_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return Err(AuthAPIError::MissingToken),
+    };
+
+    let claims = validate_token(&token, banned_token_store, session_store, user_store)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Access tokens of OAuth clients are limited to their scope, not the whole account
+    if claims.client_id.is_some() {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    Ok(claims)
+}
+
+// Checks that one of the token's roles allows the permission. What each role allows is
+// looked up when checking, so changes to role permissions apply to tokens already issued.
+#[tracing::instrument(name = "Require Permission", skip_all)]
+pub async fn require_permission(
+    claims: &Claims,
+    permission: &Permission,
+    role_store: RoleStoreType,
+) -> std::result::Result<(), AuthAPIError> {
+    let roles: Vec<Role> = claims
+        .roles
+        .iter()
+        .filter_map(|role| Role::parse(role.clone()).ok())
+        .collect();
+    match role_store.read().await.has_permission(&roles, permission).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(AuthAPIError::MissingPermission),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Authenticates a client by the credentials it sends with HTTP Basic (RFC 6749 section
+// 2.3.1) and returns its client id
+#[tracing::instrument(name = "Authenticate Client", skip_all)]
+pub async fn authenticate_client(
+    headers: &HeaderMap,
+    client_store: ClientStoreType,
+) -> std::result::Result<String, AuthAPIError> {
+    let (client_id, secret) = basic_credentials(headers).ok_or(AuthAPIError::InvalidClient)?;
+
+    match client_store.read().await.validate_client(&client_id, &secret).await {
+        Ok(()) => Ok(client_id),
+        Err(ClientStoreError::InvalidCredentials) => Err(AuthAPIError::InvalidClient),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Like `authenticate_client`, but only lets service clients through. Applications have a
+// secret for the token endpoint and nothing else.
+#[tracing::instrument(name = "Authenticate Service", skip_all)]
+pub async fn authenticate_service(
+    headers: &HeaderMap,
+    client_store: ClientStoreType,
+) -> std::result::Result<String, AuthAPIError> {
+    let client_id = authenticate_client(headers, client_store.clone()).await?;
+
+    match client_store.read().await.get_client(&client_id).await {
+        Ok(client) if client.kind == ClientKind::Service => Ok(client_id),
+        Ok(_) | Err(ClientStoreError::ClientNotFound) => Err(AuthAPIError::InvalidClient),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Access token sent by an OAuth client as a bearer token (RFC 6750 section 2.1)
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    value.strip_prefix("Bearer ")
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, Secret<String>)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let (client_id, secret) = decoded.split_once(':')?;
+    Some((client_id.to_owned(), Secret::new(secret.to_owned())))
 }
 
 #[tracing::instrument(name = "Validate Token", skip_all)]
 pub async fn validate_token(
     token: &str,
     banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
 ) -> Result<Claims> {
-    match banned_token_store.read().await.check_banned_token(&Secret::new(token.to_string())).await {
+    let claims = decode_claims(token)?;
+
+    match banned_token_store.read().await.check_banned_token(&claims.jti).await {
         Ok(value) => {
             if value {
                 return Err(eyre!("token is banned"));
@@ -63,22 +370,75 @@ pub async fn validate_token(
         Err(e) => return Err(e.into()),
     }
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
-    )
+    // Client tokens are only ended by expiry or by banning them
+    if claims.sub_type == SubjectType::Client {
+        return Ok(claims);
+    }
+
+    // Revoking a session ends every token issued for it
+    let session_id = TokenFamilyId::parse(
+        claims.sid.clone().ok_or(eyre!("user token without a session"))?,
+    )?;
+    match session_store.read().await.get_session(&session_id).await {
+        Ok(_) => {}
+        Err(SessionStoreError::SessionNotFound) => return Err(eyre!("session was revoked")),
+        Err(e) => return Err(e.into()),
+    }
+
+    // Bumping the version, e.g. by logging out everywhere, ends all tokens of the user
+    let user_id = UserId::parse(claims.sub.clone())?;
+    let token_version = user_store
+        .read()
+        .await
+        .get_token_version(&user_id)
+        .await
+        .wrap_err("failed to get token version")?;
+    if claims.ver != Some(token_version) {
+        return Err(eyre!("token version is outdated"));
+    }
+
+    Ok(claims)
+}
+
+// Checks the signature and the standard claims, the keyring lock is released before
+// the banned token store is consulted
+fn decode_claims(token: &str) -> Result<Claims> {
+    let header = decode_header(token).wrap_err("failed to decode token header")?;
+    let keyring = JWT_KEYRING
+        .read()
+        .map_err(|_| eyre!("JWT keyring lock poisoned"))?;
+    let key = keyring
+        .verification_key(header.kid.as_deref())
+        .ok_or(eyre!("token was signed with an unknown key"))?;
+
+    // Only the algorithm of the matching key is accepted, whatever the header claims
+    let mut validation = Validation::new(key.algorithm());
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+    validation.leeway = JWT_LEEWAY_SECONDS;
+
+    decode::<Claims>(token, key.decoding_key(), &validation)
     .map(|data| data.claims)
     .wrap_err("failed to decode token")
 }
 
+// Only with a key file configured, see `Keyring::signs_id_tokens`
+pub fn id_tokens_supported() -> Result<bool> {
+    let keyring = JWT_KEYRING
+        .read()
+        .map_err(|_| eyre!("JWT keyring lock poisoned"))?;
+    Ok(keyring.signs_id_tokens())
+}
+
 #[tracing::instrument(name = "Create Token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
+fn create_token<T: Serialize>(claims: &T) -> Result<String> {
+    let keyring = JWT_KEYRING
+        .read()
+        .map_err(|_| eyre!("JWT keyring lock poisoned"))?;
+    let key = keyring.signing_key();
+    encode(&key.header(), &claims, key.encoding_key())
     .wrap_err("failed to create token")
 }
 
@@ -90,8 +450,66 @@ pub enum GenerateTokenError {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
+    // Id of the user, which unlike their email address never changes
     pub sub: String,
     pub exp: usize,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub nbf: usize,
+    // Unique per token, logging out bans this id rather than the token itself
+    pub jti: String,
+    // Whether `sub` is a user or a client acting on its own behalf
+    #[serde(default)]
+    pub sub_type: SubjectType,
+    // Login session the token belongs to, the id of its refresh token family. Client
+    // tokens have no session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    // Token version of the user when the token was issued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ver: Option<u64>,
+    // Space separated scopes granted to an OAuth client, tokens for this service carry none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // OAuth client the token was issued to (RFC 9068)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // Roles the user held when the token was issued. Only auth tokens for this service
+    // carry them, OAuth clients are limited to their scope.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubjectType {
+    #[default]
+    User,
+    Client,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    // The OAuth client the token was issued to
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    // When the user logged in, which can be well before the token was issued
+    pub auth_time: i64,
+    // Echoed from the authorization request so the client can detect replays
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    // How the user logged in (RFC 8176), `mfa` when two factors were used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    pub sid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
 }
 
 #[cfg(test)]
@@ -100,14 +518,21 @@ mod tests {
 
     use tokio::sync::RwLock;
 
-    use crate::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
+    use crate::domain::{
+        BannedTokenStore, Password, RefreshTokenStore, Session, SessionStore, TwoFAMethod, User,
+        UserStore, MANAGE_ROLES_PERMISSION,
+    };
+    use crate::services::data_stores::{
+        hashmap_refresh_token_store::HashmapRefreshTokenStore, hashmap_role_store::HashmapRoleStore,
+        hashmap_session_store::HashmapSessionStore, hashmap_user_store::HashmapUserStore,
+        hashset_banned_token_store::HashsetBannedTokenStore,
+    };
 
     use super::*;
 
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&test_user_id(), &TokenFamilyId::default(), 0, &[]).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -126,20 +551,100 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let family_id = TokenFamilyId::default();
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let cookie = generate_refresh_cookie(&test_user_id(), family_id.clone(), refresh_token_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+
+        let token = RefreshToken::parse(cookie.value().to_owned()).unwrap();
+        let record = refresh_token_store.read().await.get_token(&token).await.unwrap();
+        assert_eq!(record.user_id, test_user_id());
+        assert_eq!(record.family_id, family_id);
+        assert!(!record.used);
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&test_user_id(), &TokenFamilyId::default(), 0, &[]).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
+    #[test]
+    fn test_id_token_releases_email_only_with_email_scope() {
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let user = User::new(email, password, TwoFAMethod::None);
+        let methods = [AuthenticationMethod::OneTimeCode, AuthenticationMethod::MultiFactor];
+
+        let id_token_claims = |scope: &str| {
+            let token = generate_id_token(
+                &user,
+                "client",
+                &TokenFamilyId::default(),
+                &Scope::parse(scope).unwrap(),
+                Some("n-0S6_WzA2Mj".to_owned()),
+                1_700_000_000,
+                &methods,
+            )
+            .unwrap();
+            let payload = token.split('.').nth(1).unwrap();
+            let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(payload).unwrap();
+            serde_json::from_slice::<IdTokenClaims>(&payload).unwrap()
+        };
+
+        let claims = id_token_claims("openid email");
+        assert_eq!(claims.aud, "client");
+        assert_eq!(claims.sub, user.id.as_ref());
+        assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+        assert_eq!(claims.auth_time, 1_700_000_000);
+        assert_eq!(claims.amr, vec!["otp", "mfa"]);
+        assert_eq!(claims.email.as_deref(), Some("test@example.com"));
+        assert_eq!(claims.email_verified, Some(false));
+
+        let claims = id_token_claims("openid");
+        assert!(claims.email.is_none());
+        assert!(claims.email_verified.is_none());
+    }
+
+    // Session store holding a single active session, along with the id of that session
+    async fn session_store() -> (TokenFamilyId, Arc<RwLock<HashmapSessionStore>>) {
+        let session = Session::new(test_user_id(), None, None);
+        let session_id = session.id.clone();
+        let mut session_store = HashmapSessionStore::default();
+        session_store.add_session(session).await.unwrap();
+        (session_id, Arc::new(RwLock::new(session_store)))
+    }
+
+    fn test_user_id() -> UserId {
+        UserId::parse("8f14e45f-ceea-467f-a8f2-3f3c5bd3a7a1".to_owned()).unwrap()
+    }
+
+    // User store holding the test user, whose token version is still 0
+    async fn user_store() -> UserStoreType {
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("Password123!".to_string())).unwrap();
+        let user = User {
+            id: test_user_id(),
+            ..User::new(email, password, TwoFAMethod::None)
+        };
+        let mut user_store = HashmapUserStore::default();
+        user_store.add_user(user).await.unwrap();
+        Arc::new(RwLock::new(user_store))
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let (session_id, session_store) = session_store().await;
+        let token = generate_auth_token(&test_user_id(), &session_id, 0, &[]).unwrap();
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await.unwrap();
-        assert_eq!(result.sub, "test@example.com");
+        let result = validate_token(&token, banned_token_store, session_store, user_store().await).await.unwrap();
+        assert_eq!(result.sub, test_user_id().as_ref());
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -154,7 +659,231 @@ mod tests {
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let (_, session_store) = session_store().await;
+        let result = validate_token(&token, banned_token_store, session_store, user_store().await).await;
         assert!(result.is_err());
     }
+
+    fn basic_auth_headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_basic_credentials() {
+        let encoded = STANDARD.encode("app-service:s3cret:with:colons");
+        let (client_id, secret) =
+            basic_credentials(&basic_auth_headers(&format!("Basic {}", encoded))).unwrap();
+        assert_eq!(client_id, "app-service");
+        assert_eq!(secret.expose_secret(), "s3cret:with:colons");
+
+        assert!(basic_credentials(&HeaderMap::new()).is_none());
+        assert!(basic_credentials(&basic_auth_headers("Bearer token")).is_none());
+        assert!(basic_credentials(&basic_auth_headers("Basic not-base64!")).is_none());
+        let no_separator = STANDARD.encode("app-service");
+        assert!(basic_credentials(&basic_auth_headers(&format!("Basic {}", no_separator))).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generated_token_carries_standard_claims() {
+        let (session_id, session_store) = session_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let first = validate_token(
+            &generate_auth_token(&test_user_id(), &session_id, 0, &[]).unwrap(),
+            banned_token_store.clone(),
+            session_store.clone(),
+            user_store().await,
+        )
+        .await
+        .unwrap();
+        let second = validate_token(
+            &generate_auth_token(&test_user_id(), &session_id, 0, &[]).unwrap(),
+            banned_token_store,
+            session_store,
+            user_store().await,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(first.iss, *JWT_ISSUER);
+        assert_eq!(first.aud, *JWT_AUDIENCE);
+        assert_eq!(first.nbf, first.iat);
+        assert_eq!(first.exp, first.iat + TOKEN_TTL_SECONDS as usize);
+        assert_eq!(first.sub_type, SubjectType::User);
+        assert_eq!(first.sid.as_deref(), Some(session_id.as_ref()));
+        assert_ne!(first.jti, second.jti);
+        assert!(first.roles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_require_permission_of_token_roles() {
+        let (session_id, session_store) = session_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let role_store: RoleStoreType = Arc::new(RwLock::new(HashmapRoleStore::default()));
+        let manage_roles = Permission::parse(MANAGE_ROLES_PERMISSION.to_owned()).unwrap();
+
+        let admin = [Role::parse("admin".to_owned()).unwrap()];
+        let claims = validate_token(
+            &generate_auth_token(&test_user_id(), &session_id, 0, &admin).unwrap(),
+            banned_token_store,
+            session_store,
+            user_store().await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.roles, vec!["admin"]);
+        assert!(require_permission(&claims, &manage_roles, role_store.clone()).await.is_ok());
+
+        let other = Permission::parse("reports:read".to_owned()).unwrap();
+        assert!(matches!(
+            require_permission(&claims, &other, role_store.clone()).await,
+            Err(AuthAPIError::MissingPermission)
+        ));
+        let no_roles = Claims { roles: Vec::new(), ..claims };
+        assert!(matches!(
+            require_permission(&no_roles, &manage_roles, role_store).await,
+            Err(AuthAPIError::MissingPermission)
+        ));
+    }
+
+    fn claims(session_id: &TokenFamilyId) -> Claims {
+        let now = Utc::now().timestamp() as usize;
+        Claims {
+            sub: test_user_id().as_ref().to_owned(),
+            exp: now + TOKEN_TTL_SECONDS as usize,
+            iss: JWT_ISSUER.to_owned(),
+            aud: JWT_AUDIENCE.to_owned(),
+            iat: now,
+            nbf: now,
+            jti: uuid::Uuid::new_v4().to_string(),
+            sub_type: SubjectType::User,
+            sid: Some(session_id.as_ref().to_owned()),
+            ver: Some(0),
+            scope: None,
+            client_id: None,
+            roles: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_wrong_issuer() {
+        let (session_id, session_store) = session_store().await;
+        let token = create_token(&Claims {
+            iss: "https://other.example.com".to_owned(),
+            ..claims(&session_id)
+        })
+        .unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_wrong_audience() {
+        let (session_id, session_store) = session_store().await;
+        let token = create_token(&Claims {
+            aud: "other-service".to_owned(),
+            ..claims(&session_id)
+        })
+        .unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_token_not_yet_valid() {
+        let (session_id, session_store) = session_store().await;
+        let claims = claims(&session_id);
+        let token = create_token(&Claims {
+            nbf: claims.iat + 2 * JWT_LEEWAY_SECONDS as usize,
+            ..claims
+        })
+        .unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_tolerates_clock_skew() {
+        let (session_id, session_store) = session_store().await;
+        let claims = claims(&session_id);
+        let token = create_token(&Claims {
+            nbf: claims.iat + JWT_LEEWAY_SECONDS as usize / 2,
+            ..claims
+        })
+        .unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_banned_jti() {
+        let (session_id, session_store) = session_store().await;
+        let claims = claims(&session_id);
+        let jti = claims.jti.clone();
+        let token = create_token(&claims).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        banned_token_store.write().await.store_token(jti).await.unwrap();
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_revoked_session() {
+        let (session_id, session_store) = session_store().await;
+        let token = create_token(&claims(&session_id)).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        session_store.write().await.remove_session(&session_id).await.unwrap();
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_outdated_token_version() {
+        let (session_id, session_store) = session_store().await;
+        let token = create_token(&claims(&session_id)).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_store = user_store().await;
+        user_store.write().await.bump_token_version(&test_user_id()).await.unwrap();
+        assert!(validate_token(&token, banned_token_store, session_store, user_store).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_client_token() {
+        let (_, session_store) = session_store().await;
+        let scope = Scope::parse("users:read").unwrap();
+        let token = generate_client_token("app-service", &scope).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let claims = validate_token(&token, banned_token_store, session_store, user_store().await)
+            .await
+            .unwrap();
+
+        assert_eq!(claims.sub, "app-service");
+        assert_eq!(claims.sub_type, SubjectType::Client);
+        assert_eq!(claims.client_id.as_deref(), Some("app-service"));
+        assert_eq!(claims.scope.as_deref(), Some("users:read"));
+        assert_eq!(claims.sid, None);
+        assert_eq!(claims.ver, None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_banned_client_token() {
+        let (_, session_store) = session_store().await;
+        let token = generate_client_token("app-service", &Scope::parse("").unwrap()).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let jti = decode_claims(&token).unwrap().jti;
+        banned_token_store.write().await.store_token(jti).await.unwrap();
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_user_token_without_session() {
+        let (session_id, session_store) = session_store().await;
+        let token = create_token(&Claims {
+            sid: None,
+            ..claims(&session_id)
+        })
+        .unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        assert!(validate_token(&token, banned_token_store, session_store, user_store().await).await.is_err());
+    }
 }
@@ -2,13 +2,26 @@ use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use std::env as std_env;
 use secrecy::Secret;
+use std::sync::RwLock;
+
+use super::keyring::Keyring;
 
 
 lazy_static! {
+    // Read once at startup. Reloading the keyring keeps this secret, rotating keys
+    // on SIGHUP needs key files configured with JWT_SIGNING_KEY_PATH.
     pub static ref JWT_SECRET: Secret<String> = set_token();
+    pub static ref JWT_KEYRING: RwLock<Keyring> = set_keyring();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
     pub static ref DATABASE_URL: Secret<String> = set_db_url();
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref JWT_ISSUER: String = set_jwt_issuer();
+    pub static ref JWT_AUDIENCE: String = set_jwt_audience();
+    pub static ref TOTP_ENCRYPTION_KEY: Secret<String> = set_totp_encryption_key();
+    pub static ref TOTP_SKEW_STEPS: u8 = set_totp_skew_steps();
+    pub static ref WEBAUTHN_RP_ID: String = set_webauthn_rp_id();
+    pub static ref WEBAUTHN_ORIGIN: String = set_webauthn_origin();
 }
 
 fn set_token() -> Secret<String> {
@@ -20,6 +33,13 @@ fn set_token() -> Secret<String> {
     Secret::new(secret)
 }
 
+fn set_keyring() -> RwLock<Keyring> {
+    match Keyring::from_env() {
+        Ok(keyring) => RwLock::new(keyring),
+        Err(e) => panic!("Failed to load JWT keyring: {:?}", e),
+    }
+}
+
 fn set_db_url() -> Secret<String> {
     dotenv().ok();
     let db_url = std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set.");
@@ -41,17 +61,107 @@ fn set_postmark_auth_token() -> Secret<String> {
     )
 }
 
+fn set_totp_encryption_key() -> Secret<String> {
+    dotenv().ok();
+    let key = std_env::var(env::TOTP_ENCRYPTION_KEY_ENV_VAR).expect("TOTP_ENCRYPTION_KEY must be set.");
+    if key.is_empty() {
+        panic!("TOTP_ENCRYPTION_KEY must not be empty.");
+    }
+    Secret::new(key)
+}
+
+fn set_totp_skew_steps() -> u8 {
+    dotenv().ok();
+    match std_env::var(env::TOTP_SKEW_STEPS_ENV_VAR) {
+        Ok(steps) => steps.parse().expect("TOTP_SKEW_STEPS must be a number between 0 and 255."),
+        Err(_) => DEFAULT_TOTP_SKEW_STEPS,
+    }
+}
+
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+}
+
+fn set_jwt_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_ISSUER_ENV_VAR).unwrap_or(AUTH_SERVICE_URL.to_owned())
+}
+
+// Passkeys are bound to a domain, by default the one the service is reached at
+fn set_webauthn_rp_id() -> String {
+    dotenv().ok();
+    std_env::var(env::WEBAUTHN_RP_ID_ENV_VAR).unwrap_or_else(|_| {
+        let host = AUTH_SERVICE_URL
+            .split_once("://")
+            .map_or(AUTH_SERVICE_URL.as_str(), |(_, rest)| rest);
+        host.split([':', '/']).next().unwrap_or(host).to_owned()
+    })
+}
+
+fn set_webauthn_origin() -> String {
+    dotenv().ok();
+    std_env::var(env::WEBAUTHN_ORIGIN_ENV_VAR).unwrap_or(AUTH_SERVICE_URL.to_owned())
+}
+
+fn set_jwt_audience() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_AUDIENCE_ENV_VAR).unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned())
+}
+
 
 
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_SIGNING_KEY_PATH_ENV_VAR: &str = "JWT_SIGNING_KEY_PATH";
+    pub const JWT_VERIFICATION_KEY_PATHS_ENV_VAR: &str = "JWT_VERIFICATION_KEY_PATHS";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const TOTP_SKEW_STEPS_ENV_VAR: &str = "TOTP_SKEW_STEPS";
+    pub const AUTH_CLIENTS_ENV_VAR: &str = "AUTH_CLIENTS";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const OIDC_PROVIDERS_ENV_VAR: &str = "OIDC_PROVIDERS";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+// Holds the state of a login with an external identity provider while the user is there
+pub const FEDERATED_LOGIN_COOKIE_NAME: &str = "federated_login";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1"; 
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+// Clock skew tolerated when checking exp and nbf
+pub const JWT_LEEWAY_SECONDS: u64 = 30;
+// Minimum time between two verification emails sent to the same address
+pub const EMAIL_VERIFICATION_RESEND_INTERVAL_SECONDS: u64 = 60;
+// Size of the recovery code set handed out when 2FA is enrolled
+pub const RECOVERY_CODE_COUNT: usize = 10;
+// Consecutive failed logins allowed for one address before it gets locked
+pub const LOGIN_LOCKOUT_THRESHOLD: u64 = 5;
+// First lockout, doubled for every further failure up to the maximum
+pub const LOGIN_LOCKOUT_BASE_SECONDS: u64 = 30;
+pub const LOGIN_LOCKOUT_MAX_SECONDS: u64 = 60 * 15;
+// Failed logins are forgotten once none happened for this long
+pub const LOGIN_FAILURE_WINDOW_SECONDS: u64 = 60 * 60;
+// Wrong second factors accepted for a user, across login attempts, before each further
+// wrong one discards its login attempt. Reset once a second factor succeeds.
+pub const TWO_FA_MAX_FAILED_ATTEMPTS: u64 = 5;
+// Wrong second factors are forgotten once none happened for this long
+pub const TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS: u64 = 60 * 10;
+// Number of 30 second steps a TOTP code may be early or late by
+pub const DEFAULT_TOTP_SKEW_STEPS: u8 = 1;
+// Time a user has to complete a passkey ceremony once its challenge was issued
+pub const PASSKEY_CHALLENGE_TTL_SECONDS: u64 = 60 * 5;
+// Time an OAuth client has to exchange an authorization code for tokens
+pub const AUTHORIZATION_CODE_TTL_SECONDS: u64 = 60;
+// Time a user has to log in at an external identity provider and come back
+pub const FEDERATED_LOGIN_TTL_SECONDS: u64 = 60 * 10;
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
@@ -63,6 +173,11 @@ pub mod prod {
         pub const SENDER: &str = "bogdan@codeiron.io";
         pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
     }
+    pub mod identity_provider_client {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
 }
 
 pub mod test {
@@ -73,4 +188,9 @@ pub mod test {
         pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
+    pub mod identity_provider_client {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(2);
+    }
 }
\ No newline at end of file
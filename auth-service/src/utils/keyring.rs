@@ -0,0 +1,258 @@
+use std::env as std_env;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use dotenvy::dotenv;
+use jsonwebtoken::jwk::JwkSet;
+use secrecy::ExposeSecret;
+
+use super::{
+    auth::TOKEN_TTL_SECONDS,
+    constants::{env, JWT_KEYRING, JWT_SECRET},
+    signing_key::SigningKey,
+};
+
+// All keys auth tokens are verified with. The active key signs new tokens, the others
+// are only accepted so tokens signed before a rotation stay valid until they expire.
+#[derive(Clone)]
+pub struct Keyring {
+    active: SigningKey,
+    retired: Vec<RetiredKey>,
+}
+
+#[derive(Clone)]
+struct RetiredKey {
+    key: SigningKey,
+    // None for keys listed in the configuration, which are kept until removed from it
+    expires_at: Option<Instant>,
+}
+
+impl RetiredKey {
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+impl Keyring {
+    pub fn new(active: SigningKey, verification_keys: Vec<SigningKey>) -> Self {
+        let retired = verification_keys
+            .into_iter()
+            .map(|key| RetiredKey { key, expires_at: None })
+            .collect();
+        Self { active, retired }
+    }
+
+    // Signs with the key file at JWT_SIGNING_KEY_PATH, or HS256 with JWT_SECRET when it
+    // is not set, and also accepts the keys in the comma separated JWT_VERIFICATION_KEY_PATHS
+    pub fn from_env() -> Result<Self> {
+        dotenv().ok();
+        let active = match std_env::var(env::JWT_SIGNING_KEY_PATH_ENV_VAR) {
+            Ok(path) if !path.is_empty() => load_key(&path)?,
+            _ => SigningKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        };
+
+        let verification_keys = std_env::var(env::JWT_VERIFICATION_KEY_PATHS_ENV_VAR)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(load_key)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new(active, verification_keys))
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.active
+    }
+
+    // Finds the key a token was signed with by the `kid` in its header
+    pub fn verification_key(&self, kid: Option<&str>) -> Option<&SigningKey> {
+        if self.active.kid() == kid {
+            return Some(&self.active);
+        }
+        let now = Instant::now();
+        self.retired
+            .iter()
+            .find(|retired| retired.key.kid() == kid && retired.is_live(now))
+            .map(|retired| &retired.key)
+    }
+
+    // Clients verify ID tokens with the published keys. Shared secrets are never
+    // published, so a keyring signing with HS256 can not issue them.
+    pub fn signs_id_tokens(&self) -> bool {
+        self.active.jwk().is_some()
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        let now = Instant::now();
+        let retired = self.retired.iter().filter(|retired| retired.is_live(now));
+        JwkSet {
+            keys: std::iter::once(&self.active)
+                .chain(retired.map(|retired| &retired.key))
+                .filter_map(|key| key.jwk().cloned())
+                .collect(),
+        }
+    }
+
+    // Switches to the next keyring. Keys it no longer lists, including the previous
+    // active key, keep verifying tokens for the grace period before they age out.
+    pub fn rotate(&mut self, next: Keyring, grace: Duration) {
+        let now = Instant::now();
+        let previous = std::mem::replace(self, next);
+        let outgoing = std::iter::once(RetiredKey {
+            key: previous.active,
+            expires_at: None,
+        })
+        .chain(previous.retired);
+
+        for mut retired in outgoing {
+            if self.verification_key(retired.key.kid()).is_some() {
+                continue;
+            }
+            retired.expires_at = Some(retired.expires_at.unwrap_or(now + grace));
+            if retired.is_live(now) {
+                self.retired.push(retired);
+            }
+        }
+    }
+}
+
+// Rereads the key files, keeping keys that were dropped valid until tokens signed
+// with them have expired. JWT_SECRET is not reread, see its definition.
+pub fn reload_keyring() -> Result<()> {
+    let next = Keyring::from_env()?;
+    JWT_KEYRING
+        .write()
+        .map_err(|_| eyre!("JWT keyring lock poisoned"))?
+        .rotate(next, Duration::from_secs(TOKEN_TTL_SECONDS as u64));
+    Ok(())
+}
+
+// Reloads the keyring whenever the process receives SIGHUP, so keys can be rotated
+// by replacing the key files without restarting the server. Only applies when
+// signing with JWT_SIGNING_KEY_PATH.
+#[cfg(unix)]
+pub async fn reload_keyring_on_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+    while hangups.recv().await.is_some() {
+        if std_env::var(env::JWT_SIGNING_KEY_PATH_ENV_VAR).unwrap_or_default().is_empty() {
+            tracing::warn!("JWT_SECRET is only read at startup, set JWT_SIGNING_KEY_PATH to rotate keys");
+        }
+        match reload_keyring() {
+            Ok(()) => tracing::info!("Reloaded JWT keyring"),
+            Err(e) => tracing::error!("Failed to reload JWT keyring, keeping current keys: {:?}", e),
+        }
+    }
+}
+
+fn load_key(path: &str) -> Result<SigningKey> {
+    let pem = std::fs::read(path).wrap_err(format!("failed to read JWT key {}", path))?;
+    SigningKey::from_pem(&pem).wrap_err(format!("invalid JWT key {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::{rand::SystemRandom, signature::Ed25519KeyPair};
+
+    fn ed25519_key() -> SigningKey {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+        SigningKey::from_pem(pem.as_bytes()).unwrap()
+    }
+
+    fn kids(keyring: &Keyring) -> Vec<String> {
+        keyring
+            .jwks()
+            .keys
+            .into_iter()
+            .filter_map(|jwk| jwk.common.key_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_keys_by_kid() {
+        let active = ed25519_key();
+        let verification = ed25519_key();
+        let keyring = Keyring::new(active.clone(), vec![verification.clone()]);
+
+        assert_eq!(keyring.signing_key().kid(), active.kid());
+        assert_eq!(keyring.verification_key(active.kid()).unwrap().kid(), active.kid());
+        assert_eq!(
+            keyring.verification_key(verification.kid()).unwrap().kid(),
+            verification.kid()
+        );
+        assert!(keyring.verification_key(Some("unknown")).is_none());
+        assert!(keyring.verification_key(None).is_none());
+        assert_eq!(kids(&keyring).len(), 2);
+    }
+
+    #[test]
+    fn test_only_published_keys_sign_id_tokens() {
+        assert!(Keyring::new(ed25519_key(), vec![]).signs_id_tokens());
+        assert!(!Keyring::new(SigningKey::from_secret(b"secret"), vec![]).signs_id_tokens());
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_key_during_grace_period() {
+        let old = ed25519_key();
+        let new = ed25519_key();
+        let mut keyring = Keyring::new(old.clone(), vec![]);
+
+        keyring.rotate(Keyring::new(new.clone(), vec![]), Duration::from_secs(600));
+        assert_eq!(keyring.signing_key().kid(), new.kid());
+        assert!(keyring.verification_key(old.kid()).is_some());
+        assert!(kids(&keyring).contains(&old.kid().unwrap().to_owned()));
+    }
+
+    #[test]
+    fn test_rotated_out_keys_age_out() {
+        let old = ed25519_key();
+        let new = ed25519_key();
+        let mut keyring = Keyring::new(old.clone(), vec![]);
+
+        keyring.rotate(Keyring::new(new.clone(), vec![]), Duration::ZERO);
+        assert!(keyring.verification_key(old.kid()).is_none());
+        assert_eq!(kids(&keyring), vec![new.kid().unwrap().to_owned()]);
+    }
+
+    #[test]
+    fn test_reloading_same_keys_changes_nothing() {
+        let active = ed25519_key();
+        let mut keyring = Keyring::new(active.clone(), vec![]);
+
+        keyring.rotate(Keyring::new(active.clone(), vec![]), Duration::from_secs(600));
+        assert_eq!(keyring.signing_key().kid(), active.kid());
+        assert_eq!(kids(&keyring).len(), 1);
+    }
+
+    #[test]
+    fn test_grace_period_is_not_extended_by_later_rotations() {
+        let first = ed25519_key();
+        let second = ed25519_key();
+        let third = ed25519_key();
+        let mut keyring = Keyring::new(first.clone(), vec![]);
+
+        keyring.rotate(Keyring::new(second.clone(), vec![]), Duration::from_millis(50));
+        keyring.rotate(Keyring::new(third.clone(), vec![]), Duration::from_secs(600));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(keyring.verification_key(first.kid()).is_none());
+        assert!(keyring.verification_key(second.kid()).is_some());
+    }
+
+    #[test]
+    fn test_switching_from_shared_secret_keeps_secret_tokens_valid() {
+        let key = ed25519_key();
+        let mut keyring = Keyring::new(SigningKey::from_secret(b"secret"), vec![]);
+
+        keyring.rotate(Keyring::new(key.clone(), vec![]), Duration::from_secs(600));
+        assert_eq!(keyring.signing_key().kid(), key.kid());
+        // Tokens signed with the secret carry no kid
+        assert!(keyring.verification_key(None).is_some());
+        // The secret itself is never published
+        assert_eq!(kids(&keyring), vec![key.kid().unwrap().to_owned()]);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod constants;
 pub mod auth;
+pub mod keyring;
+pub mod signing_key;
 pub mod tracing;
\ No newline at end of file
@@ -0,0 +1,230 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header,
+};
+use ring::{
+    rsa::PublicKeyComponents,
+    signature::{Ed25519KeyPair, KeyPair, RsaKeyPair},
+};
+use sha2::{Digest, Sha256};
+
+// Key auth tokens are signed and verified with. Asymmetric keys carry a public JWK
+// so other services can verify tokens offline instead of calling /verify-token.
+#[derive(Clone)]
+pub struct SigningKey {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl SigningKey {
+    // HS256 with a shared secret, used when no key file is configured
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self {
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    // Accepts an RSA private key in PKCS#1 or PKCS#8 form, signing with RS256,
+    // or an Ed25519 private key in PKCS#8 form, signing with EdDSA
+    pub fn from_pem(pem: &[u8]) -> Result<Self> {
+        let parsed = pem::parse(pem).wrap_err("failed to parse signing key PEM")?;
+        let der = parsed.contents();
+
+        let (algorithm, encoding_key, parameters) = match parsed.tag() {
+            "RSA PRIVATE KEY" => {
+                let key_pair = RsaKeyPair::from_der(der)
+                    .map_err(|e| eyre!("invalid RSA private key: {}", e))?;
+                (Algorithm::RS256, EncodingKey::from_rsa_pem(pem)?, rsa_parameters(&key_pair))
+            }
+            "PRIVATE KEY" => match RsaKeyPair::from_pkcs8(der) {
+                Ok(key_pair) => {
+                    (Algorithm::RS256, EncodingKey::from_rsa_pem(pem)?, rsa_parameters(&key_pair))
+                }
+                Err(_) => {
+                    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der)
+                        .map_err(|e| eyre!("PKCS#8 key is neither RSA nor Ed25519: {}", e))?;
+                    (Algorithm::EdDSA, EncodingKey::from_ed_pem(pem)?, ed25519_parameters(&key_pair))
+                }
+            },
+            tag => return Err(eyre!("unsupported signing key PEM block: {}", tag)),
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm(algorithm)),
+                key_id: Some(thumbprint(&parameters)),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+        let decoding_key = DecodingKey::from_jwk(&jwk).wrap_err("failed to build decoding key")?;
+
+        Ok(Self {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    // RFC 7638 thumbprint of the public key, absent for shared secrets
+    pub fn kid(&self) -> Option<&str> {
+        self.jwk.as_ref().and_then(|jwk| jwk.common.key_id.as_deref())
+    }
+
+    // Public half of the key, shared secrets are never published
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.kid().map(str::to_owned);
+        header
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+}
+
+fn rsa_parameters(key_pair: &RsaKeyPair) -> AlgorithmParameters {
+    let components = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+    AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(components.n),
+        e: URL_SAFE_NO_PAD.encode(components.e),
+    })
+}
+
+fn ed25519_parameters(key_pair: &Ed25519KeyPair) -> AlgorithmParameters {
+    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+        key_type: OctetKeyPairType::OctetKeyPair,
+        curve: EllipticCurve::Ed25519,
+        x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+    })
+}
+
+fn key_algorithm(algorithm: Algorithm) -> KeyAlgorithm {
+    match algorithm {
+        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+        _ => KeyAlgorithm::RS256,
+    }
+}
+
+// The required members in lexicographic order, hashed with SHA-256 (RFC 7638 section 3)
+fn thumbprint(parameters: &AlgorithmParameters) -> String {
+    let canonical = match parameters {
+        AlgorithmParameters::RSA(rsa) => {
+            format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, rsa.e, rsa.n)
+        }
+        AlgorithmParameters::OctetKeyPair(okp) => {
+            format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, okp.x)
+        }
+        _ => unreachable!("only RSA and Ed25519 signing keys are loaded"),
+    };
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, decode_header, encode, Validation};
+    use ring::rand::SystemRandom;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn ed25519_pem() -> String {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()))
+    }
+
+    // Signs with the key and verifies with only the published JWK, like another service would
+    fn assert_round_trip(key: &SigningKey) {
+        let claims = TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: 10_000_000_000,
+        };
+        let token = encode(&key.header(), &claims, key.encoding_key()).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, key.algorithm());
+        assert_eq!(header.kid.as_deref(), key.kid());
+
+        let jwk = key.jwk().unwrap();
+        let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+        let decoded = decode::<TestClaims>(&token, &decoding_key, &Validation::new(key.algorithm()));
+        assert_eq!(decoded.unwrap().claims, claims);
+    }
+
+    #[test]
+    fn test_ed25519_key_signs_verifiable_tokens() {
+        let key = SigningKey::from_pem(ed25519_pem().as_bytes()).unwrap();
+        assert_eq!(key.algorithm(), Algorithm::EdDSA);
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_rsa_pkcs1_key_signs_verifiable_tokens() {
+        let key = SigningKey::from_pem(include_bytes!("../../tests/keys/rsa_pkcs1.pem")).unwrap();
+        assert_eq!(key.algorithm(), Algorithm::RS256);
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_rsa_pkcs8_key_signs_verifiable_tokens() {
+        let key = SigningKey::from_pem(include_bytes!("../../tests/keys/rsa_pkcs8.pem")).unwrap();
+        assert_eq!(key.algorithm(), Algorithm::RS256);
+        assert_round_trip(&key);
+    }
+
+    #[test]
+    fn test_kid_is_stable_for_the_same_key() {
+        let pem = ed25519_pem();
+        let first = SigningKey::from_pem(pem.as_bytes()).unwrap();
+        let second = SigningKey::from_pem(pem.as_bytes()).unwrap();
+        let other = SigningKey::from_pem(ed25519_pem().as_bytes()).unwrap();
+        assert_eq!(first.kid(), second.kid());
+        assert_ne!(first.kid(), other.kid());
+    }
+
+    #[test]
+    fn test_secret_key_is_not_published() {
+        let key = SigningKey::from_secret(b"secret");
+        assert_eq!(key.algorithm(), Algorithm::HS256);
+        assert!(key.kid().is_none());
+        assert!(key.jwk().is_none());
+        assert!(key.header().kid.is_none());
+    }
+
+    #[test]
+    fn test_rejects_invalid_pem() {
+        assert!(SigningKey::from_pem(b"not a key").is_err());
+        let public_only = pem::encode(&pem::Pem::new("PUBLIC KEY", vec![0u8; 32]));
+        assert!(SigningKey::from_pem(public_only.as_bytes()).is_err());
+    }
+}
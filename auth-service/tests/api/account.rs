@@ -0,0 +1,118 @@
+use auth_service::{
+    domain::{Email, TwoFAMethod},
+    routes::SecondFactorRequiredResponse,
+    utils::constants::JWT_COOKIE_NAME,
+};
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.delete_account(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_incorrect() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+
+    let response = app.delete_account(&json!({ "password": "WrongPassword123!" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The account is left untouched
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_account_and_send_confirmation() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // Expect 2 requests: the verification email on signup and the confirmation
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(false).await;
+
+    // Log in once more to hold on to the auth token, which must stop working
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app.delete_account(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().expect("No email sent").body)
+        .expect("Failed to parse email request body");
+    assert_eq!(body["Subject"], "Your account was deleted");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_fresh_2fa_code_if_2fa_enabled() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(true).await;
+
+    // Without a code a new one is emailed and the account is kept
+    let response = app.delete_account(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(
+        response
+            .json::<SecondFactorRequiredResponse>()
+            .await
+            .expect("Could not deserialize response body to SecondFactorRequiredResponse")
+            .two_fa_method,
+        TwoFAMethod::Email
+    );
+    let code = app.get_reauthentication_code(&email).await;
+
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+    let response = app
+        .delete_account(&json!({ "password": "Password123!", "2FACode": wrong_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .delete_account(&json!({ "password": "Password123!", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Pending 2FA codes of the user are purged along with the account
+    let email = Email::parse(Secret::new(email)).unwrap();
+    assert!(app.two_fa_code_store.read().await.get_code(&email).await.is_err());
+    assert!(app.reauthentication_code_store.read().await.get_code(&email).await.is_err());
+    app.clean_up().await;
+}
@@ -0,0 +1,184 @@
+use auth_service::{
+    routes::{IntrospectionResponse, UserRolesResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp, TEST_CLIENT_ID, TEST_CLIENT_SECRET};
+
+// Signs up a verified user without logging in and returns their email and id
+async fn signup(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "Password123!",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&email).await;
+    let user_id = app.get_user_id(&email).await;
+    (email, user_id)
+}
+
+// Logs in a new user holding the given roles and returns their auth token
+async fn login_with_roles(app: &TestApp, roles: &[&str]) -> String {
+    let (email, _) = signup(app).await;
+    for role in roles {
+        app.grant_role(&email, role).await;
+    }
+    let response = app
+        .post_login(&json!({ "email": email, "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    auth_cookie.value().to_owned()
+}
+
+async fn roles(response: reqwest::Response) -> Vec<String> {
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<UserRolesResponse>()
+        .await
+        .expect("Could not deserialize response body to UserRolesResponse")
+        .roles
+}
+
+#[tokio::test]
+async fn should_grant_and_revoke_role() {
+    let mut app = TestApp::new().await;
+    let (_, user_id) = signup(&app).await;
+    login_with_roles(&app, &["admin"]).await;
+
+    assert!(roles(app.get_user_roles(&user_id).await).await.is_empty());
+
+    let response = app.post_user_role(&user_id, &json!({ "role": "admin" })).await;
+    assert_eq!(roles(response).await, vec!["admin"]);
+    assert_eq!(roles(app.get_user_roles(&user_id).await).await, vec!["admin"]);
+
+    let response = app.delete_user_role(&user_id, "admin").await;
+    assert!(roles(response).await.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_put_roles_in_auth_token() {
+    let mut app = TestApp::new().await;
+    let token = login_with_roles(&app, &["admin"]).await;
+
+    let response = app
+        .post_introspect(&json!({ "token": token }), Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let introspection = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(introspection.active);
+    assert_eq!(introspection.roles, vec!["admin"]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_without_permission() {
+    let mut app = TestApp::new().await;
+    let (_, user_id) = signup(&app).await;
+    login_with_roles(&app, &[]).await;
+
+    let response = app.get_user_roles(&user_id).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_user_role(&user_id, &json!({ "role": "admin" })).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.delete_user_role(&user_id, "admin").await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+    let (_, user_id) = signup(&app).await;
+
+    let response = app.post_user_role(&user_id, &json!({ "role": "admin" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_unknown_role() {
+    let mut app = TestApp::new().await;
+    let (_, user_id) = signup(&app).await;
+    login_with_roles(&app, &["admin"]).await;
+
+    for role in ["superuser", "Not A Role"] {
+        let response = app.post_user_role(&user_id, &json!({ "role": role })).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", role);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_user() {
+    let mut app = TestApp::new().await;
+    login_with_roles(&app, &["admin"]).await;
+
+    for user_id in [uuid::Uuid::new_v4().to_string(), "not-a-uuid".to_owned()] {
+        let response = app.post_user_role(&user_id, &json!({ "role": "admin" })).await;
+        assert_eq!(response.status().as_u16(), 404, "{}", user_id);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_end_tokens_carrying_revoked_role() {
+    let mut app = TestApp::new().await;
+    let token = login_with_roles(&app, &["admin"]).await;
+    let user_id = {
+        let response = app
+            .post_introspect(&json!({ "token": token }), Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+            .await;
+        response.json::<IntrospectionResponse>().await.unwrap().sub.unwrap()
+    };
+
+    // Admins can revoke their own role
+    let response = app.delete_user_role(&user_id, "admin").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The refreshed token no longer carries the role
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.get_user_roles(&user_id).await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_check_permission_at_verify_token() {
+    let mut app = TestApp::new().await;
+    let admin_token = login_with_roles(&app, &["admin"]).await;
+    let user_token = login_with_roles(&app, &[]).await;
+
+    let check = |token: String, permission: &str| {
+        json!({ "token": token, "permission": permission })
+    };
+    let response = app.post_verify_token(&check(admin_token.clone(), "roles:manage")).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_verify_token(&check(admin_token, "reports:read")).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_verify_token(&check(user_token.clone(), "roles:manage")).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_verify_token(&check(user_token.clone(), "Not a permission")).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Without a permission any valid token passes
+    let response = app.post_verify_token(&json!({ "token": user_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
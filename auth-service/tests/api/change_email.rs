@@ -0,0 +1,179 @@
+use auth_service::{
+    domain::EmailChangeToken, routes::ChangeEmailResponse, utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use serde_json::json;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Returns the body of every email received by the mock Postmark server
+async fn get_sent_emails(app: &TestApp) -> Vec<serde_json::Value> {
+    app.email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled")
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).expect("Failed to parse email request body"))
+        .collect()
+}
+
+// Pulls the confirmation token out of the link in the email sent to the new address
+async fn get_change_token_from_email(app: &TestApp, new_email: &str) -> String {
+    let emails = get_sent_emails(app).await;
+    let body = emails
+        .iter()
+        .rev()
+        .find(|body| body["To"] == new_email)
+        .expect("No email sent to the new address");
+    let text = body["TextBody"].as_str().expect("No TextBody in email");
+    text.split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in email")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let body = json!({ "newEmail": get_random_email(), "password": "Password123!" });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_new_email_taken() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let taken_email = get_random_email();
+    let signup_body = json!({
+        "email": taken_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.signup_and_login(false).await;
+
+    let body = json!({ "newEmail": taken_email, "password": "Password123!" });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 409);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let token = EmailChangeToken::default();
+    let response = app.get_change_email_confirm(token.as_ref().expose_secret()).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_email_both_addresses_on_request() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3) // Expect 3 requests: the verification email on signup, the link and the notice
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(false).await;
+    let new_email = get_random_email();
+
+    let body = json!({ "newEmail": new_email, "password": "Password123!" });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let emails = get_sent_emails(&app).await;
+    assert_eq!(emails[1]["To"], new_email);
+    assert_eq!(emails[1]["Subject"], "Confirm your new email address");
+    assert_eq!(emails[2]["To"], email);
+    assert_eq!(emails[2]["Subject"], "Your email address is being changed");
+
+    // Nothing changes until the link is opened
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_change_email_when_link_opened() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(false).await;
+    let user_id = app.get_user_id(&email).await;
+
+    // Log in once more to hold on to the auth token, which must stop working
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let new_email = get_random_email();
+    let body = json!({ "newEmail": new_email, "password": "Password123!" });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_change_token_from_email(&app, &new_email).await;
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<ChangeEmailResponse>()
+            .await
+            .expect("Could not deserialize response body to ChangeEmailResponse"),
+        ChangeEmailResponse {
+            message: "Email changed, please log in with the new email address".to_owned(),
+        }
+    );
+
+    let response = app.post_verify_token(&json!({ "token": auth_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_login(&json!({ "email": new_email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The user keeps their id
+    assert_eq!(app.get_user_id(&new_email).await, user_id);
+
+    // The link works only once
+    let response = app.get_change_email_confirm(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
@@ -0,0 +1,172 @@
+use auth_service::{
+    utils::constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_THRESHOLD},
+    ErrorResponse,
+};
+use serde_json::json;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::TestApp;
+
+fn auth_token(response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "Password123!",
+            "newPassword": "NewPassword123!"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_incorrect() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+
+    for current_password in ["WrongPassword123!", "short"] {
+        let response = app
+            .post_change_password(&json!({
+                "currentPassword": current_password,
+                "newPassword": "NewPassword123!"
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for {}", current_password);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_too_many_incorrect_current_passwords() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+
+    let body = json!({
+        "currentPassword": "WrongPassword123!",
+        "newPassword": "NewPassword123!"
+    });
+    for _ in 0..LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_change_password(&body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The right password is not even checked while locked, here or at /login
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "Password123!",
+            "newPassword": "NewPassword123!"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 429);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_invalid() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "Password123!",
+            "newPassword": "short"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_is_current_password() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "Password123!",
+            "newPassword": "Password123!"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "New password must differ from the current one".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_change_password_and_notify_user() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // Expect 2 requests: the verification email on signup and the notification
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(false).await;
+    let old_login_body = json!({ "email": email, "password": "Password123!" });
+
+    // A second session, e.g. on another device, whose token is kept around
+    let other_client = reqwest::Client::new();
+    let response = other_client
+        .post(format!("{}/login", &app.address))
+        .json(&old_login_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let other_token = auth_token(&response);
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "Password123!",
+            "newPassword": "NewPassword123!"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_token = auth_token(&response);
+
+    // The session that changed the password stays logged in, the other one is ended
+    let response = app.post_verify_token(&json!({ "token": new_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_verify_token(&json!({ "token": other_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Only the new password is accepted
+    let response = app.post_login(&old_login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": "NewPassword123!" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().expect("No email sent").body)
+        .expect("Failed to parse email request body");
+    assert_eq!(body["Subject"], "Your password was changed");
+    app.clean_up().await;
+}
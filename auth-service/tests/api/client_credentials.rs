@@ -0,0 +1,142 @@
+use auth_service::{
+    routes::{IntrospectionResponse, TokenResponse},
+    utils::auth::SubjectType,
+};
+use serde_json::json;
+
+use crate::{
+    helpers::{TestApp, TEST_CLIENT_ID, TEST_CLIENT_SCOPE, TEST_CLIENT_SECRET},
+    oauth::register_client,
+};
+
+async fn client_token(app: &TestApp, scope: Option<&str>) -> TokenResponse {
+    let mut body = json!({ "grant_type": "client_credentials" });
+    if let Some(scope) = scope {
+        body["scope"] = json!(scope);
+    }
+    let response = app
+        .post_token(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+}
+
+async fn introspect(app: &TestApp, token: &str) -> IntrospectionResponse {
+    let response = app
+        .post_introspect(&json!({ "token": token }), Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse")
+}
+
+#[tokio::test]
+async fn should_issue_client_token_with_registered_scope() {
+    let mut app = TestApp::new().await;
+
+    let tokens = client_token(&app, None).await;
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, TEST_CLIENT_SCOPE);
+    assert!(tokens.refresh_token.is_none());
+    assert!(tokens.id_token.is_none());
+
+    let introspection = introspect(&app, &tokens.access_token).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.sub.as_deref(), Some(TEST_CLIENT_ID));
+    assert_eq!(introspection.sub_type, Some(SubjectType::Client));
+    assert_eq!(introspection.client_id.as_deref(), Some(TEST_CLIENT_ID));
+    assert_eq!(introspection.sid, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_client_token_for_requested_scope() {
+    let mut app = TestApp::new().await;
+
+    let tokens = client_token(&app, Some("users:read")).await;
+    assert_eq!(tokens.scope, "users:read");
+
+    let introspection = introspect(&app, &tokens.access_token).await;
+    assert_eq!(introspection.scope.as_deref(), Some("users:read"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_scope_not_registered() {
+    let mut app = TestApp::new().await;
+
+    let body = json!({ "grant_type": "client_credentials", "scope": "users:read users:delete" });
+    let response = app
+        .post_token(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_valid_client_credentials() {
+    let mut app = TestApp::new().await;
+
+    let body = json!({ "grant_type": "client_credentials", "client_id": TEST_CLIENT_ID });
+    let response = app.post_token(&body, None).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let body = json!({ "grant_type": "client_credentials" });
+    let response = app.post_token(&body, Some((TEST_CLIENT_ID, "wrong-secret"))).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_for_public_client() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, false).await;
+
+    let body = json!({ "grant_type": "client_credentials", "client_id": client.client_id });
+    let response = app.post_token(&body, None).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_client_token_at_userinfo() {
+    let mut app = TestApp::new().await;
+
+    let tokens = client_token(&app, None).await;
+    let response = app.get_userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_client_token_at_verify_token() {
+    let mut app = TestApp::new().await;
+
+    let tokens = client_token(&app, None).await;
+    let response = app.post_verify_token(&json!({ "token": tokens.access_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_deactivate_revoked_client_token() {
+    let mut app = TestApp::new().await;
+
+    let tokens = client_token(&app, None).await;
+    let response = app
+        .post_revoke(
+            &json!({ "token": tokens.access_token }),
+            Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let introspection = introspect(&app, &tokens.access_token).await;
+    assert!(!introspection.active);
+    app.clean_up().await;
+}
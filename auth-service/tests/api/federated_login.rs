@@ -0,0 +1,267 @@
+use auth_service::{
+    routes::TwoFactorAuthResponse,
+    utils::{constants::JWT_COOKIE_NAME, signing_key::SigningKey},
+};
+use chrono::Utc;
+use jsonwebtoken::encode;
+use ring::{rand::SystemRandom, signature::Ed25519KeyPair};
+use serde_json::json;
+use url::Url;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, TEST_IDP_CLIENT_ID, TEST_IDP_NAME};
+
+const SUBJECT: &str = "248289761001";
+
+// Signs ID tokens the way the provider behind `identity_provider_server` would
+struct FakeProvider {
+    key: SigningKey,
+}
+
+impl FakeProvider {
+    async fn mount(app: &TestApp) -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()));
+        let key = SigningKey::from_pem(pem.as_bytes()).unwrap();
+
+        let issuer = app.identity_provider_server.uri();
+        Mock::given(path("/.well-known/openid-configuration"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{}/authorize", issuer),
+                "token_endpoint": format!("{}/token", issuer),
+                "jwks_uri": format!("{}/jwks", issuer),
+            })))
+            .mount(&app.identity_provider_server)
+            .await;
+        Mock::given(path("/jwks"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "keys": [key.jwk().unwrap()],
+            })))
+            .mount(&app.identity_provider_server)
+            .await;
+
+        Self { key }
+    }
+
+    fn id_token(&self, app: &TestApp, nonce: &str, email: &str, email_verified: bool) -> String {
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "iss": app.identity_provider_server.uri(),
+            "sub": SUBJECT,
+            "aud": TEST_IDP_CLIENT_ID,
+            "exp": now + 300,
+            "iat": now,
+            "nonce": nonce,
+            "email": email,
+            "email_verified": email_verified,
+        });
+        encode(&self.key.header(), &claims, self.key.encoding_key()).unwrap()
+    }
+
+    // Answers the next code exchange with the given ID token
+    async fn issue(&self, app: &TestApp, id_token: String) {
+        Mock::given(path("/token"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "provider-access-token",
+                "token_type": "Bearer",
+                "id_token": id_token,
+            })))
+            .up_to_n_times(1)
+            .mount(&app.identity_provider_server)
+            .await;
+    }
+}
+
+// Starts a login and returns the state and nonce sent to the provider
+async fn start_login(app: &TestApp) -> (String, String) {
+    let response = app.get_federated_login(TEST_IDP_NAME).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let location = response.headers().get("location").unwrap().to_str().unwrap();
+    let url = Url::parse(location).unwrap();
+    assert!(location.starts_with(&format!("{}/authorize", app.identity_provider_server.uri())));
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap_or_else(|| panic!("No {} in authorization URL", name))
+    };
+    assert_eq!(param("client_id"), TEST_IDP_CLIENT_ID);
+    assert_eq!(param("code_challenge_method"), "S256");
+    (param("state"), param("nonce"))
+}
+
+async fn log_in(app: &TestApp, provider: &FakeProvider, email: &str, email_verified: bool) -> reqwest::Response {
+    let (state, nonce) = start_login(app).await;
+    provider.issue(app, provider.id_token(app, &nonce, email, email_verified)).await;
+    app.get_federated_login_callback(TEST_IDP_NAME, &[("code", "provider-code"), ("state", state.as_str())])
+        .await
+}
+
+fn auth_cookie(response: &reqwest::Response) -> Option<String> {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned())
+}
+
+#[tokio::test]
+async fn should_provision_new_user() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+    let email = get_random_email();
+
+    let response = log_in(&app, &provider, &email, true).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(auth_cookie(&response).is_some());
+
+    // The provider account stays linked to the same user
+    let user_id = app.get_user_id(&email).await;
+    let response = log_in(&app, &provider, &email, true).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.get_user_id(&email).await, user_id);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_link_existing_verified_user() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({ "email": email, "password": "Password123!", "requires2FA": false }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&email).await;
+    let user_id = app.get_user_id(&email).await;
+
+    let response = log_in(&app, &provider, &email, true).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.get_user_id(&email).await, user_id);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_local_account_is_unverified() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({ "email": email, "password": "Password123!", "requires2FA": false }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = log_in(&app, &provider, &email, true).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(auth_cookie(&response).is_none());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_provider_did_not_verify_email() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+
+    let response = log_in(&app, &provider, &get_random_email(), false).await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_206_if_2fa_enabled() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({ "email": email, "password": "Password123!", "requires2FA": true }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&email).await;
+
+    let response = log_in(&app, &provider, &email, true).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(auth_cookie(&response).is_none());
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_state_does_not_match() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+
+    let (_, nonce) = start_login(&app).await;
+    provider.issue(&app, provider.id_token(&app, &nonce, &get_random_email(), true)).await;
+    let response = app
+        .get_federated_login_callback(TEST_IDP_NAME, &[("code", "provider-code"), ("state", "forged-state")])
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_nonce_does_not_match() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+
+    let (state, _) = start_login(&app).await;
+    provider.issue(&app, provider.id_token(&app, "other-nonce", &get_random_email(), true)).await;
+    let response = app
+        .get_federated_login_callback(TEST_IDP_NAME, &[("code", "provider-code"), ("state", state.as_str())])
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_is_replayed() {
+    let mut app = TestApp::new().await;
+    let provider = FakeProvider::mount(&app).await;
+
+    let (state, nonce) = start_login(&app).await;
+    provider.issue(&app, provider.id_token(&app, &nonce, &get_random_email(), true)).await;
+    let query = [("code", "provider-code"), ("state", state.as_str())];
+    let response = app.get_federated_login_callback(TEST_IDP_NAME, &query).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_federated_login_callback(TEST_IDP_NAME, &query).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_provider_returns_error() {
+    let mut app = TestApp::new().await;
+    FakeProvider::mount(&app).await;
+
+    let (state, _) = start_login(&app).await;
+    let response = app
+        .get_federated_login_callback(TEST_IDP_NAME, &[("error", "access_denied"), ("state", state.as_str())])
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_provider() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_federated_login("unknown-idp").await;
+    assert_eq!(response.status().as_u16(), 404);
+    app.clean_up().await;
+}
@@ -5,9 +5,11 @@ use std::{str::FromStr, sync::Arc};
 use tokio::sync::RwLock;
 
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType}, domain::Email, get_postgres_pool, get_redis_client, services::{data_stores::{
-        postgres_user_store::PostgresUserStore, redis_banned_token_store::RedisBannedTokenStore, redis_two_fa_code_store::RedisTwoFACodeStore,
-    }, mock_email_client::MockEmailClient, postmark_email_client::PostmarkEmailClient}, utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME}, Application
+    app_state::{AppState, BannedTokenStoreType, EmailVerificationTokenStoreType, LoginAttemptStoreType, PasswordResetTokenStoreType, RefreshTokenStoreType, RoleStoreType, SessionStoreType, TotpSecretStoreType, TwoFACodeStoreType, UserStoreType}, domain::{ClientStore, Email, IdentityProvider, Role, Scope, UserId}, get_postgres_pool, routes::TwoFactorAuthResponse, get_redis_client, services::{data_stores::{
+        postgres_user_store::PostgresUserStore, postgres_totp_secret_store::PostgresTotpSecretStore, postgres_recovery_code_store::PostgresRecoveryCodeStore, postgres_client_store::PostgresClientStore, postgres_passkey_store::PostgresPasskeyStore, postgres_consent_store::PostgresConsentStore, postgres_federated_identity_store::PostgresFederatedIdentityStore, postgres_role_store::PostgresRoleStore, redis_banned_token_store::RedisBannedTokenStore, redis_two_fa_code_store::RedisTwoFACodeStore,
+        redis_refresh_token_store::RedisRefreshTokenStore, redis_password_reset_token_store::RedisPasswordResetTokenStore,
+        redis_email_verification_token_store::RedisEmailVerificationTokenStore, redis_email_change_token_store::RedisEmailChangeTokenStore, redis_magic_link_token_store::RedisMagicLinkTokenStore, redis_login_attempt_store::RedisLoginAttemptStore, redis_session_store::RedisSessionStore, redis_passkey_challenge_store::RedisPasskeyChallengeStore, redis_authorization_code_store::RedisAuthorizationCodeStore, redis_federated_login_store::RedisFederatedLoginStore,
+    }, mock_email_client::MockEmailClient, oidc_provider_client::OidcProviderClient, postmark_email_client::PostmarkEmailClient}, utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME}, Application
 };
 
 use secrecy::{ExposeSecret, Secret};
@@ -18,10 +20,21 @@ use uuid::Uuid;
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
+    pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub http_client: reqwest::Client,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub reauthentication_code_store: TwoFACodeStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
+    pub session_store: SessionStoreType,
+    pub totp_secret_store: TotpSecretStoreType,
+    pub role_store: RoleStoreType,
     pub email_server: MockServer, // New!
+    // Stands in for an external OpenID Connect provider users can log in with
+    pub identity_provider_server: MockServer,
     pub db_name: String,
     pub clean_up_called: bool,
 }
@@ -31,7 +44,24 @@ impl TestApp {
         let db_name = Uuid::new_v4().to_string();
         // let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
         let pg_pool = configure_postgresql(&db_name).await;
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+        let totp_secret_store = Arc::new(RwLock::new(PostgresTotpSecretStore::new(pg_pool.clone())));
+        let recovery_code_store = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+        let passkey_store = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+        let consent_store = Arc::new(RwLock::new(PostgresConsentStore::new(pg_pool.clone())));
+        let federated_identity_store = Arc::new(RwLock::new(PostgresFederatedIdentityStore::new(pg_pool.clone())));
+        let role_store = Arc::new(RwLock::new(PostgresRoleStore::new(pg_pool.clone())));
+        let client_store = Arc::new(RwLock::new(PostgresClientStore::new(pg_pool)));
+        client_store
+            .write()
+            .await
+            .register_client(
+                TEST_CLIENT_ID,
+                Secret::new(TEST_CLIENT_SECRET.to_owned()),
+                Scope::parse(TEST_CLIENT_SCOPE).unwrap(),
+            )
+            .await
+            .expect("Failed to register test client");
         // let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
         let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
         // let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default())); // New!
@@ -41,14 +71,53 @@ impl TestApp {
         let email_server = MockServer::start().await; // New!
         let base_url = email_server.uri(); // New!
         let email_client = Arc::new(RwLock::new(configure_postmark_email_client(base_url)));
-
+        let reauthentication_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::for_reauthentication(Arc::new(RwLock::new(configure_redis())))));
+        let refresh_token_store = Arc::new(RwLock::new(RedisRefreshTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let email_verification_token_store = Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let email_change_token_store = Arc::new(RwLock::new(RedisEmailChangeTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let magic_link_token_store = Arc::new(RwLock::new(RedisMagicLinkTokenStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let login_attempt_store = Arc::new(RwLock::new(RedisLoginAttemptStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let session_store = Arc::new(RwLock::new(RedisSessionStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let passkey_challenge_store = Arc::new(RwLock::new(RedisPasskeyChallengeStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(Arc::new(RwLock::new(configure_redis())))));
+        let federated_login_store = Arc::new(RwLock::new(RedisFederatedLoginStore::new(Arc::new(RwLock::new(configure_redis())))));
+
+        let identity_provider_server = MockServer::start().await;
+        let identity_provider = IdentityProvider::new(
+            TEST_IDP_NAME.to_owned(),
+            identity_provider_server.uri(),
+            TEST_IDP_CLIENT_ID.to_owned(),
+            Secret::new(TEST_IDP_CLIENT_SECRET.to_owned()),
+        )
+        .expect("Failed to configure test identity provider");
+        let identity_provider_client = Arc::new(RwLock::new(configure_identity_provider_client()));
 
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(), 
             two_fa_code_store.clone(),
             email_client,
-        );
+        )
+        .with_reauthentication_code_store(reauthentication_code_store.clone())
+        .with_refresh_token_store(refresh_token_store.clone())
+        .with_password_reset_token_store(password_reset_token_store.clone())
+        .with_email_verification_token_store(email_verification_token_store.clone())
+        .with_email_change_token_store(email_change_token_store)
+        .with_magic_link_token_store(magic_link_token_store)
+        .with_totp_secret_store(totp_secret_store.clone())
+        .with_recovery_code_store(recovery_code_store)
+        .with_login_attempt_store(login_attempt_store.clone())
+        .with_client_store(client_store)
+        .with_session_store(session_store.clone())
+        .with_passkey_store(passkey_store)
+        .with_passkey_challenge_store(passkey_challenge_store)
+        .with_authorization_code_store(authorization_code_store)
+        .with_consent_store(consent_store)
+        .with_federated_login_store(federated_login_store)
+        .with_federated_identity_store(federated_identity_store)
+        .with_role_store(role_store.clone())
+        .with_identity_providers(vec![identity_provider], identity_provider_client);
 
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -68,12 +137,22 @@ impl TestApp {
         Self {
             address,
             cookie_jar,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            reauthentication_code_store,
+            refresh_token_store,
+            password_reset_token_store,
+            email_verification_token_store,
+            login_attempt_store,
+            session_store,
+            totp_secret_store,
+            role_store,
             http_client,
             db_name,
             clean_up_called: false,
             email_server,
+            identity_provider_server,
         }
     }
 
@@ -85,6 +164,14 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(&format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -109,6 +196,27 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_magic_link<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/magic-link", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_magic_link_consume(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/magic-link/consume", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
             .post(format!("{}/logout", &self.address))
@@ -117,6 +225,339 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_request<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/request", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_change_email_confirm(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/change-email/confirm", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .delete(format!("{}/account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password-reset/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_email_resend<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email/resend", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Marks the account as verified directly in the store, for tests that
+    // need a user able to log in but do not exercise the verification flow
+    pub async fn verify_user_email(&self, email: &str) {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        self.user_store
+            .write()
+            .await
+            .mark_email_verified(&email)
+            .await
+            .expect("Failed to mark email as verified");
+    }
+
+    // Creates a verified user, logs them in, completing emailed 2FA if needed,
+    // and returns their email
+    pub async fn signup_and_login(&self, requires_2fa: bool) -> String {
+        let email = get_random_email();
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": "Password123!",
+            "requires2FA": requires_2fa
+        });
+        let response = self.post_signup(&signup_body).await;
+        assert_eq!(response.status().as_u16(), 201);
+        self.verify_user_email(&email).await;
+
+        let login_body = serde_json::json!({ "email": email, "password": "Password123!" });
+        let response = self.post_login(&login_body).await;
+        if !requires_2fa {
+            assert_eq!(response.status().as_u16(), 200);
+            return email;
+        }
+
+        assert_eq!(response.status().as_u16(), 206);
+        let login_attempt_id = response
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFactorAuthResponse")
+            .login_attempt_id;
+        let code = self.get_two_fa_code(&email).await;
+        let response = self
+            .post_verify_2fa(&serde_json::json!({
+                "email": email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": code
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+        email
+    }
+
+    // The code of the user's current login attempt, as sent in the 2FA email
+    pub async fn get_two_fa_code(&self, email: &str) -> String {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        let (_, code) = self.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+        code.as_ref().to_owned()
+    }
+
+    // The code last emailed to confirm a sensitive action
+    pub async fn get_reauthentication_code(&self, email: &str) -> String {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        let (_, code) = self.reauthentication_code_store.read().await.get_code(&email).await.unwrap();
+        code.as_ref().to_owned()
+    }
+
+    // Grants the role directly in the store, like the first admin is granted in SQL
+    pub async fn grant_role(&self, email: &str, role: &str) {
+        let user_id = UserId::parse(self.get_user_id(email).await).unwrap();
+        self.role_store
+            .write()
+            .await
+            .grant_role(&user_id, &Role::parse(role.to_owned()).unwrap())
+            .await
+            .expect("Failed to grant role");
+    }
+
+    pub async fn get_user_id(&self, email: &str) -> String {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        let user = self
+            .user_store
+            .read()
+            .await
+            .get_user(&email)
+            .await
+            .expect("Failed to get user");
+        user.id.as_ref().to_owned()
+    }
+
+    pub async fn post_enable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/enable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_disable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/disable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/totp/enroll", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_recovery_code<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-2fa/recovery-code", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_register_start<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/register/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_register_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_login_start(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/passkeys/login/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_login_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/login/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_2fa_passkey_start<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-2fa/passkey/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_2fa_passkey<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-2fa/passkey", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_regenerate_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/recovery-codes/regenerate", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -141,6 +582,200 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_user_roles(&self, user_id: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}/roles", &self.address, user_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_user_role<Body>(&self, user_id: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/users/{}/roles", &self.address, user_id))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_user_role(&self, user_id: &str, role: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin/users/{}/roles/{}", &self.address, user_id, role))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Sends the token form as the given client, or unauthenticated without credentials
+    pub async fn post_introspect<Body>(
+        &self,
+        body: &Body,
+        credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/introspect", &self.address))
+            .form(body);
+        if let Some((client_id, secret)) = credentials {
+            request = request.basic_auth(client_id, Some(secret));
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_revoke<Body>(
+        &self,
+        body: &Body,
+        credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/revoke", &self.address))
+            .form(body);
+        if let Some((client_id, secret)) = credentials {
+            request = request.basic_auth(client_id, Some(secret));
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_client<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/oauth/clients", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // The authorization endpoint answers with redirects meant for the OAuth client,
+    // so they are returned instead of followed
+    pub async fn get_authorize<Query>(&self, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize,
+    {
+        self.no_redirect_client()
+            .get(format!("{}/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_authorize<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.no_redirect_client()
+            .post(format!("{}/authorize", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_token<Body>(
+        &self,
+        body: &Body,
+        credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/token", &self.address))
+            .form(body);
+        if let Some((client_id, secret)) = credentials {
+            request = request.basic_auth(client_id, Some(secret));
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_federated_login(&self, provider: &str) -> reqwest::Response {
+        self.no_redirect_client()
+            .get(format!("{}/login/oidc/{}", &self.address, provider))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_federated_login_callback<Query>(&self, provider: &str, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize + ?Sized,
+    {
+        self.http_client
+            .get(format!("{}/login/oidc/{}/callback", &self.address, provider))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/openid-configuration", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_userinfo(&self, access_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/userinfo", &self.address))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    fn no_redirect_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_revoke_session<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/sessions/revoke", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_revoke_other_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/sessions/revoke-others", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn clean_up(&mut self) {
         println!("Cleaning up database: {}", self.db_name);
         self.clean_up_called = true;
@@ -157,6 +792,13 @@ impl Drop for TestApp {
     }
 }
 
+pub const TEST_CLIENT_ID: &str = "test-client";
+pub const TEST_CLIENT_SECRET: &str = "test-client-secret";
+pub const TEST_CLIENT_SCOPE: &str = "users:read users:write";
+pub const TEST_IDP_NAME: &str = "test-idp";
+pub const TEST_IDP_CLIENT_ID: &str = "auth-service";
+pub const TEST_IDP_CLIENT_SECRET: &str = "test-idp-secret";
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
@@ -240,7 +882,7 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
+pub fn configure_redis() -> redis::Connection {
     get_redis_client(REDIS_HOST_NAME.to_owned())
         .expect("Failed to get Redis client")
         .get_connection()
@@ -258,4 +900,13 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
         .expect("Failed to build HTTP client");
 
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
+}
+
+fn configure_identity_provider_client() -> OidcProviderClient {
+    let http_client = Client::builder()
+        .timeout(test::identity_provider_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    OidcProviderClient::new(http_client)
 }
\ No newline at end of file
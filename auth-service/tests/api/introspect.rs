@@ -0,0 +1,112 @@
+use auth_service::{routes::IntrospectionResponse, utils::constants::JWT_COOKIE_NAME};
+
+use crate::helpers::{get_random_email, TestApp, TEST_CLIENT_ID, TEST_CLIENT_SECRET};
+
+async fn login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (random_email, token)
+}
+
+#[tokio::test]
+async fn should_return_claims_for_active_token() {
+    let mut app = TestApp::new().await;
+    let (random_email, token) = login(&app).await;
+
+    let body = serde_json::json!({ "token": token });
+    let response = app
+        .post_introspect(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let introspection = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(introspection.active);
+    assert_eq!(introspection.sub, Some(app.get_user_id(&random_email).await));
+    assert!(introspection.exp > introspection.iat);
+    assert!(introspection.sid.is_some());
+    assert!(introspection.jti.is_some());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_inactive_for_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({ "token": "invalid" });
+    let response = app
+        .post_introspect(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "active": false }));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_inactive_for_banned_token() {
+    let mut app = TestApp::new().await;
+    let (_, token) = login(&app).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = serde_json::json!({ "token": token });
+    let introspection = app
+        .post_introspect(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)))
+        .await
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(!introspection.active);
+    assert!(introspection.sub.is_none());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_valid_client_credentials() {
+    let mut app = TestApp::new().await;
+    let (_, token) = login(&app).await;
+    let body = serde_json::json!({ "token": token });
+
+    let credentials = [
+        None,
+        Some((TEST_CLIENT_ID, "wrong-secret")),
+        Some(("unknown-client", TEST_CLIENT_SECRET)),
+    ];
+    for credentials in credentials {
+        let response = app.post_introspect(&body, credentials).await;
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for credentials: {:?}",
+            credentials
+        );
+        assert_eq!(response.headers()["www-authenticate"], "Basic");
+    }
+    app.clean_up().await;
+}
@@ -0,0 +1,80 @@
+use auth_service::utils::{
+    auth::Claims,
+    constants::{JWT_AUDIENCE, JWT_COOKIE_NAME},
+};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_200_with_key_set() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let jwks = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    for jwk in jwks.keys.iter() {
+        assert!(jwk.common.key_id.is_some());
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_publish_key_that_verifies_issued_tokens() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let jwks = app
+        .get_jwks()
+        .await
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    let header = decode_header(&token).expect("Failed to decode token header");
+    match header.kid {
+        // Verify the way another service would, with nothing but the published key
+        Some(kid) => {
+            let jwk = jwks.find(&kid).expect("Signing key not published");
+            let decoding_key = DecodingKey::from_jwk(jwk).expect("Invalid published key");
+            let mut validation = Validation::new(header.alg);
+            validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+            let claims = decode::<Claims>(&token, &decoding_key, &validation)
+                .expect("Token does not verify against the published key")
+                .claims;
+            assert_eq!(claims.sub, app.get_user_id(&random_email).await);
+        }
+        // Without a key file tokens use the shared secret, which is never published
+        None => {
+            assert_eq!(header.alg, Algorithm::HS256);
+            assert!(jwks.keys.is_empty());
+        }
+    }
+    app.clean_up().await;
+}
@@ -2,7 +2,7 @@ use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
     domain::Email,
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_BASE_SECONDS, LOGIN_LOCKOUT_THRESHOLD},
     ErrorResponse,
 };
 use secrecy::Secret;
@@ -96,6 +96,39 @@ async fn should_return_401_if_incorrect_credentials() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_403_if_email_not_verified() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email not verified".to_owned()
+    );
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     let mut app = TestApp::new().await;
@@ -111,6 +144,7 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
@@ -144,11 +178,12 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
-        .expect(1)
+        .expect(2) // Expect 2 requests: the verification email on signup and the 2FA code on login
         .mount(&app.email_server)
         .await;
 
     let _ = app.post_signup(&body).await; // Will work
+    app.verify_user_email(&random_email).await;
     
     // Single login attempt
     let response = app.post_login(&body).await;
@@ -173,4 +208,90 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let stored_login_attempt_id = stored_code.0.as_ref().to_string();
     assert_eq!(stored_login_attempt_id, login_attempt_id);
     app.clean_up().await;
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn should_return_429_after_too_many_failed_attempts() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password456!",
+    });
+    for _ in 0..LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_login(&wrong_login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the correct password is refused while the account is locked
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: u64 = response
+        .headers()
+        .get("retry-after")
+        .expect("No Retry-After header found")
+        .to_str()
+        .unwrap()
+        .parse()
+        .expect("Retry-After is not a number of seconds");
+    assert!(retry_after > 0 && retry_after <= LOGIN_LOCKOUT_BASE_SECONDS);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many failed login attempts".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_failed_attempts_on_successful_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+    });
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password456!",
+    });
+
+    // Staying one short of the threshold either side of a successful login never locks
+    for _ in 0..2 {
+        for _ in 1..LOGIN_LOCKOUT_THRESHOLD {
+            let response = app.post_login(&wrong_login_body).await;
+            assert_eq!(response.status().as_u16(), 401);
+        }
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    app.clean_up().await;
+}
@@ -1,9 +1,11 @@
 use crate::helpers::{get_random_email, TestApp};
 
-use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::utils::{
+    auth::validate_token,
+    constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
 use reqwest::Url;
 use serde_json::json;
-use secrecy::Secret;
 #[tokio::test]
 async fn should_return_400_if_jwt_cookie_missing() {
     let mut app = TestApp::new().await;
@@ -50,6 +52,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let response = app.post_signup(&signup_body).await;
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
@@ -67,6 +70,14 @@ async fn should_return_200_if_valid_jwt_cookie() {
     assert!(!auth_cookie.value().is_empty()); // Changed: should NOT be empty after login
     
     let token = auth_cookie.value();
+    let claims = validate_token(
+        token,
+        app.banned_token_store.clone(),
+        app.session_store.clone(),
+        app.user_store.clone(),
+    )
+    .await
+    .expect("Failed to validate token");
 
     let response = app.post_logout().await;
     assert_eq!(response.status().as_u16(), 200);
@@ -85,7 +96,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let banned_token_store = app.banned_token_store.read().await;
     let contains_token = banned_token_store
-        .check_banned_token(&Secret::new(token.to_string()))
+        .check_banned_token(&claims.jti)
         .await
         .expect("Failed to check if token is banned");
     assert!(contains_token);
@@ -113,6 +124,7 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
     });
 
     app.post_signup(&signup_body).await;
+    app.verify_user_email(&email).await;
     app.post_login(&login_body).await;
     // First logout should succeed
     
@@ -123,4 +135,78 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
     let response2 = app.post_logout().await;
     assert_eq!(response2.status(), 400);
     app.clean_up().await;
-}
\ No newline at end of file
+}
+
+// Logs in, which starts a new session, and returns the auth and refresh tokens
+async fn login(app: &TestApp, login_body: &serde_json::Value) -> (String, String) {
+    let response = app.post_login(login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie_value = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("Cookie not found")
+            .value()
+            .to_owned()
+    };
+    (cookie_value(JWT_COOKIE_NAME), cookie_value(REFRESH_TOKEN_COOKIE_NAME))
+}
+
+#[tokio::test]
+async fn should_return_400_if_logout_all_without_jwt_cookie() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_tokens_of_all_sessions_on_logout_all() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&email).await;
+
+    let login_body = json!({
+        "email": email,
+        "password": "Password123!",
+    });
+    let (first_token, first_refresh_token) = login(&app, &login_body).await;
+    let (second_token, _) = login(&app, &login_body).await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_cookie = response.cookies().find(|cookie| cookie.name() == JWT_COOKIE_NAME);
+    assert!(auth_cookie.is_none_or(|cookie| cookie.value().is_empty()));
+
+    for token in [&first_token, &second_token] {
+        let response = app.post_verify_token(&json!({ "token": token })).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The refresh token of the other session cannot mint new auth tokens either
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, first_refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Tokens issued after logging out everywhere carry the new version
+    let (token, _) = login(&app, &login_body).await;
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
@@ -0,0 +1,171 @@
+use auth_service::{
+    domain::{Email, MagicLinkToken, TwoFAMethod},
+    routes::{MagicLinkResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Creates a verified user and returns their email
+async fn signup_user(app: &TestApp, requires_2fa: bool) -> String {
+    let email = get_random_email();
+    let body = json!({
+        "email": email,
+        "password": "Password123!",
+        "requires2FA": requires_2fa
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&email).await;
+    email
+}
+
+// Pulls the token out of the link in the last email received by the mock Postmark server
+async fn get_magic_link_token_from_email(app: &TestApp) -> String {
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().expect("No email sent").body)
+        .expect("Failed to parse email request body");
+    assert_eq!(body["Subject"], "Your login link");
+    let text = body["TextBody"].as_str().expect("No TextBody in email");
+    text.split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in email")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_magic_link(&json!({ "email": "not-an-email" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_without_sending_email_if_user_unknown() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_magic_link(&json!({ "email": get_random_email() })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<MagicLinkResponse>()
+            .await
+            .expect("Could not deserialize response body to MagicLinkResponse"),
+        MagicLinkResponse {
+            message: "If the account exists, a login link has been sent".to_owned(),
+        }
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let token = MagicLinkToken::default();
+    let response = app.get_magic_link_consume(token.as_ref().expose_secret()).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_log_in_once_with_magic_link() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // Expect 2 requests: the verification email on signup and the login link
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app, false).await;
+
+    let response = app.post_magic_link(&json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_magic_link_token_from_email(&app).await;
+    let response = app.get_magic_link_consume(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let response = app.post_verify_token(&json!({ "token": auth_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The link works only once
+    let response = app.get_magic_link_consume(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_after_magic_link_if_enabled() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app, true).await;
+
+    let response = app.post_magic_link(&json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_magic_link_token_from_email(&app).await;
+    let response = app.get_magic_link_consume(&token).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(json_body.two_fa_method, TwoFAMethod::Email);
+
+    let parsed_email = Email::parse(Secret::new(email.clone())).unwrap();
+    let (_, code) = app.two_fa_code_store.read().await.get_code(&parsed_email).await.unwrap();
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": json_body.login_attempt_id,
+            "2FACode": code.as_ref()
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+    app.clean_up().await;
+}
@@ -1,7 +1,27 @@
+mod account;
+mod admin;
+mod change_email;
+mod change_password;
+mod client_credentials;
+mod federated_login;
 mod helpers;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod passkeys;
+mod password_reset;
+mod recovery_codes;
+mod refresh;
+mod revoke;
 mod root;
+mod sessions;
 mod signup;
+mod totp;
+mod two_fa;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
\ No newline at end of file
@@ -0,0 +1,466 @@
+use auth_service::{
+    routes::{ConsentRequiredResponse, IntrospectionResponse, RegisterClientResponse, TokenResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::header::LOCATION;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::helpers::{TestApp, TEST_CLIENT_ID, TEST_CLIENT_SECRET};
+
+const REDIRECT_URI: &str = "https://app.example.com/callback";
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+fn code_challenge() -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(CODE_VERIFIER.as_bytes()))
+}
+
+// Only admins may register clients, so this leaves a new admin logged in. Tests log their
+// user in afterwards.
+pub(crate) async fn register_client(app: &TestApp, confidential: bool) -> RegisterClientResponse {
+    login_as_admin(app).await;
+    let response = app
+        .post_oauth_client(&json!({
+            "clientName": "Example App",
+            "redirectUris": [REDIRECT_URI],
+            "confidential": confidential,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    response
+        .json::<RegisterClientResponse>()
+        .await
+        .expect("Could not deserialize response body to RegisterClientResponse")
+}
+
+async fn login_as_admin(app: &TestApp) {
+    let email = app.signup_and_login(false).await;
+    app.grant_role(&email, "admin").await;
+    // The role only ends up in tokens issued after the grant
+    let response = app
+        .post_login(&json!({ "email": email, "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+pub(crate) fn authorization_request(client_id: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("response_type", "code".to_owned()),
+        ("client_id", client_id.to_owned()),
+        ("redirect_uri", REDIRECT_URI.to_owned()),
+        ("scope", "profile".to_owned()),
+        ("state", "xyz".to_owned()),
+        ("code_challenge", code_challenge()),
+        ("code_challenge_method", "S256".to_owned()),
+    ]
+}
+
+// Parameters the service sent back on the client's redirect URI
+pub(crate) fn redirect_params(response: &reqwest::Response) -> Vec<(String, String)> {
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response.headers()[LOCATION].to_str().unwrap();
+    assert!(location.starts_with(REDIRECT_URI), "{}", location);
+    Url::parse(location)
+        .unwrap()
+        .query_pairs()
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect()
+}
+
+pub(crate) fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(param, _)| param == name)
+        .map(|(_, value)| value.as_str())
+}
+
+// Goes through the consent prompt and returns the authorization code
+async fn authorize(app: &TestApp, client_id: &str) -> String {
+    let request = authorization_request(client_id);
+    let response = app.get_authorize(&request).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let mut consent = request;
+    consent.push(("consent", "approve".to_owned()));
+    let params = redirect_params(&app.post_authorize(&consent).await);
+    assert_eq!(param(&params, "state"), Some("xyz"));
+    param(&params, "code").expect("No code in redirect").to_owned()
+}
+
+pub(crate) async fn exchange_code(
+    app: &TestApp,
+    client: &RegisterClientResponse,
+    code: &str,
+) -> TokenResponse {
+    let response = app
+        .post_token(
+            &json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": REDIRECT_URI,
+                "code_verifier": CODE_VERIFIER,
+            }),
+            Some((&client.client_id, client.client_secret.as_deref().unwrap())),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+}
+
+#[tokio::test]
+async fn should_register_client() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    assert_eq!(client.client_name, "Example App");
+    assert_eq!(client.redirect_uris, vec![REDIRECT_URI.to_owned()]);
+    assert!(client.client_secret.is_some());
+
+    // Public clients are not given a secret
+    let client = register_client(&app, false).await;
+    assert!(client.client_secret.is_none());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_not_admin() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+
+    let response = app
+        .post_oauth_client(&json!({
+            "clientName": "Example App",
+            "redirectUris": [REDIRECT_URI],
+            "confidential": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+// A registered application's secret only works at the token endpoint, introspection,
+// revocation and the client credentials grant are left to service clients
+#[tokio::test]
+async fn should_return_401_if_registered_client_acts_as_service() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+    let token = exchange_code(&app, &client, &code).await.access_token;
+    let credentials = Some((client.client_id.as_str(), client.client_secret.as_deref().unwrap()));
+
+    let response = app.post_introspect(&json!({ "token": token }), credentials).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_revoke(&json!({ "token": token }), credentials).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_token(&json!({ "grant_type": "client_credentials" }), credentials)
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_redirect_uri_not_allowed() {
+    let mut app = TestApp::new().await;
+    login_as_admin(&app).await;
+
+    let response = app
+        .post_oauth_client(&json!({
+            "clientName": "Example App",
+            "redirectUris": ["http://app.example.com/callback"],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_redirect_to_unregistered_uri() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+
+    let mut request = authorization_request(&client.client_id);
+    request[2].1 = "https://evil.example.com/callback".to_owned();
+    let response = app.get_authorize(&request).await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_with_error_if_pkce_missing() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+
+    let request: Vec<_> = authorization_request(&client.client_id)
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with("code_challenge"))
+        .collect();
+    let params = redirect_params(&app.get_authorize(&request).await);
+    assert_eq!(param(&params, "error"), Some("invalid_request"));
+    assert_eq!(param(&params, "state"), Some("xyz"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_user_to_login_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .get_authorize(&authorization_request(&client.client_id))
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response.headers()[LOCATION].to_str().unwrap();
+    assert!(location.starts_with("/?return_to=%2Fauthorize%3F"), "{}", location);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_tokens_for_authorization_code() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    let email = app.signup_and_login(false).await;
+
+    let code = authorize(&app, &client.client_id).await;
+    let tokens = exchange_code(&app, &client, &code).await;
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "profile");
+
+    let response = app
+        .post_introspect(
+            &json!({ "token": tokens.access_token }),
+            Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET)),
+        )
+        .await;
+    let introspection = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert!(introspection.active);
+    assert_eq!(introspection.sub, Some(app.get_user_id(&email).await));
+    assert_eq!(introspection.client_id, Some(client.client_id.clone()));
+    assert_eq!(introspection.scope, Some("profile".to_owned()));
+
+    // Having consented once, the user is not asked again
+    let params = redirect_params(
+        &app.get_authorize(&authorization_request(&client.client_id)).await,
+    );
+    assert!(param(&params, "code").is_some());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_with_error_if_consent_denied() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+
+    let mut consent = authorization_request(&client.client_id);
+    consent.push(("consent", "deny".to_owned()));
+    let params = redirect_params(&app.post_authorize(&consent).await);
+    assert_eq!(param(&params, "error"), Some("access_denied"));
+    assert!(param(&params, "code").is_none());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_code_verifier_wrong() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+
+    let response = app
+        .post_token(
+            &json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": REDIRECT_URI,
+                "code_verifier": CODE_VERIFIER.replace('d', "e"),
+            }),
+            Some((&client.client_id, client.client_secret.as_deref().unwrap())),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // The failed attempt used up the code
+    let response = app
+        .post_token(
+            &json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": REDIRECT_URI,
+                "code_verifier": CODE_VERIFIER,
+            }),
+            Some((&client.client_id, client.client_secret.as_deref().unwrap())),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_code_reused() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+
+    exchange_code(&app, &client, &code).await;
+
+    let response = app
+        .post_token(
+            &json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": REDIRECT_URI,
+                "code_verifier": CODE_VERIFIER,
+            }),
+            Some((&client.client_id, client.client_secret.as_deref().unwrap())),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confidential_client_not_authenticated() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+
+    let response = app
+        .post_token(
+            &json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": REDIRECT_URI,
+                "code_verifier": CODE_VERIFIER,
+                "client_id": client.client_id,
+            }),
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_tokens_to_public_client_with_pkce() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, false).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+
+    let response = app
+        .post_token(
+            &json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": REDIRECT_URI,
+                "code_verifier": CODE_VERIFIER,
+                "client_id": client.client_id,
+            }),
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_refresh_access_token() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+    let tokens = exchange_code(&app, &client, &code).await;
+    let credentials = Some((client.client_id.as_str(), client.client_secret.as_deref().unwrap()));
+
+    let response = app
+        .post_token(
+            &json!({ "grant_type": "refresh_token", "refresh_token": tokens.refresh_token }),
+            credentials,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let refreshed = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(refreshed.scope, "profile");
+    assert_ne!(refreshed.refresh_token, tokens.refresh_token);
+
+    // Refresh tokens are single use here too
+    let response = app
+        .post_token(
+            &json!({ "grant_type": "refresh_token", "refresh_token": tokens.refresh_token }),
+            credentials,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_accept_access_token_as_auth_cookie() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+    let tokens = exchange_code(&app, &client, &code).await;
+
+    let url = reqwest::Url::parse(&app.address).unwrap();
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}", JWT_COOKIE_NAME, tokens.access_token),
+        &url,
+    );
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_access_token_at_verify_token() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    let code = authorize(&app, &client.client_id).await;
+    let tokens = exchange_code(&app, &client, &code).await;
+
+    let response = app.post_verify_token(&json!({ "token": tokens.access_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_show_consent_prompt_for_new_scope() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+    authorize(&app, &client.client_id).await;
+
+    let mut request = authorization_request(&client.client_id);
+    request[3].1 = "profile email".to_owned();
+    let response = app.get_authorize(&request).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let prompt = response
+        .json::<ConsentRequiredResponse>()
+        .await
+        .expect("Could not deserialize response body to ConsentRequiredResponse");
+    assert_eq!(prompt.client_name, "Example App");
+    assert_eq!(prompt.scope, "email profile");
+    app.clean_up().await;
+}
@@ -0,0 +1,237 @@
+use auth_service::{
+    domain::Email,
+    routes::{TwoFactorAuthResponse, UserinfoResponse},
+    utils::{
+        auth::{id_tokens_supported, IdTokenClaims},
+        constants::{AUTH_SERVICE_URL, JWT_COOKIE_NAME, JWT_ISSUER},
+    },
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode_header, jwk::JwkSet};
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::{
+    helpers::{get_random_email, TestApp},
+    oauth::{
+        authorization_request, exchange_code, param, redirect_params, register_client,
+    },
+};
+
+// Authorizes the client for the given scope, returns the code
+async fn authorize_scope(app: &TestApp, client_id: &str, scope: &str) -> String {
+    let mut request = authorization_request(client_id);
+    request[3].1 = scope.to_owned();
+    request.push(("nonce", "n-0S6_WzA2Mj".to_owned()));
+    request.push(("consent", "approve".to_owned()));
+
+    let params = redirect_params(&app.post_authorize(&request).await);
+    param(&params, "code").expect("No code in redirect").to_owned()
+}
+
+// The signature is checked by the client, here only the claims matter
+fn id_token_claims(id_token: &str) -> IdTokenClaims {
+    let payload = id_token.split('.').nth(1).expect("ID token is not a JWT");
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap())
+        .expect("Could not deserialize ID token claims")
+}
+
+#[tokio::test]
+async fn should_publish_openid_configuration() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_openid_configuration().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let configuration = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(configuration["issuer"], JWT_ISSUER.as_str());
+    assert_eq!(
+        configuration["authorization_endpoint"],
+        format!("{}/authorize", AUTH_SERVICE_URL.as_str())
+    );
+    assert_eq!(
+        configuration["jwks_uri"],
+        format!("{}/.well-known/jwks.json", AUTH_SERVICE_URL.as_str())
+    );
+    assert_eq!(configuration["code_challenge_methods_supported"], json!(["S256"]));
+    // Clients can not verify HS256 ID tokens, so it is never offered
+    let algorithms = configuration["id_token_signing_alg_values_supported"].as_array().unwrap();
+    assert_eq!(algorithms.is_empty(), !id_tokens_supported().unwrap());
+    assert!(!algorithms.contains(&json!("HS256")));
+    app.clean_up().await;
+}
+
+// The default configuration signs with JWT_SECRET, ID tokens need a key file
+#[tokio::test]
+async fn should_refuse_openid_scope_without_key_file() {
+    if id_tokens_supported().unwrap() {
+        return;
+    }
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+
+    let mut request = authorization_request(&client.client_id);
+    request[3].1 = "openid email".to_owned();
+    request.push(("consent", "approve".to_owned()));
+    let params = redirect_params(&app.post_authorize(&request).await);
+    assert_eq!(param(&params, "error"), Some("invalid_scope"));
+    assert!(param(&params, "code").is_none());
+
+    let configuration = app
+        .get_openid_configuration()
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(configuration["scopes_supported"], json!([]));
+    assert_eq!(configuration["id_token_signing_alg_values_supported"], json!([]));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_id_token_for_openid_scope() {
+    if !id_tokens_supported().unwrap() {
+        return;
+    }
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    let email = app.signup_and_login(false).await;
+
+    let code = authorize_scope(&app, &client.client_id, "openid email").await;
+    let tokens = exchange_code(&app, &client, &code).await;
+
+    let id_token = tokens.id_token.expect("No ID token issued");
+    let jwks = app
+        .get_jwks()
+        .await
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+    let kid = decode_header(&id_token).unwrap().kid.expect("ID token has no kid");
+    assert!(jwks.find(&kid).is_some(), "ID token signing key not published");
+
+    let claims = id_token_claims(&id_token);
+    assert_eq!(claims.iss, JWT_ISSUER.as_str());
+    assert_eq!(claims.sub, app.get_user_id(&email).await);
+    assert_eq!(claims.aud, client.client_id);
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+    assert_eq!(claims.amr, vec!["pwd"]);
+    assert!(claims.auth_time as usize <= claims.iat);
+    assert_eq!(claims.email, Some(email));
+    assert_eq!(claims.email_verified, Some(true));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_issue_id_token_without_openid_scope() {
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    app.signup_and_login(false).await;
+
+    let code = authorize_scope(&app, &client.client_id, "profile").await;
+    let tokens = exchange_code(&app, &client, &code).await;
+    assert!(tokens.id_token.is_none());
+
+    // Nor is userinfo available to the access token
+    let response = app.get_userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_report_second_factor_in_amr() {
+    if !id_tokens_supported().unwrap() {
+        return;
+    }
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = get_random_email();
+    let body = json!({ "email": email, "password": "Password123!", "requires2FA": true });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&email).await;
+
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let parsed_email = Email::parse(Secret::new(email.clone())).unwrap();
+    let (_, code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&parsed_email)
+        .await
+        .unwrap();
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let code = authorize_scope(&app, &client.client_id, "openid").await;
+    let tokens = exchange_code(&app, &client, &code).await;
+
+    let claims = id_token_claims(&tokens.id_token.expect("No ID token issued"));
+    assert_eq!(claims.amr, vec!["otp", "mfa"]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_userinfo_for_access_token() {
+    if !id_tokens_supported().unwrap() {
+        return;
+    }
+    let mut app = TestApp::new().await;
+    let client = register_client(&app, true).await;
+    let email = app.signup_and_login(false).await;
+
+    let code = authorize_scope(&app, &client.client_id, "openid email").await;
+    let tokens = exchange_code(&app, &client, &code).await;
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let userinfo = response
+        .json::<UserinfoResponse>()
+        .await
+        .expect("Could not deserialize response body to UserinfoResponse");
+    assert_eq!(userinfo.sub, app.get_user_id(&email).await);
+    assert_eq!(userinfo.email, Some(email));
+    assert_eq!(userinfo.email_verified, Some(true));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_for_userinfo_with_auth_cookie_token() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let body = json!({ "email": email, "password": "Password123!", "requires2FA": false });
+    app.post_signup(&body).await;
+    app.verify_user_email(&email).await;
+    let response = app.post_login(&body).await;
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    // Tokens for this service were not issued to a client and carry no scope
+    let response = app.get_userinfo(&auth_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
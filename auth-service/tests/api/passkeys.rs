@@ -0,0 +1,367 @@
+use auth_service::{
+    domain::TwoFAMethod,
+    routes::{
+        PasskeyCreationOptionsResponse, PasskeyRegisteredResponse, PasskeyRequestOptionsResponse,
+        TwoFactorAuthResponse,
+    },
+    utils::constants::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use rand::Rng;
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::TestApp;
+
+// Software authenticator holding a single ES256 passkey, answering the ceremonies
+// the way a browser passes them on from a platform authenticator
+struct Authenticator {
+    key_pair: EcdsaKeyPair,
+    credential_id: String,
+    user_handle: Option<String>,
+    sign_count: u32,
+}
+
+impl Authenticator {
+    fn new() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        Self {
+            key_pair,
+            credential_id: URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 16]>()),
+            user_handle: None,
+            sign_count: 0,
+        }
+    }
+
+    fn auth_data(&self, flags: u8) -> Vec<u8> {
+        let mut auth_data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&self.sign_count.to_be_bytes());
+        auth_data
+    }
+
+    fn client_data(ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        json!({ "type": ceremony_type, "challenge": challenge, "origin": WEBAUTHN_ORIGIN.as_str() })
+            .to_string()
+            .into_bytes()
+    }
+
+    fn create(&mut self, options: &PasskeyCreationOptionsResponse) -> serde_json::Value {
+        self.user_handle = Some(options.public_key.user.id.clone());
+
+        let public_key = self.key_pair.public_key().as_ref();
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(-7)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(public_key[1..33].to_vec())),
+            (Value::from(-3), Value::Bytes(public_key[33..].to_vec())),
+        ]);
+        let credential_id = URL_SAFE_NO_PAD.decode(&self.credential_id).unwrap();
+
+        // User present, user verified and attested credential data included
+        let mut auth_data = self.auth_data(0x45);
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        json!({
+            "id": self.credential_id,
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD
+                    .encode(Self::client_data("webauthn.create", &options.public_key.challenge)),
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+            }
+        })
+    }
+
+    fn get(&mut self, options: &PasskeyRequestOptionsResponse) -> serde_json::Value {
+        self.sign_count += 1;
+
+        let client_data_json = Self::client_data("webauthn.get", &options.public_key.challenge);
+        // User present and user verified
+        let auth_data = self.auth_data(0x05);
+        let mut signed_data = auth_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), &signed_data)
+            .unwrap();
+
+        json!({
+            "id": self.credential_id,
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+                "userHandle": self.user_handle,
+            }
+        })
+    }
+}
+
+// Registration for a user without 2FA, who only confirms their password
+async fn register(app: &TestApp, authenticator: &mut Authenticator) -> PasskeyRegisteredResponse {
+    let response = app.post_passkey_register_start(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let options = response
+        .json::<PasskeyCreationOptionsResponse>()
+        .await
+        .expect("Could not deserialize response body to PasskeyCreationOptionsResponse");
+
+    let response = app
+        .post_passkey_register_finish(&json!({
+            "password": "Password123!",
+            "credential": authenticator.create(&options)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<PasskeyRegisteredResponse>()
+        .await
+        .expect("Could not deserialize response body to PasskeyRegisteredResponse")
+}
+
+async fn login_options(app: &TestApp) -> PasskeyRequestOptionsResponse {
+    let response = app.post_passkey_login_start().await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<PasskeyRequestOptionsResponse>()
+        .await
+        .expect("Could not deserialize response body to PasskeyRequestOptionsResponse")
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_passkey_register_start(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_register_passkey_as_second_factor() {
+    let mut app = TestApp::new().await;
+
+    let email = app.signup_and_login(false).await;
+    let mut authenticator = Authenticator::new();
+
+    let registered = register(&app, &mut authenticator).await;
+    assert_eq!(registered.message, "Passkey registered");
+    assert_eq!(registered.recovery_codes.len(), 10);
+
+    // Passkey users confirm further registrations with an emailed code
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let response = app.post_passkey_register_start(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let code = app.get_reauthentication_code(&email).await;
+    let response = app
+        .post_passkey_register_start(&json!({ "password": "Password123!", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let options = response
+        .json::<PasskeyCreationOptionsResponse>()
+        .await
+        .expect("Could not deserialize response body to PasskeyCreationOptionsResponse");
+    assert_eq!(options.public_key.rp.id, WEBAUTHN_RP_ID.as_str());
+    assert_eq!(options.public_key.user.name, email);
+    // The registered passkey is excluded from being registered again
+    assert_eq!(options.public_key.exclude_credentials.len(), 1);
+    assert_eq!(options.public_key.exclude_credentials[0].id, authenticator.credential_id);
+
+    // Each emailed code confirms a single step
+    let credential = authenticator.create(&options);
+    let response = app
+        .post_passkey_register_finish(&json!({ "password": "Password123!", "credential": credential }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let code = app.get_reauthentication_code(&email).await;
+    let response = app
+        .post_passkey_register_finish(&json!({
+            "password": "Password123!",
+            "2FACode": code,
+            "credential": credential
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_log_in_with_passkey_without_password() {
+    let mut app = TestApp::new().await;
+
+    app.signup_and_login(false).await;
+    let mut authenticator = Authenticator::new();
+    register(&app, &mut authenticator).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let options = login_options(&app).await;
+    assert!(options.public_key.allow_credentials.is_empty());
+
+    let response = app.post_passkey_login_finish(&authenticator.get(&options)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let response = app.post_verify_token(&json!({ "token": auth_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_forget_passkeys_when_2fa_is_disabled() {
+    let mut app = TestApp::new().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(false).await;
+    let mut authenticator = Authenticator::new();
+    register(&app, &mut authenticator).await;
+
+    let response = app.post_disable_2fa(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let code = app.get_reauthentication_code(&email).await;
+    let response = app
+        .post_disable_2fa(&json!({ "password": "Password123!", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The passkey no longer logs the user in without their password
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let options = login_options(&app).await;
+    let response = app.post_passkey_login_finish(&authenticator.get(&options)).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_challenge_reused() {
+    let mut app = TestApp::new().await;
+
+    app.signup_and_login(false).await;
+    let mut authenticator = Authenticator::new();
+    register(&app, &mut authenticator).await;
+
+    let options = login_options(&app).await;
+    let response = app.post_passkey_login_finish(&authenticator.get(&options)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_passkey_login_finish(&authenticator.get(&options)).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_signed_by_another_key() {
+    let mut app = TestApp::new().await;
+
+    app.signup_and_login(false).await;
+    let mut authenticator = Authenticator::new();
+    register(&app, &mut authenticator).await;
+
+    // Same credential id, but a key the service never saw
+    let mut impostor = Authenticator::new();
+    impostor.credential_id = authenticator.credential_id.clone();
+    impostor.user_handle = authenticator.user_handle.clone();
+
+    let options = login_options(&app).await;
+    let response = app.post_passkey_login_finish(&impostor.get(&options)).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_complete_password_login_with_passkey_as_second_factor() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1) // Only the verification email on signup, passkey users are not sent a 2FA code
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(false).await;
+    let mut authenticator = Authenticator::new();
+    register(&app, &mut authenticator).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(json_body.two_fa_method, TwoFAMethod::Passkey);
+
+    let response = app
+        .post_verify_2fa_passkey_start(&json!({
+            "email": email,
+            "loginAttemptId": json_body.login_attempt_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let options = response
+        .json::<PasskeyRequestOptionsResponse>()
+        .await
+        .expect("Could not deserialize response body to PasskeyRequestOptionsResponse");
+    assert_eq!(options.public_key.allow_credentials.len(), 1);
+
+    let response = app
+        .post_verify_2fa_passkey(&json!({
+            "email": email,
+            "loginAttemptId": json_body.login_attempt_id,
+            "credential": authenticator.get(&options),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    // The login attempt is used up
+    let response = app
+        .post_verify_2fa_passkey_start(&json!({
+            "email": email,
+            "loginAttemptId": json_body.login_attempt_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
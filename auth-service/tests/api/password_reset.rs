@@ -0,0 +1,256 @@
+use auth_service::{
+    domain::PasswordResetToken,
+    routes::PasswordResetResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Creates a user without 2FA and returns its email
+async fn signup_user(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&email).await;
+    email
+}
+
+// Pulls the reset token out of the last email received by the mock Postmark server
+async fn get_reset_token_from_email(app: &TestApp) -> String {
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().expect("No email sent").body)
+        .expect("Failed to parse email request body");
+    let text = body["TextBody"].as_str().expect("No TextBody in email");
+    text.split("token is: ")
+        .nth(1)
+        .and_then(|rest| rest.split('.').next())
+        .expect("No token in email")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "mail": "user@example.com" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({ "token": "abc" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": "invalid" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_without_sending_email_if_user_does_not_exist() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_reset_email_if_user_exists() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // Expect 2 requests: the verification email on signup and the reset email
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+    let response = app
+        .post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_reset_token_from_email(&app).await;
+    let stored_email = app
+        .password_reset_token_store
+        .read()
+        .await
+        .get_email(&PasswordResetToken::parse(token).unwrap())
+        .await
+        .expect("Reset token was not stored");
+    assert_eq!(stored_email.as_ref().expose_secret(), &email);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        "invalid".to_owned(),
+        PasswordResetToken::default().as_ref().expose_secret().to_owned(),
+    ];
+
+    for token in test_cases.iter() {
+        let response = app
+            .post_password_reset_confirm(&serde_json::json!({
+                "token": token,
+                "newPassword": "NewPassword123!"
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for token: {}", token);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_invalid() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // Expect 2 requests: the verification email on signup and the reset email
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+    app.post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+    let token = get_reset_token_from_email(&app).await;
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "newPassword": "weak"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid credentials".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_password_and_invalidate_sessions() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // Expect 2 requests: the verification email on signup and the reset email
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+    let old_login_body = serde_json::json!({
+        "email": email,
+        "password": "Password123!",
+    });
+    let response = app.post_login(&old_login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    app.post_password_reset_request(&serde_json::json!({ "email": email }))
+        .await;
+    let token = get_reset_token_from_email(&app).await;
+
+    let confirm_body = serde_json::json!({
+        "token": token,
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<PasswordResetResponse>()
+            .await
+            .expect("Could not deserialize response body to PasswordResetResponse"),
+        PasswordResetResponse {
+            message: "Password has been reset".to_owned()
+        }
+    );
+
+    // The token is single use
+    let response = app.post_password_reset_confirm(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Only the new password is accepted
+    let response = app.post_login(&old_login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "NewPassword123!",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The session opened before the reset can no longer be extended
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // and its auth token is rejected as well
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
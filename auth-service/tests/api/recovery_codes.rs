@@ -0,0 +1,224 @@
+use auth_service::{
+    routes::{RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Signs up a verified user with email 2FA, returns their login body and recovery codes
+async fn signup_with_2fa(app: &TestApp) -> (serde_json::Value, Vec<String>) {
+    // Accept the verification and 2FA emails, the codes are never read in these tests
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "Password123!",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes returned");
+    app.verify_user_email(&email).await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "Password123!",
+    });
+    (login_body, recovery_codes)
+}
+
+// Runs the password step and returns the login attempt id
+async fn start_login(app: &TestApp, login_body: &serde_json::Value) -> String {
+    let response = app.post_login(login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": get_random_email(),
+            "loginAttemptId": "7a6a5b1e-4b8c-4b8e-9d1a-2b3c4d5e6f70",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+    let (login_body, recovery_codes) = signup_with_2fa(&app).await;
+    let login_attempt_id = start_login(&app, &login_body).await;
+
+    let test_cases = [
+        serde_json::json!({
+            "email": "invalid",
+            "loginAttemptId": login_attempt_id,
+            "recoveryCode": recovery_codes[0],
+        }),
+        serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": "invalid",
+            "recoveryCode": recovery_codes[0],
+        }),
+        serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            "recoveryCode": "not-a-code",
+        }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_verify_recovery_code(test_case).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", test_case);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_password_step() {
+    let mut app = TestApp::new().await;
+    let (login_body, recovery_codes) = signup_with_2fa(&app).await;
+
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": "7a6a5b1e-4b8c-4b8e-9d1a-2b3c4d5e6f70",
+            "recoveryCode": recovery_codes[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_log_in_with_recovery_code_only_once() {
+    let mut app = TestApp::new().await;
+    let (login_body, recovery_codes) = signup_with_2fa(&app).await;
+
+    let login_attempt_id = start_login(&app, &login_body).await;
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            // Codes are accepted however the user types them
+            "recoveryCode": recovery_codes[0].replace('-', "").to_lowercase(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    // The code has been burned
+    let login_attempt_id = start_login(&app, &login_body).await;
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            "recoveryCode": recovery_codes[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The other codes still work
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            "recoveryCode": recovery_codes[1],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_regenerating_without_jwt() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_codes_on_regenerate() {
+    let mut app = TestApp::new().await;
+    let (login_body, old_codes) = signup_with_2fa(&app).await;
+
+    // Log in with a recovery code to get a session
+    let login_attempt_id = start_login(&app, &login_body).await;
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            "recoveryCode": old_codes[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // New codes need the password and a fresh emailed code
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.post_regenerate_recovery_codes(&body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let code = app.get_reauthentication_code(login_body["email"].as_str().unwrap()).await;
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({
+            "password": "Password123!",
+            "2FACode": code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(new_codes.len(), 10);
+
+    let login_attempt_id = start_login(&app, &login_body).await;
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            "recoveryCode": old_codes[1],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_recovery_code(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            "recoveryCode": new_codes[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
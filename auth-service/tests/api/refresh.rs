@@ -0,0 +1,156 @@
+use auth_service::{
+    domain::RefreshToken,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Signs up a user without 2FA, logs in and returns the refresh token issued on login
+async fn login_and_get_refresh_token(app: &TestApp) -> String {
+    let random_email = get_random_email();
+    let body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
+
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+    refresh_cookie.value().to_owned()
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    set_refresh_cookie(&app, "invalid");
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Well formed, but never issued
+    let unknown_token = RefreshToken::default();
+    set_refresh_cookie(&app, unknown_token.as_ref().expose_secret());
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    let first_token = login_and_get_refresh_token(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    let second_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+    assert_ne!(first_token, second_token);
+
+    let first_record = app
+        .refresh_token_store
+        .read()
+        .await
+        .get_token(&RefreshToken::parse(first_token).unwrap())
+        .await
+        .expect("Failed to get refresh token");
+    assert!(first_record.used);
+
+    let second_record = app
+        .refresh_token_store
+        .read()
+        .await
+        .get_token(&RefreshToken::parse(second_token).unwrap())
+        .await
+        .expect("Failed to get refresh token");
+    assert!(!second_record.used);
+    assert_eq!(first_record.family_id, second_record.family_id);
+
+    // The rotated token stored in the cookie jar can be used again
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_token_family_if_refresh_token_reused() {
+    let mut app = TestApp::new().await;
+
+    let first_token = login_and_get_refresh_token(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let second_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    // Replay the already rotated token
+    set_refresh_cookie(&app, &first_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The newest token of the family is no longer accepted either
+    set_refresh_cookie(&app, &second_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_after_logout() {
+    let mut app = TestApp::new().await;
+
+    let token = login_and_get_refresh_token(&app).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
@@ -0,0 +1,99 @@
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+
+use crate::helpers::{get_random_email, TestApp, TEST_CLIENT_ID, TEST_CLIENT_SECRET};
+
+// Logs a new user in and returns the auth and refresh tokens
+async fn login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie_value = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("Cookie not found")
+            .value()
+            .to_owned()
+    };
+    (cookie_value(JWT_COOKIE_NAME), cookie_value(REFRESH_TOKEN_COOKIE_NAME))
+}
+
+#[tokio::test]
+async fn should_revoke_auth_token_and_its_session() {
+    let mut app = TestApp::new().await;
+    let (token, _) = login(&app).await;
+
+    let body = serde_json::json!({ "token": token });
+    let response = app.post_revoke(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET))).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The refresh token issued with it can no longer extend the session
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_refresh_token() {
+    let mut app = TestApp::new().await;
+    let (_, refresh_token) = login(&app).await;
+
+    let body = serde_json::json!({ "token": refresh_token });
+    let response = app.post_revoke(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET))).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_for_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    for token in ["invalid", &"a".repeat(64)] {
+        let body = serde_json::json!({ "token": token });
+        let response = app.post_revoke(&body, Some((TEST_CLIENT_ID, TEST_CLIENT_SECRET))).await;
+        assert_eq!(response.status().as_u16(), 200, "Failed for token: {}", token);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_valid_client_credentials() {
+    let mut app = TestApp::new().await;
+    let (token, _) = login(&app).await;
+    let body = serde_json::json!({ "token": token });
+
+    for credentials in [None, Some((TEST_CLIENT_ID, "wrong-secret"))] {
+        let response = app.post_revoke(&body, credentials).await;
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for credentials: {:?}",
+            credentials
+        );
+    }
+
+    // The token is left alone
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
@@ -0,0 +1,167 @@
+use auth_service::{
+    routes::SessionResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+use reqwest::Url;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_user_email(&random_email).await;
+    random_email
+}
+
+// Logs in, which starts a new session, and returns the auth and refresh tokens
+async fn login(app: &TestApp, email: &str) -> (String, String) {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "Password123!",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie_value = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("Cookie not found")
+            .value()
+            .to_owned()
+    };
+    (cookie_value(JWT_COOKIE_NAME), cookie_value(REFRESH_TOKEN_COOKIE_NAME))
+}
+
+// Switches the test client back to an earlier session
+fn use_session(app: &TestApp, (token, refresh_token): &(String, String)) {
+    let url = Url::parse("http://127.0.0.1").expect("Failed to parse URL");
+    for (name, value) in [(JWT_COOKIE_NAME, token), (REFRESH_TOKEN_COOKIE_NAME, refresh_token)] {
+        app.cookie_jar.add_cookie_str(
+            &format!("{}={}; HttpOnly; SameSite=Lax; Secure; Path=/", name, value),
+            &url,
+        );
+    }
+}
+
+async fn get_sessions(app: &TestApp) -> Vec<SessionResponse> {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to sessions")
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_sessions_of_user() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    login(&app, &email).await;
+    login(&app, &email).await;
+
+    // Sessions of other users are not listed
+    let other_email = signup(&app).await;
+    login(&app, &other_email).await;
+    login(&app, &email).await;
+
+    let sessions = get_sessions(&app).await;
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+    for session in sessions.iter() {
+        assert_eq!(session.ip_address.as_deref(), Some("127.0.0.1"));
+        assert!(session.last_seen >= session.created_at);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_session() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    let (old_token, _) = login(&app, &email).await;
+    login(&app, &email).await;
+
+    let old_session = get_sessions(&app)
+        .await
+        .into_iter()
+        .find(|session| !session.current)
+        .expect("Other session not listed");
+
+    let body = serde_json::json!({ "sessionId": old_session.id });
+    let response = app.post_revoke_session(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": old_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_when_revoking_session_of_other_user() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    let (token, _) = login(&app, &email).await;
+    let other_session_id = get_sessions(&app).await[0].id.clone();
+
+    let other_email = signup(&app).await;
+    login(&app, &other_email).await;
+
+    for session_id in [other_session_id, "not-a-session".to_owned()] {
+        let body = serde_json::json!({ "sessionId": session_id });
+        let response = app.post_revoke_session(&body).await;
+        assert_eq!(response.status().as_u16(), 404, "Failed for session: {}", session_id);
+    }
+
+    let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_other_sessions() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    let first = login(&app, &email).await;
+    let second = login(&app, &email).await;
+    let (current_token, _) = login(&app, &email).await;
+
+    let response = app.post_revoke_other_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    for (token, _) in [&first, &second] {
+        let response = app.post_verify_token(&serde_json::json!({ "token": token })).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    let response = app.post_verify_token(&serde_json::json!({ "token": current_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Revoked sessions can not be extended either
+    use_session(&app, &first);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
@@ -43,10 +43,6 @@ async fn should_return_201_if_valid_input() {
         "requires2FA": true
     });
 
-    let expected_response = SignupResponse {
-        message: "User created successfully!".to_owned(),
-    };
-
     let response = app.post_signup(&body).await;
     assert_eq!(
         response.status().as_u16(),
@@ -60,7 +56,34 @@ async fn should_return_201_if_valid_input() {
         .await
         .expect("Could not deserialize response body to SignupResponse");
 
-    assert_eq!(actual_response, expected_response);
+    assert_eq!(actual_response.message, "User created successfully!".to_owned());
+    // Signing up with 2FA hands out the recovery codes
+    assert_eq!(actual_response.recovery_codes.map(|codes| codes.len()), Some(10));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_return_recovery_codes_without_2fa() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(
+        response
+            .json::<SignupResponse>()
+            .await
+            .expect("Could not deserialize response body to SignupResponse"),
+        SignupResponse {
+            message: "User created successfully!".to_owned(),
+            recovery_codes: None,
+        }
+    );
     app.clean_up().await;
 }
 
@@ -0,0 +1,348 @@
+use auth_service::{
+    domain::{Email, TotpSecretStoreError, TwoFAMethod},
+    routes::{TotpConfirmResponse, TotpEnrollmentResponse, TwoFactorAuthResponse},
+    utils::constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_THRESHOLD},
+    ErrorResponse,
+};
+use secrecy::Secret;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::TOTP;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::TestApp;
+
+// Enrollment of a user without 2FA, who only confirms their password
+async fn enroll(app: &TestApp) -> TotpEnrollmentResponse {
+    let response = app.post_totp_enroll(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse")
+}
+
+// Computes the current code the way an authenticator app scanning the URI would
+fn current_code(otpauth_uri: &str) -> String {
+    TOTP::from_url(otpauth_uri)
+        .expect("Invalid otpauth URI")
+        .generate_current()
+        .expect("Failed to generate TOTP code")
+}
+
+// Each code is only accepted once, tests that need several take them from the time steps
+// around the current one, in order, which the default skew of one step still accepts
+fn code_at_step(otpauth_uri: &str, steps_from_now: i64) -> String {
+    let totp = TOTP::from_url(otpauth_uri).expect("Invalid otpauth URI");
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    totp.generate((now + steps_from_now * totp.step as i64) as u64)
+}
+
+// Any code that is not valid right now
+fn wrong_code(otpauth_uri: &str) -> String {
+    let code: u32 = current_code(otpauth_uri).parse().unwrap();
+    format!("{:06}", (code + 500_000) % 1_000_000)
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_totp_enroll(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_totp_confirm(&json!({ "password": "Password123!", "2FACode": "123456" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_otpauth_uri_on_enroll() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+    let login_body = serde_json::json!({ "email": email, "password": "Password123!" });
+
+    let enrollment = enroll(&app).await;
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment
+        .otpauth_uri
+        .contains(&format!("secret={}", enrollment.secret)));
+
+    // Enrolling alone does not switch the account to TOTP
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_incorrect() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+
+    let response = app.post_totp_enroll(&json!({ "password": "WrongPassword123!" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let enrollment = enroll(&app).await;
+    let response = app
+        .post_totp_confirm(&json!({
+            "password": "WrongPassword123!",
+            "2FACode": current_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_too_many_wrong_totp_codes_when_reenrolling() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+    let enrollment = enroll(&app).await;
+    let response = app
+        .post_totp_confirm(&json!({
+            "password": "Password123!",
+            "2FACode": current_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Replacing the secret needs a code from the current one
+    let response = app.post_totp_enroll(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let body = json!({ "password": "Password123!", "2FACode": wrong_code(&enrollment.otpauth_uri) });
+    for _ in 0..LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_totp_enroll(&body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_totp_enroll(&json!({
+            "password": "Password123!",
+            "2FACode": current_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 429);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confirmation_code_incorrect() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+    let enrollment = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&json!({ "password": "Password123!", "2FACode": wrong_code(&enrollment.otpauth_uri) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_confirmation_code_malformed() {
+    let mut app = TestApp::new().await;
+    app.signup_and_login(false).await;
+    enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&json!({ "password": "Password123!", "2FACode": "12345a" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_totp_code_on_login_once_confirmed() {
+    let mut app = TestApp::new().await;
+
+    // Only the verification email on signup, TOTP logins send nothing
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = app.signup_and_login(false).await;
+    let login_body = serde_json::json!({ "email": email, "password": "Password123!" });
+    let enrollment = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&json!({ "password": "Password123!", "2FACode": code_at_step(&enrollment.otpauth_uri, -1) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let confirm_response = response
+        .json::<TotpConfirmResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpConfirmResponse");
+    assert_eq!(confirm_response.message, "TOTP 2FA enabled".to_owned());
+    assert_eq!(confirm_response.recovery_codes.len(), 10);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let two_fa_response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(two_fa_response.two_fa_method, TwoFAMethod::Totp);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": two_fa_response.login_attempt_id,
+            "2FACode": wrong_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": two_fa_response.login_attempt_id,
+            "2FACode": current_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_active_secret_when_reenrolling() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+    let login_body = serde_json::json!({ "email": email, "password": "Password123!" });
+
+    let enrollment = enroll(&app).await;
+    let response = app
+        .post_totp_confirm(&json!({ "password": "Password123!", "2FACode": code_at_step(&enrollment.otpauth_uri, -1) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Starting a new enrollment without confirming it leaves the old secret in place
+    let response = app
+        .post_totp_enroll(&json!({
+            "password": "Password123!",
+            "2FACode": current_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let pending_enrollment = response
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollmentResponse");
+    assert_ne!(pending_enrollment.secret, enrollment.secret);
+
+    let response = app.post_login(&login_body).await;
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": login_body["email"],
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code_at_step(&enrollment.otpauth_uri, 1)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_secret_when_2fa_is_disabled() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+
+    let enrollment = enroll(&app).await;
+    let response = app
+        .post_totp_confirm(&json!({ "password": "Password123!", "2FACode": code_at_step(&enrollment.otpauth_uri, -1) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_disable_2fa(&json!({
+            "password": "Password123!",
+            "2FACode": current_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let email = Email::parse(Secret::new(email)).unwrap();
+    let result = app.totp_secret_store.read().await.get_secret(&email).await;
+    assert_eq!(result.unwrap_err(), TotpSecretStoreError::SecretNotFound);
+    app.clean_up().await;
+}
+
+// RFC 6238 section 5.2, a code seen by someone looking over the user's shoulder must not
+// work again while it is still inside the accepted window
+#[tokio::test]
+async fn should_reject_code_used_before() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+    let login_body = serde_json::json!({ "email": email, "password": "Password123!" });
+
+    let enrollment = enroll(&app).await;
+    let response = app
+        .post_totp_confirm(&json!({ "password": "Password123!", "2FACode": code_at_step(&enrollment.otpauth_uri, -1) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let code = current_code(&enrollment.otpauth_uri);
+    let response = app.post_login(&login_body).await;
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body).await;
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Reauthenticating refuses it too
+    let response = app
+        .post_totp_enroll(&json!({ "password": "Password123!", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
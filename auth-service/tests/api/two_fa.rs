@@ -0,0 +1,163 @@
+use auth_service::{
+    domain::TwoFAMethod,
+    routes::{SecondFactorRequiredResponse, TwoFAEnabledResponse, TwoFactorAuthResponse},
+    utils::constants::LOGIN_LOCKOUT_THRESHOLD,
+};
+use serde_json::json;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::TestApp;
+
+async fn mock_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_enable_2fa(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.post_disable_2fa(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_incorrect() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+
+    let response = app.post_enable_2fa(&json!({ "password": "WrongPassword123!" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // 2FA is still off
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+// Confirming a change with the password must not allow more guesses than /login does
+#[tokio::test]
+async fn should_lock_account_after_too_many_wrong_passwords() {
+    let mut app = TestApp::new().await;
+    let email = app.signup_and_login(false).await;
+
+    for _ in 0..LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_enable_2fa(&json!({ "password": "WrongPassword123!" })).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_enable_2fa(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 429);
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 429);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_enable_2fa() {
+    let mut app = TestApp::new().await;
+    mock_email_server(&app).await;
+    let email = app.signup_and_login(false).await;
+
+    let response = app.post_enable_2fa(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = response
+        .json::<TwoFAEnabledResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFAEnabledResponse");
+    assert!(!response.recovery_codes.is_empty());
+
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(response.two_fa_method, TwoFAMethod::Email);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_fresh_2fa_code_to_disable_2fa() {
+    let mut app = TestApp::new().await;
+    mock_email_server(&app).await;
+    let email = app.signup_and_login(true).await;
+
+    // Without a code a new one is emailed and 2FA stays on
+    let response = app.post_disable_2fa(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(
+        response
+            .json::<SecondFactorRequiredResponse>()
+            .await
+            .expect("Could not deserialize response body to SecondFactorRequiredResponse")
+            .two_fa_method,
+        TwoFAMethod::Email
+    );
+    let code = app.get_reauthentication_code(&email).await;
+
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+    let response = app
+        .post_disable_2fa(&json!({ "password": "Password123!", "2FACode": wrong_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_disable_2fa(&json!({ "password": "Password123!", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&json!({ "email": email, "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_reauthentication_codes_apart_from_login_codes() {
+    let mut app = TestApp::new().await;
+    mock_email_server(&app).await;
+    let email = app.signup_and_login(true).await;
+
+    // A second login is waiting for its code while the user confirms a change
+    let login_attempt_id = app
+        .post_login(&json!({ "email": email, "password": "Password123!" }))
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let login_code = app.get_two_fa_code(&email).await;
+
+    let response = app.post_disable_2fa(&json!({ "password": "Password123!" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let code = app.get_reauthentication_code(&email).await;
+
+    if login_code != code {
+        let response = app
+            .post_disable_2fa(&json!({ "password": "Password123!", "2FACode": login_code }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Sending the code did not replace the one of the login
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": login_code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_disable_2fa(&json!({ "password": "Password123!", "2FACode": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
@@ -1,11 +1,14 @@
 use auth_service::{
-    domain::Email,
-    routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME,
+    domain::{Email, TwoFACode, UserId},
+    routes::{TotpEnrollmentResponse, TwoFactorAuthResponse},
+    utils::constants::{JWT_COOKIE_NAME, TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS, TWO_FA_MAX_FAILED_ATTEMPTS},
+    ErrorResponse,
 };
 use secrecy::{ExposeSecret, Secret};
+use totp_rs::TOTP;
 use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
 
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{configure_redis, get_random_email, TestApp};
 
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
@@ -49,7 +52,7 @@ async fn should_return_401_if_incorrect_credentials() {
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
-        .expect(1)
+        .expect(2) // Expect 2 requests: the verification email on signup and the 2FA code on login
         .mount(&app.email_server)
         .await;
 
@@ -63,6 +66,7 @@ async fn should_return_401_if_incorrect_credentials() {
     });
 
     app.post_signup(&login_body).await;
+    app.verify_user_email(&email).await;
 
     // get the login attempt id
     let login_response = app.post_login(&login_body).await;
@@ -92,7 +96,7 @@ async fn should_return_401_if_old_code() {
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
-        .expect(2) // Expect 2 requests since we call login twice
+        .expect(3) // Expect 3 requests: the verification email on signup and one 2FA code per login
         .mount(&app.email_server)
         .await;
 
@@ -105,6 +109,7 @@ async fn should_return_401_if_old_code() {
     });
 
     app.post_signup(&login_body).await;
+    app.verify_user_email(email.as_ref().expose_secret()).await;
     let login_response = app.post_login(&login_body).await;
     let login_attempt_id = login_response
         .json::<TwoFactorAuthResponse>()
@@ -132,7 +137,7 @@ async fn should_return_401_if_same_code_twice() {
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
-        .expect(2) // Expect 2 requests since we call login twice
+        .expect(3) // Expect 3 requests: the verification email on signup and one 2FA code per login
         .mount(&app.email_server)
         .await;
 
@@ -144,6 +149,7 @@ async fn should_return_401_if_same_code_twice() {
         "requires2FA": true
     });
     app.post_signup(&login_body).await;
+    app.verify_user_email(email.as_ref().expose_secret()).await;
     let login_response = app.post_login(&login_body).await; 
     let login_attempt_id = login_response
         .json::<TwoFactorAuthResponse>()
@@ -171,7 +177,7 @@ async fn should_return_200_if_correct_code() {
     Mock::given(path("/email"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
-        .expect(1)
+        .expect(2) // Expect 2 requests: the verification email on signup and the 2FA code on login
         .mount(&app.email_server)
         .await;
 
@@ -185,6 +191,7 @@ async fn should_return_200_if_correct_code() {
 
     // Sign up and login
     app.post_signup(&login_body).await;
+    app.verify_user_email(email.as_ref().expose_secret()).await;
     let login_response = app.post_login(&login_body).await;
     let login_attempt_id = login_response
         .json::<TwoFactorAuthResponse>()
@@ -218,4 +225,223 @@ async fn should_return_200_if_correct_code() {
     assert!(!auth_cookie.value().is_empty());
 
     app.clean_up().await;
-}
\ No newline at end of file
+}
+
+// Any code other than the one that was sent
+fn wrong_code(code: &TwoFACode) -> String {
+    let code: u32 = code.as_ref().parse().unwrap();
+    format!("{:06}", (code + 1) % 1_000_000)
+}
+
+#[tokio::test]
+async fn should_discard_login_attempt_after_too_many_wrong_codes() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3) // Expect 3 requests: the verification email on signup and one 2FA code per login
+        .mount(&app.email_server)
+        .await;
+
+    let email: Email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let login_body = serde_json::json!({
+        "email": &email.as_ref().expose_secret(),
+        "password": "Password123!",
+        "requires2FA": true
+    });
+    app.post_signup(&login_body).await;
+    app.verify_user_email(email.as_ref().expose_secret()).await;
+
+    let login_attempt_id = app
+        .post_login(&login_body)
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .unwrap()
+        .login_attempt_id;
+    let (_, code) = app.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+
+    let wrong_body = serde_json::json!({
+        "email": &email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id,
+        "2FACode": wrong_code(&code)
+    });
+    for _ in 1..TWO_FA_MAX_FAILED_ATTEMPTS {
+        let response = app.post_verify_2fa(&wrong_body).await;
+        assert_eq!(response.status(), 401);
+    }
+
+    let response = app.post_verify_2fa(&wrong_body).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many incorrect 2FA codes, please log in again".to_owned()
+    );
+
+    // The wrong codes count as failed logins and lock the account
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 429);
+    let user_id = UserId::parse(app.get_user_id(email.as_ref().expose_secret()).await).unwrap();
+    app.login_attempt_store.write().await.reset(&user_id).await.unwrap();
+
+    // Once the lockout has passed, the right code no longer works for the discarded attempt
+    let body = serde_json::json!({
+        "email": &email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code.as_ref()
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status(), 401);
+
+    // But it does for a new attempt
+    let login_attempt_id = app
+        .post_login(&login_body)
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .unwrap()
+        .login_attempt_id;
+    let (_, code) = app.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+    let body = serde_json::json!({
+        "email": &email.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code.as_ref()
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_forget_wrong_codes_after_window() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // The verification email on signup and the 2FA code
+        .mount(&app.email_server)
+        .await;
+
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let login_body = serde_json::json!({
+        "email": &email.as_ref().expose_secret(),
+        "password": "Password123!",
+        "requires2FA": true
+    });
+    app.post_signup(&login_body).await;
+    app.verify_user_email(email.as_ref().expose_secret()).await;
+
+    let login_attempt_id = start_2fa_login(&app, &login_body).await;
+    let (_, code) = app.two_fa_code_store.read().await.get_code(&email).await.unwrap();
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": &email.as_ref().expose_secret(),
+            "loginAttemptId": login_attempt_id,
+            "2FACode": wrong_code(&code)
+        }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    // The hashmap store forgets them after the same window, see its unit tests
+    let ttl: i64 = redis::cmd("TTL")
+        .arg(format!("two_fa_attempts:{}", email.as_ref().expose_secret()))
+        .query(&mut configure_redis())
+        .unwrap();
+    assert!(ttl > 0 && ttl as u64 <= TWO_FA_FAILED_ATTEMPTS_WINDOW_SECONDS);
+    app.clean_up().await;
+}
+
+async fn start_2fa_login(app: &TestApp, login_body: &serde_json::Value) -> String {
+    app.post_login(login_body)
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .unwrap()
+        .login_attempt_id
+}
+
+#[tokio::test]
+async fn should_not_reset_totp_failures_when_logging_in_again() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1) // Only the verification email on signup, TOTP logins send nothing
+        .mount(&app.email_server)
+        .await;
+
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let login_body = serde_json::json!({
+        "email": &email.as_ref().expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    app.post_signup(&login_body).await;
+    app.verify_user_email(email.as_ref().expose_secret()).await;
+    assert_eq!(app.post_login(&login_body).await.status(), 200);
+
+    let enrollment = app
+        .post_totp_enroll(&serde_json::json!({ "password": "Password123!" }))
+        .await
+        .json::<TotpEnrollmentResponse>()
+        .await
+        .unwrap();
+    let totp = TOTP::from_url(&enrollment.otpauth_uri).unwrap();
+    let response = app
+        .post_totp_confirm(&serde_json::json!({
+            "password": "Password123!",
+            "2FACode": totp.generate_current().unwrap()
+        }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let current_code: u32 = totp.generate_current().unwrap().parse().unwrap();
+    let wrong_code = format!("{:06}", (current_code + 500_000) % 1_000_000);
+
+    // Spread the guesses over several login attempts, logging in again each time
+    // the password is correct must not give the next attempt a fresh budget
+    for attempt in 1..=TWO_FA_MAX_FAILED_ATTEMPTS {
+        let login_attempt_id = start_2fa_login(&app, &login_body).await;
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": &email.as_ref().expose_secret(),
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code
+            }))
+            .await;
+        assert_eq!(response.status(), 401);
+        let error = response.json::<ErrorResponse>().await.unwrap().error;
+        if attempt < TWO_FA_MAX_FAILED_ATTEMPTS {
+            assert_eq!(error, "Incorrect credentials");
+        } else {
+            assert_eq!(error, "Too many incorrect 2FA codes, please log in again");
+        }
+    }
+
+    // The wrong codes also count as failed logins and lock the account
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 429);
+
+    // Every further wrong code discards its login attempt straight away
+    let user_id = UserId::parse(app.get_user_id(email.as_ref().expose_secret()).await).unwrap();
+    app.login_attempt_store.write().await.reset(&user_id).await.unwrap();
+    let login_attempt_id = start_2fa_login(&app, &login_body).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": &email.as_ref().expose_secret(),
+            "loginAttemptId": login_attempt_id,
+            "2FACode": wrong_code
+        }))
+        .await;
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Too many incorrect 2FA codes, please log in again"
+    );
+    app.clean_up().await;
+}
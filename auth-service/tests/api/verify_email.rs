@@ -0,0 +1,216 @@
+use auth_service::{domain::EmailVerificationToken, routes::VerifyEmailResponse, ErrorResponse};
+use secrecy::ExposeSecret;
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Creates a user without 2FA, leaving its email unverified, and returns the email
+async fn signup_user(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    email
+}
+
+// Pulls the verification token out of the link in the last email received by the mock Postmark server
+async fn get_verification_token_from_email(app: &TestApp) -> String {
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: serde_json::Value = serde_json::from_slice(&requests.last().expect("No email sent").body)
+        .expect("Failed to parse email request body");
+    let text = body["TextBody"].as_str().expect("No TextBody in email");
+    text.split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in email")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_send_verification_email_on_signup() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+
+    let token = get_verification_token_from_email(&app).await;
+    let stored_email = app
+        .email_verification_token_store
+        .read()
+        .await
+        .get_email(&EmailVerificationToken::parse(token).unwrap())
+        .await
+        .expect("Verification token was not stored");
+    assert_eq!(stored_email.as_ref().expose_secret(), &email);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_verify_email_and_allow_login() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "Password123!",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let token = get_verification_token_from_email(&app).await;
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<VerifyEmailResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyEmailResponse"),
+        VerifyEmailResponse {
+            message: "Email verified".to_owned()
+        }
+    );
+
+    // The link is single use
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        "invalid".to_owned(),
+        EmailVerificationToken::default().as_ref().expose_secret().to_owned(),
+    ];
+
+    for token in test_cases.iter() {
+        let response = app.get_verify_email(token).await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for token: {}", token);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid auth token".to_owned()
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_resend_email_invalid() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_verify_email_resend(&serde_json::json!({ "email": "invalid" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_resend_verification_email_and_invalidate_previous_link() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // Expect 2 requests: the email sent on signup and the resent one
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+    let first_token = get_verification_token_from_email(&app).await;
+
+    let response = app
+        .post_verify_email_resend(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let second_token = get_verification_token_from_email(&app).await;
+    assert_ne!(first_token, second_token);
+
+    let response = app.get_verify_email(&first_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.get_verify_email(&second_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_resent_too_soon() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2) // The throttled resend must not send anything
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+    let body = serde_json::json!({ "email": email });
+
+    let response = app.post_verify_email_resend(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_email_resend(&body).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_resend_if_already_verified() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1) // Only the email sent on signup
+        .mount(&app.email_server)
+        .await;
+
+    let email = signup_user(&app).await;
+    app.verify_user_email(&email).await;
+
+    let response = app
+        .post_verify_email_resend(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
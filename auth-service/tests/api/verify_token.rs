@@ -33,6 +33,7 @@ async fn should_return_200_valid_token() {
     });
 
     let _ = app.post_signup(&body).await; // Will work
+    app.verify_user_email(&random_email).await;
 
     let response = app.post_login(&body).await;
 
@@ -61,6 +62,7 @@ async fn should_return_401_if_invalid_token() {
     });
 
     let _ = app.post_signup(&body).await; // Will work
+    app.verify_user_email(&random_email).await;
 
     let response = app.post_login(&body).await;
     
@@ -103,6 +105,7 @@ async fn should_return_401_if_banned_token() {
     });
 
     let _ = app.post_signup(&body).await; // Will work
+    app.verify_user_email(&random_email).await;
 
     let login_response1 = app.post_login(&body).await;
     
@@ -21,6 +21,10 @@ while IFS= read -r line; do
   fi
 done < <(grep -v '^#' "$ENV_FILE")
 
+# compose.yml signs tokens with this key, create it on the first run
+mkdir -p jwt
+[[ -f jwt/signing_key.pem ]] || openssl genpkey -algorithm ed25519 -out jwt/signing_key.pem
+
 # Run docker-compose commands with exported variables
 docker compose down -v
 docker compose build